@@ -1,6 +1,6 @@
 //! Implements the Actors which are interactive game elements like treasure chests
 //! and monster bosses. Actors are little state machines which are written like
-//! ```
+//! ```text
 //! 000=CREATESELF 87 // speaker, blocker
 //! 000=SETINTEL ID=777 MESSAGEBEARER
 //! 000=SETTEAM ID=777 NPC //  Sewer Gate
@@ -19,11 +19,12 @@
 use crate::{
     body,
     cast::{
-        yield_now, Cond, Response, SharedGameState, WaitFuture, WaitFutureResult,
-        WaitResponseFuture,
+        yield_now, ActorInfo, Cond, DebugPauseFuture, Response, SharedGameState, WaitFuture,
+        WaitFutureResult, WaitResponseFuture,
     },
     data::PropTypeRes,
     data::{ActorRes, PROPS},
+    events::GameEvent,
     game::{Dialog, InvalidDataError, TransactionType},
     js,
     stage::{PetKind, Stage},
@@ -54,6 +55,7 @@ pub(crate) struct Actor {
     compiled: CompiledActions,
     stack: RefCell<VecDeque<u16>>,
     initialized: Cell<bool>,
+    waiting_on: RefCell<Option<String>>,
 
     // Location when the map is first loaded. Not updated as it moves
     x: f64,
@@ -77,6 +79,7 @@ impl Actor {
             compiled,
             stack: RefCell::new(VecDeque::new()),
             initialized: Cell::new(false),
+            waiting_on: RefCell::new(None),
             resume_state,
             dead: Cell::new(false),
             x: res.x,
@@ -104,6 +107,7 @@ impl Actor {
             compiled,
             stack: RefCell::new(VecDeque::new()),
             initialized: Cell::new(false),
+            waiting_on: RefCell::new(None),
             resume_state: None,
             dead: Cell::new(dead),
             x: *x,
@@ -120,7 +124,7 @@ impl Actor {
         } else {
             (self.x, self.y)
         };
-        js::log(&format!(
+        self.stage.console().debug(&format!(
             "for actor {}, dead={}, died={}",
             self.res.id,
             self.dead.get(),
@@ -159,9 +163,39 @@ impl Actor {
     /// Runs the actor state machine
     pub async fn act(&self) {
         if self.dead.get() {
-            js::log(&format!("not running {} because dead", self.res.name));
+            self.stage
+                .console()
+                .debug(&format!("not running {} because dead", self.res.name));
             return;
         }
+        if let Err(e) = self.run().await {
+            self.stage.console().debug(&format!(
+                "{}: Actor error in actor {} state {}: {}",
+                self.res.name,
+                self.res.id,
+                self.compiled.state(),
+                e
+            ));
+            // A broken program would fail the same way every time it ran
+            if e.is_corrupt() {
+                self.dead.set(true);
+                return;
+            }
+        }
+
+        // If the actor finishes, but has a body the player could kill
+        // don't set the actor to dead, it needs to reappear. Otherwise
+        // set the actor dead so it never runs again
+        let actor_body = self.stage.get_body(self.res.id);
+        if actor_body.is_none() || matches!(actor_body.unwrap().prop_id, 64 | 87) {
+            self.dead.set(true);
+            self.stage
+                .console()
+                .debug(&format!("actor {} has died", self.res.id));
+        }
+    }
+
+    async fn run(&self) -> Result<(), ActorError> {
         loop {
             let code = self.compiled.next();
             match code {
@@ -169,22 +203,18 @@ impl Actor {
                     break;
                 }
                 Some(PUSH) => {
-                    let size = self.compiled.next().unwrap();
+                    self.debug_pause().await;
+                    let size = self.compiled.next().ok_or(ActorError::StackUnderflow)?;
                     for _ in 0..size {
-                        self.stack
-                            .borrow_mut()
-                            .push_front(self.compiled.next().unwrap());
+                        let param = self.compiled.next().ok_or(ActorError::StackUnderflow)?;
+                        self.stack.borrow_mut().push_front(param);
                     }
                 }
                 Some(CALL) => {
-                    let action_id = self.compiled.next().unwrap();
-                    let result = self.execute_action(action_id).await;
-                    if let Err(e) = result {
-                        js::log(&format!("{}: Actor error: {}", self.res.name, e));
-                        break;
-                    }
+                    let action_id = self.compiled.next().ok_or(ActorError::StackUnderflow)?;
+                    self.execute_action(action_id).await?;
                 }
-                Some(c) => panic!("Unrecognized code {}", c),
+                Some(c) => return Err(ActorError::UnknownOpcode(c)),
             }
             if !self.initialized.get() && self.compiled.state() != 0 {
                 self.initialized.set(true);
@@ -192,7 +222,9 @@ impl Actor {
                     // don't resume to state 0, we just ran that
                     if state != 0 {
                         self.compiled.set_state(state);
-                        js::log(&format!("{}: jumped to state {}", self.res.name, state));
+                        self.stage
+                            .console()
+                            .debug(&format!("{}: jumped to state {}", self.res.name, state));
                     }
                 }
                 // give every actor a chance to complete initialization
@@ -200,71 +232,120 @@ impl Actor {
                 yield_now().await;
             }
         }
+        Ok(())
+    }
 
-        // If the actor finishes, but has a body the player could kill
-        // don't set the actor to dead, it needs to reappear. Otherwise
-        // set the actor dead so it never runs again
-        let actor_body = self.stage.get_body(self.res.id);
-        if actor_body.is_none() || matches!(actor_body.unwrap().prop_id, 64 | 87) {
-            self.dead.set(true);
-            js::log(&format!("actor {} has died", self.res.id));
+    /// Waits while the debugger has this actor paused, pausing it first if it's at a breakpoint
+    async fn debug_pause(&self) {
+        {
+            let mut game_state = self.game_state.borrow_mut();
+            let state = self.compiled.state();
+            let at_breakpoint = self.compiled.starting_state()
+                && game_state.breakpoints.contains(&(self.res.id, state));
+            if at_breakpoint {
+                self.stage
+                    .console()
+                    .debug(&format!("{}: breakpoint at state {}", self.res.name, state));
+                game_state.paused_actors.insert(self.res.id);
+            }
+            if !game_state.paused_actors.contains(&self.res.id) {
+                return;
+            }
+        }
+
+        *self.waiting_on.borrow_mut() = Some("debugger".to_string());
+        DebugPauseFuture {
+            state: self.game_state.clone(),
+            actor_id: self.res.id,
+        }
+        .await;
+        *self.waiting_on.borrow_mut() = None;
+    }
+
+    pub(crate) fn info(&self) -> ActorInfo {
+        ActorInfo {
+            id: self.res.id,
+            name: self.res.name.clone(),
+            state: self.compiled.state(),
+            waiting_on: self.waiting_on.borrow().clone(),
+            stack: self.stack.borrow().iter().copied().collect(),
+            paused: self
+                .game_state
+                .borrow()
+                .paused_actors
+                .contains(&self.res.id),
         }
     }
 
+    /// Pops the next parameter of the running action
+    fn pop(&self) -> Result<u16, ActorError> {
+        self.stack
+            .borrow_mut()
+            .pop_front()
+            .ok_or(ActorError::StackUnderflow)
+    }
+
     async fn execute_action(&self, action_id: u16) -> Result<(), ActorError> {
         match action_id {
             0x0006 => self.attack().await?,
             0x010a => self.set_intel().await?,
-            0x0206 => self.create_self().await,
+            0x0206 => self.create_self().await?,
             0x0308 => self.give_item().await?,
             0x0408 => self.set_team().await?,
-            0x050e => self.wait().await,
-            0x060e => self.eval_if().await,
-            0x0808 => self.set_state().await,
+            0x050e => self.wait().await?,
+            0x060e => self.eval_if().await?,
+            0x0808 => self.set_state().await?,
             0x0a06 => self.set_message().await?,
             0x0b06 => self.tell_message().await?,
-            0x0c06 => self.add_var().await,
-            0x0d06 => self.sub_var().await,
-            0x0e06 => self.set_var().await,
+            0x0c06 => self.add_var().await?,
+            0x0d06 => self.sub_var().await?,
+            0x0e06 => self.set_var().await?,
             0x0f08 => self.move_actor().await?,
             0x1008 => self.drop_item().await?,
             0x1108 => self.take_item().await?,
             0x1208 => self.set_loc().await?,
             0x1306 => self.add_sell_item().await?,
             0x1406 => self.execute_trade().await?,
-            0x1506 => self.remove().await,
+            0x1506 => self.remove().await?,
             0x1606 => self.freeze().await?,
             0x1706 => self.unfreeze().await?,
             0x1808 => self.give_gold().await?,
             0x1908 => self.give_exp().await?,
             0x1a08 => self.give_henchmen().await?,
             0x1b08 => self.set_health().await?,
-            0x1c08 => self.spawn().await,
+            0x1c08 => self.spawn().await?,
             0x1d08 => self.set_portrait().await?,
             0x1e0a => self.patrol().await?,
             0x1f0a => self.wander().await?,
             0x2008 => self.set_level().await?,
-            0x2106 => self.add_quest_log().await,
-            0x2206 => self.remove_quest_log().await,
+            0x2106 => self.add_quest_log().await?,
+            0x2206 => self.remove_quest_log().await?,
             0x2308 => self.take_gold().await?,
             0x2608 => self.give_quest_pet().await?,
             0x2708 => self.take_pet().await?,
             0x2808 => self.map_set_loc().await?,
-            _ => panic!("Unrecognized action {:X}", action_id),
+            _ => return Err(ActorError::UnknownAction(action_id)),
         }
         Ok(())
     }
 
-    async fn add_quest_log(&self) {
-        let msg_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: add_quest_log({})", self.res.name, msg_id));
+    async fn add_quest_log(&self) -> Result<(), ActorError> {
+        let msg_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: add_quest_log({})", self.res.name, msg_id));
 
-        self.game_state.borrow_mut().quest_log.insert(msg_id);
+        if self.game_state.borrow_mut().quest_log.insert(msg_id) {
+            self.stage.emit(GameEvent::QuestLogAdded { msg_id });
+        }
+        Ok(())
     }
 
     async fn add_sell_item(&self) -> Result<(), ActorError> {
-        let prop_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: add_sell_item({})", self.res.name, prop_id));
+        let prop_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: add_sell_item({})", self.res.name, prop_id));
 
         let body = self
             .stage
@@ -278,24 +359,29 @@ impl Actor {
             .add_sell_item(prop_id)
     }
 
-    async fn add_var(&self) {
-        let param_a = self.stack.borrow_mut().pop_front().unwrap();
+    async fn add_var(&self) -> Result<(), ActorError> {
+        let param_a = self.pop()?;
         let var = (param_a >> 8) as usize;
         let val = (param_a & 0xff) as u8;
 
-        js::log(&format!("{}: add_var({}, {})", self.res.name, var, val));
+        self.stage
+            .console()
+            .debug(&format!("{}: add_var({}, {})", self.res.name, var, val));
 
         let mut game_state = self.game_state.borrow_mut();
-        if game_state.vars[var] <= 255 - val {
-            game_state.vars[var] += val;
-        } else {
-            game_state.vars[var] = 255;
-        }
+        let slot = game_state
+            .vars
+            .get_mut(var)
+            .ok_or(ActorError::VarOutOfRange(var))?;
+        *slot = slot.saturating_add(val);
+        Ok(())
     }
 
     async fn attack(&self) -> Result<(), ActorError> {
-        let actor_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: attack({})", self.res.name, actor_id));
+        let actor_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: attack({})", self.res.name, actor_id));
 
         let attackee = self
             .stage
@@ -309,9 +395,11 @@ impl Actor {
         Ok(())
     }
 
-    async fn create_self(&self) {
-        let prop_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: create_self({})", self.res.name, prop_id));
+    async fn create_self(&self) -> Result<(), ActorError> {
+        let prop_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: create_self({})", self.res.name, prop_id));
 
         let body = self.stage.get_body(self.res.id);
         if body.is_some() {
@@ -334,13 +422,13 @@ impl Actor {
 
         body.equip_default(self.now());
         body.set_portrait(self.res.bmp_offset);
+        Ok(())
     }
 
     async fn drop_item(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let prop_id = stack.pop_front().unwrap() as u16;
-        let actor_id = stack.pop_front().unwrap() as u16;
-        js::log(&format!(
+        let prop_id = self.pop()? as u16;
+        let actor_id = self.pop()? as u16;
+        self.stage.console().debug(&format!(
             "{}: drop_item({}, {})",
             self.res.name, actor_id, prop_id
         ));
@@ -357,30 +445,32 @@ impl Actor {
         Ok(())
     }
 
-    async fn eval_if(&self) {
-        let mut stack = self.stack.borrow_mut();
-        let new_state = stack.pop_front().unwrap();
-        let cond_type = stack.pop_front().unwrap() >> 8;
-        let param_c = stack.pop_front().unwrap();
-        let param_b = stack.pop_front().unwrap();
-        let param_a = stack.pop_front().unwrap();
-        js::log(&format!(
+    async fn eval_if(&self) -> Result<(), ActorError> {
+        let new_state = self.pop()?;
+        let cond_type = self.pop()? >> 8;
+        let param_c = self.pop()?;
+        let param_b = self.pop()?;
+        let param_a = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: eval_if({}, {}, {}, {}, {})",
             self.res.name, cond_type, param_a, param_b, param_c, new_state
         ));
 
-        let cond = Cond::new(cond_type, param_a, param_b);
+        let cond = Cond::new(cond_type, param_a, param_b)?;
         let game_state = &mut self.game_state.borrow_mut();
         if cond.satisfied(&self.stage, game_state) {
             // don't allow resume_state to take affect
             self.initialized.set(true);
             self.compiled.set_state(new_state);
         }
+        Ok(())
     }
 
     async fn execute_trade(&self) -> Result<(), ActorError> {
-        let trade_type = self.stack.borrow_mut().pop_front().unwrap() >> 8;
-        js::log(&format!("{}: execute_trade({})", self.res.name, trade_type));
+        let trade_type = self.pop()? >> 8;
+        self.stage
+            .console()
+            .debug(&format!("{}: execute_trade({})", self.res.name, trade_type));
 
         match trade_type {
             0 => {
@@ -401,14 +491,16 @@ impl Actor {
                 self.dialog
                     .buy_sell(self.stage.get_player(), items, TransactionType::Sell);
             }
-            kind => panic!("Unrecognized trade type {}", kind),
+            kind => return Err(ActorError::TradeTypeNotFound(kind.to_string())),
         }
         Ok(())
     }
 
     async fn freeze(&self) -> Result<(), ActorError> {
-        let actor_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: freeze({})", self.res.name, actor_id));
+        let actor_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: freeze({})", self.res.name, actor_id));
 
         self.stage
             .get_body(actor_id)
@@ -418,10 +510,9 @@ impl Actor {
     }
 
     async fn give_exp(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let exp = stack.pop_front().unwrap() as i32;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let exp = self.pop()? as i32;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: give_exp({}, {})",
             self.res.name, actor_id, exp
         ));
@@ -435,10 +526,9 @@ impl Actor {
     }
 
     async fn give_gold(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let gold = stack.pop_front().unwrap() as i32;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let gold = self.pop()? as i32;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: give_gold({}, {})",
             self.res.name, actor_id, gold
         ));
@@ -451,10 +541,9 @@ impl Actor {
     }
 
     async fn give_henchmen(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let prop_id = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let prop_id = self.pop()?;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: give_henchmen({}, {})",
             self.res.name, actor_id, prop_id
         ));
@@ -465,7 +554,7 @@ impl Actor {
             .ok_or(ActorError::BodyNotFound)?;
 
         let actor_id = Some(self.res.id + 1000);
-        let name = body::pet_name();
+        let name = body::pet_name(&mut self.stage.rng());
         self.stage
             .create_pet(name, prop_id, reciever_body, PetKind::Normal, actor_id);
 
@@ -473,10 +562,9 @@ impl Actor {
     }
 
     async fn give_item(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let prop_id = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let prop_id = self.pop()?;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: give_item({}, {})",
             self.res.name, actor_id, prop_id
         ));
@@ -490,10 +578,9 @@ impl Actor {
     }
 
     async fn give_quest_pet(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let prop_id = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let prop_id = self.pop()?;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: give_henchmen({}, {})",
             self.res.name, actor_id, prop_id
         ));
@@ -515,21 +602,24 @@ impl Actor {
     }
 
     async fn map_set_loc(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let pos = stack.pop_front().unwrap();
-        let map_id = stack.pop_front().unwrap();
+        let pos = self.pop()?;
+        let map_id = self.pop()?;
         let x = (pos >> 8) as f64;
         let y = (pos & 0xff) as f64;
-        js::log(&format!(
+        self.stage.console().debug(&format!(
             "{}: map_set_loc({}, {}, {})",
             self.res.name, map_id, x, y
         ));
 
         if self.stage.map_id() == map_id {
-            js::log(&format!("{}: just setting the body", self.res.name));
+            self.stage
+                .console()
+                .debug(&format!("{}: just setting the body", self.res.name));
             self.stage.set_actor_body_loc(0, x, y);
         } else {
-            js::log(&format!("{}: changing the map", self.res.name));
+            self.stage
+                .console()
+                .debug(&format!("{}: changing the map", self.res.name));
             let mut game_state = self.game_state.borrow_mut();
             game_state.map_change_request = Some((map_id, x, y));
         }
@@ -537,12 +627,11 @@ impl Actor {
     }
 
     async fn move_actor(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let actor_id = stack.pop_front().unwrap();
-        let pos = stack.pop_front().unwrap();
+        let actor_id = self.pop()?;
+        let pos = self.pop()?;
         let x = (pos >> 8) as f64;
         let y = (pos & 0xff) as f64;
-        js::log(&format!(
+        self.stage.console().debug(&format!(
             "{}: move_actor({}, {}, {})",
             self.res.name, actor_id, x, y
         ));
@@ -555,15 +644,14 @@ impl Actor {
     }
 
     async fn patrol(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let pos2 = stack.pop_front().unwrap();
-        let pos1 = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
+        let pos2 = self.pop()?;
+        let pos1 = self.pop()?;
+        let actor_id = self.pop()?;
         let x1 = (pos1 >> 8) as f64;
         let y1 = (pos1 & 0xff) as f64;
         let x2 = (pos2 >> 8) as f64;
         let y2 = (pos2 & 0xff) as f64;
-        js::log(&format!(
+        self.stage.console().debug(&format!(
             "{}: patrol({}, {}, {}, {}, {})",
             self.res.name, actor_id, x1, y1, x2, y2
         ));
@@ -575,26 +663,33 @@ impl Actor {
         Ok(())
     }
 
-    async fn remove_quest_log(&self) {
-        let msg_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: remove({})", self.res.name, msg_id));
+    async fn remove_quest_log(&self) -> Result<(), ActorError> {
+        let msg_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: remove({})", self.res.name, msg_id));
 
-        self.game_state.borrow_mut().quest_log.remove(&msg_id);
+        if self.game_state.borrow_mut().quest_log.remove(&msg_id) {
+            self.stage.emit(GameEvent::QuestLogRemoved { msg_id });
+        }
+        Ok(())
     }
 
-    async fn remove(&self) {
-        let actor_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: remove({})", self.res.name, actor_id,));
+    async fn remove(&self) -> Result<(), ActorError> {
+        let actor_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: remove({})", self.res.name, actor_id,));
 
         self.stage.remove_body(actor_id);
+        Ok(())
     }
 
     async fn set_health(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
         // TODO: this needs to support -1, does it?
-        let health = stack.pop_front().unwrap() as i32;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let health = self.pop()? as i32;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: set_health({}, {})",
             self.res.name, actor_id, health
         ));
@@ -607,14 +702,13 @@ impl Actor {
     }
 
     async fn set_intel(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
         // This function is passed some extra junk it doesn't use.
-        let _junk = stack.pop_front().unwrap();
-        let param = stack.pop_front().unwrap();
+        let _junk = self.pop()?;
+        let param = self.pop()?;
         let intel_type_u8 = (param >> 8) as u8;
         let hostile_to = (param & 0xff) as u8;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: set_intel({}, {}, {})",
             self.res.name, actor_id, intel_type_u8, hostile_to
         ));
@@ -626,16 +720,14 @@ impl Actor {
 
         let intel_type: IntelType = intel_type_u8
             .try_into()
-            .map_err(|_| ActorError::TypeConversion)
-            .unwrap();
+            .map_err(|_| ActorError::TypeConversion)?;
 
         body.set_intel(intel_type);
 
         if hostile_to > 0 {
             let team: Team = hostile_to
                 .try_into()
-                .map_err(|_| ActorError::TypeConversion)
-                .unwrap();
+                .map_err(|_| ActorError::TypeConversion)?;
 
             body.set_enemy(team);
         }
@@ -643,10 +735,9 @@ impl Actor {
     }
 
     async fn set_level(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let level = (stack.pop_front().unwrap() >> 8) as i32;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let level = (self.pop()? >> 8) as i32;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: set_level({}, {})",
             self.res.name, actor_id, level
         ));
@@ -659,12 +750,11 @@ impl Actor {
     }
 
     async fn set_loc(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let actor_id = stack.pop_front().unwrap();
-        let pos = stack.pop_front().unwrap();
+        let actor_id = self.pop()?;
+        let pos = self.pop()?;
         let x = (pos >> 8) as f64;
         let y = (pos & 0xff) as f64;
-        js::log(&format!(
+        self.stage.console().debug(&format!(
             "{}: set_loc({}, {}, {})",
             self.res.name, actor_id, x, y
         ));
@@ -674,8 +764,10 @@ impl Actor {
     }
 
     async fn set_message(&self) -> Result<(), ActorError> {
-        let msg_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: set_message({})", self.res.name, msg_id));
+        let msg_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: set_message({})", self.res.name, msg_id));
 
         let body = self
             .stage
@@ -692,10 +784,9 @@ impl Actor {
     }
 
     async fn set_portrait(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let portrait_id = stack.pop_front().unwrap() as u16;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let portrait_id = self.pop()? as u16;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: set_portrait({}, {})",
             self.res.name, actor_id, portrait_id
         ));
@@ -707,11 +798,10 @@ impl Actor {
         Ok(())
     }
 
-    async fn set_state(&self) {
-        let mut stack = self.stack.borrow_mut();
-        let state = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+    async fn set_state(&self) -> Result<(), ActorError> {
+        let state = self.pop()?;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: set_state({}, {})",
             self.res.name, actor_id, state
         ));
@@ -729,13 +819,13 @@ impl Actor {
                 .state_interrupt_by_actor
                 .insert(actor_id, state);
         }
+        Ok(())
     }
 
     async fn set_team(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let team_u8 = (stack.pop_front().unwrap() >> 8) as u8;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let team_u8 = (self.pop()? >> 8) as u8;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: set_team({}, {})",
             self.res.name, actor_id, team_u8
         ));
@@ -745,31 +835,35 @@ impl Actor {
             .get_body(actor_id)
             .ok_or(ActorError::BodyNotFound)?;
 
-        let team: Team = team_u8
-            .try_into()
-            .map_err(|_| ActorError::TypeConversion)
-            .unwrap();
+        let team: Team = team_u8.try_into().map_err(|_| ActorError::TypeConversion)?;
 
         body.set_team(team);
         Ok(())
     }
 
-    async fn set_var(&self) {
-        let param_a = self.stack.borrow_mut().pop_front().unwrap();
+    async fn set_var(&self) -> Result<(), ActorError> {
+        let param_a = self.pop()?;
         let var = (param_a >> 8) as usize;
         let val = (param_a & 0xff) as u8;
-        js::log(&format!("{}: set_var({}, {})", self.res.name, var, val));
-
-        self.game_state.borrow_mut().vars[var] = val;
+        self.stage
+            .console()
+            .debug(&format!("{}: set_var({}, {})", self.res.name, var, val));
+
+        *self
+            .game_state
+            .borrow_mut()
+            .vars
+            .get_mut(var)
+            .ok_or(ActorError::VarOutOfRange(var))? = val;
+        Ok(())
     }
 
-    async fn spawn(&self) {
-        let mut stack = self.stack.borrow_mut();
-        let position = stack.pop_front().unwrap();
+    async fn spawn(&self) -> Result<(), ActorError> {
+        let position = self.pop()?;
         let x = (position >> 8) as f64;
         let y = (position & 0xff) as f64;
-        let prop_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let prop_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: spawn({}, {}, {})",
             self.res.name, prop_id, x, y
         ));
@@ -789,27 +883,30 @@ impl Actor {
         if self.compiled.state() != 0 {
             body.persist();
         }
+        Ok(())
     }
 
-    async fn sub_var(&self) {
-        let param_a = self.stack.borrow_mut().pop_front().unwrap();
+    async fn sub_var(&self) -> Result<(), ActorError> {
+        let param_a = self.pop()?;
         let var = (param_a >> 8) as usize;
         let val = (param_a & 0xff) as u8;
-        js::log(&format!("{}: sub_var({}, {})", self.res.name, var, val));
+        self.stage
+            .console()
+            .debug(&format!("{}: sub_var({}, {})", self.res.name, var, val));
 
         let mut game_state = self.game_state.borrow_mut();
-        if game_state.vars[var] > val {
-            game_state.vars[var] -= val;
-        } else {
-            game_state.vars[var] = 0;
-        }
+        let slot = game_state
+            .vars
+            .get_mut(var)
+            .ok_or(ActorError::VarOutOfRange(var))?;
+        *slot = slot.saturating_sub(val);
+        Ok(())
     }
 
     async fn take_gold(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let gold = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let gold = self.pop()?;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: take_gold({}, {})",
             self.res.name, actor_id, gold
         ));
@@ -822,10 +919,9 @@ impl Actor {
     }
 
     async fn take_item(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let prop_id = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let prop_id = self.pop()?;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: take_item({}, {})",
             self.res.name, actor_id, prop_id
         ));
@@ -838,10 +934,9 @@ impl Actor {
     }
 
     async fn take_pet(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let pet_type = stack.pop_front().unwrap() >> 8;
-        let actor_id = stack.pop_front().unwrap();
-        js::log(&format!(
+        let pet_type = self.pop()? >> 8;
+        let actor_id = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: take_pet({}, {})",
             self.res.name, actor_id, pet_type
         ));
@@ -856,7 +951,7 @@ impl Actor {
             0 => body.quest_pet.take(),
             1 => body.summoned_pet.take(),
             2 => body.pet.take(),
-            _ => panic!("Unrecognized pet type: {}", pet_type),
+            _ => return Err(ActorError::PetTypeNotFound(pet_type.to_string())),
         };
         let Some(pet) = pet else {
             return Err(ActorError::PetTypeNotFound(pet_type.to_string()));
@@ -868,8 +963,10 @@ impl Actor {
     }
 
     async fn tell_message(&self) -> Result<(), ActorError> {
-        let msg_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: tell_message({})", self.res.name, msg_id));
+        let msg_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: tell_message({})", self.res.name, msg_id));
         let body = self
             .stage
             .get_body(self.res.id)
@@ -885,8 +982,10 @@ impl Actor {
     }
 
     async fn unfreeze(&self) -> Result<(), ActorError> {
-        let actor_id = self.stack.borrow_mut().pop_front().unwrap();
-        js::log(&format!("{}: unfreeze({})", self.res.name, actor_id));
+        let actor_id = self.pop()?;
+        self.stage
+            .console()
+            .debug(&format!("{}: unfreeze({})", self.res.name, actor_id));
 
         self.stage
             .get_body(actor_id)
@@ -895,24 +994,25 @@ impl Actor {
         Ok(())
     }
 
-    async fn wait(&self) {
-        let mut stack = self.stack.borrow_mut();
-        let _junk = stack.pop_front().unwrap();
-        let cond_type = stack.pop_front().unwrap() >> 8;
-        let param_c = stack.pop_front().unwrap();
-        let param_b = stack.pop_front().unwrap();
-        let param_a = stack.pop_front().unwrap();
-        js::log(&format!(
+    async fn wait(&self) -> Result<(), ActorError> {
+        let _junk = self.pop()?;
+        let cond_type = self.pop()? >> 8;
+        let param_c = self.pop()?;
+        let param_b = self.pop()?;
+        let param_a = self.pop()?;
+        self.stage.console().debug(&format!(
             "{}: wait({}, {}, {}, {})",
             self.res.name, cond_type, param_a, param_b, param_c
         ));
 
         if cond_type == 3 {
+            *self.waiting_on.borrow_mut() = Some("response".to_string());
             let response = WaitResponseFuture {
                 state: self.game_state.clone(),
                 actor_id: self.res.id,
             }
             .await;
+            *self.waiting_on.borrow_mut() = None;
             match response {
                 Response::A => self.compiled.set_state(param_a),
                 Response::B => self.compiled.set_state(param_b),
@@ -923,10 +1023,13 @@ impl Actor {
                     self.compiled.set_state(state);
                 }
             }
-            return;
+            return Ok(());
         }
-        let cond = Cond::new(cond_type, param_a, param_b);
-        js::log(&format!("{}: condition: {:?}", self.res.name, cond));
+        let cond = Cond::new(cond_type, param_a, param_b)?;
+        self.stage
+            .console()
+            .debug(&format!("{}: condition: {:?}", self.res.name, cond));
+        *self.waiting_on.borrow_mut() = Some(format!("{:?}", cond));
         let result = WaitFuture {
             actor_id: self.res.id,
             cond,
@@ -934,7 +1037,10 @@ impl Actor {
             state: self.game_state.clone(),
         }
         .await;
-        js::log(&format!("{}: condition: met!", self.res.name));
+        *self.waiting_on.borrow_mut() = None;
+        self.stage
+            .console()
+            .debug(&format!("{}: condition: met!", self.res.name));
         match result {
             WaitFutureResult::Ok => {}
             WaitFutureResult::StateInterrupt(state) => {
@@ -943,18 +1049,18 @@ impl Actor {
                 self.compiled.set_state(state);
             }
         }
+        Ok(())
     }
 
     async fn wander(&self) -> Result<(), ActorError> {
-        let mut stack = self.stack.borrow_mut();
-        let pos2 = stack.pop_front().unwrap();
-        let pos1 = stack.pop_front().unwrap();
-        let actor_id = stack.pop_front().unwrap();
+        let pos2 = self.pop()?;
+        let pos1 = self.pop()?;
+        let actor_id = self.pop()?;
         let x1 = (pos1 >> 8) as f64;
         let y1 = (pos1 & 0xff) as f64;
         let x2 = (pos2 >> 8) as f64;
         let y2 = (pos2 & 0xff) as f64;
-        js::log(&format!(
+        self.stage.console().debug(&format!(
             "{}: wander({}, {}, {}, {}, {})",
             self.res.name, actor_id, x1, y1, x2, y2
         ));
@@ -976,34 +1082,42 @@ struct CompiledActions {
 }
 
 impl CompiledActions {
+    /// Broken byte code compiles to a program that fails when it gets to the broken action: an
+    /// unknown action is called as is, and a truncated action is called with the parameters
+    /// that are there.
     fn new(action_str: &str) -> Self {
-        let actions_bin = general_purpose::STANDARD.decode(action_str).unwrap();
+        let actions_bin = general_purpose::STANDARD
+            .decode(action_str)
+            .unwrap_or_else(|err| {
+                js::log(&format!("actor byte code is not base64: {}", err));
+                Vec::new()
+            });
         let mut iter = actions_bin.iter();
 
         let mut all_actions: Vec<Vec<u16>> = vec![vec![]];
         let mut states: Vec<u16> = vec![0];
         let mut last_state: u16 = 0;
 
-        loop {
-            let wrapped_state = next_u16(&mut iter);
-            if let None = wrapped_state {
+        while let Some(state) = next_u16(&mut iter) {
+            let Some(op) = next_u16(&mut iter) else {
                 break;
-            }
-            let state = wrapped_state.unwrap();
-            let op = next_u16(&mut iter).unwrap();
-            let size = ACTION_SIZE[&op];
+            };
 
             if last_state != state {
                 all_actions.push(Vec::new());
                 states.push(state);
             }
             let actions = all_actions.last_mut().unwrap();
+            let Some(size) = ACTION_SIZE.get(&op) else {
+                // the size of the parameters is unknown so nothing after can be read
+                actions.push(CALL);
+                actions.push(op);
+                break;
+            };
+            let params: Vec<u16> = (0..*size).map_while(|_| next_u16(&mut iter)).collect();
             actions.push(PUSH);
-            actions.push(size);
-            for _ in 0..size {
-                let param = next_u16(&mut iter).unwrap();
-                actions.push(param);
-            }
+            actions.push(params.len() as u16);
+            actions.extend(params);
             actions.push(CALL);
             actions.push(op);
             last_state = state;
@@ -1032,6 +1146,11 @@ impl CompiledActions {
         self.action_idx.set(0);
     }
 
+    /// Whether the last code returned by `next` was the first of its state
+    fn starting_state(&self) -> bool {
+        self.action_idx.get() == 1
+    }
+
     fn next(&self) -> Option<u16> {
         if self.state_idx.get() >= self.states.len() {
             return None;
@@ -1049,7 +1168,7 @@ impl CompiledActions {
 }
 
 /// A map of action ids to the number of parameters it accepts (each parameter is a u16)
-static ACTION_SIZE: Lazy<HashMap<u16, u16>> = Lazy::new(|| {
+pub(crate) static ACTION_SIZE: Lazy<HashMap<u16, u16>> = Lazy::new(|| {
     HashMap::from([
         (0x0006, 1), // attack
         (0x010a, 3), // set_intel
@@ -1102,6 +1221,26 @@ pub enum ActorError {
     IntelNotFound,
     TypeConversion,
     PetTypeNotFound(String),
+    TradeTypeNotFound(String),
+    UnknownOpcode(u16),
+    UnknownAction(u16),
+    StackUnderflow,
+    BadCondition(String),
+    VarOutOfRange(usize),
+}
+
+impl ActorError {
+    /// Whether the error comes from the actor's byte code rather than the state of the game
+    pub fn is_corrupt(&self) -> bool {
+        matches!(
+            self,
+            ActorError::UnknownOpcode(_)
+                | ActorError::UnknownAction(_)
+                | ActorError::StackUnderflow
+                | ActorError::BadCondition(_)
+                | ActorError::VarOutOfRange(_)
+        )
+    }
 }
 
 impl Error for ActorError {}
@@ -1114,6 +1253,12 @@ impl fmt::Display for ActorError {
             ActorError::IntelNotFound => write!(f, "Intel not found"),
             ActorError::TypeConversion => write!(f, "Type conversion"),
             ActorError::PetTypeNotFound(msg) => write!(f, "Pet type not found: {}", msg),
+            ActorError::TradeTypeNotFound(msg) => write!(f, "Trade type not found: {}", msg),
+            ActorError::UnknownOpcode(code) => write!(f, "Unknown opcode {}", code),
+            ActorError::UnknownAction(id) => write!(f, "Unknown action {:#06x}", id),
+            ActorError::StackUnderflow => write!(f, "Stack underflow"),
+            ActorError::BadCondition(msg) => write!(f, "Bad condition: {}", msg),
+            ActorError::VarOutOfRange(var) => write!(f, "Var {} out of range", var),
         }
     }
 }
@@ -0,0 +1,391 @@
+//! Fights a player build against a creature many times over, for tuning the numbers in `stats`
+//! and props.json. Fights go through the same `make_attack` and `take_attack` as the game, so
+//! armor class, crits, procs and resistances all count. There's no walking, sneaking or
+//! regeneration, and missiles land the moment they're fired.
+use crate::{
+    body::Body,
+    combat::{make_attack, Attack, BattleEventType, MissileEffect},
+    console::Console,
+    data::{PropTypeRes, PROPS},
+    game::InvalidDataError,
+    thrift::save::{ClassType, Team},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::rc::Rc;
+
+/// How often bodies update between attacks, the same as a frame in the game
+const TICK: f64 = 2000.0 / 30.0;
+
+/// Fights that last longer than this (10 minutes) are called a draw
+const MAX_FIGHT_TIME: f64 = 600_000.0;
+
+const PLAYER_PROP: u16 = 55;
+
+/// A player character to fight with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Build {
+    pub class: ClassType,
+    pub level: i32,
+    pub strength: i32,
+    pub dexterity: i32,
+    pub vitality: i32,
+    pub intelligence: i32,
+    pub wisdom: i32,
+    pub luck: i32,
+    pub weapon: Option<u16>,
+    pub armor: Vec<u16>,
+}
+
+impl Default for Build {
+    /// A level 1 fighter with all stats at 10 and nothing equipped
+    fn default() -> Self {
+        Self {
+            class: ClassType::FIGHTER,
+            level: 1,
+            strength: 10,
+            dexterity: 10,
+            vitality: 10,
+            intelligence: 10,
+            wisdom: 10,
+            luck: 10,
+            weapon: None,
+            armor: Vec::new(),
+        }
+    }
+}
+
+/// What one side of the fights did, summed over all of them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SideStats {
+    pub attacks: u32,
+    pub hits: u32,
+    pub crits: u32,
+    pub misses: u32,
+    pub fizzles: u32,
+    /// Health taken from the other side, after resistances and including procs and poison
+    pub damage: i64,
+}
+
+impl SideStats {
+    /// Percent of attacks that were `count`
+    fn percent(&self, count: u32) -> f64 {
+        if self.attacks == 0 {
+            return 0.0;
+        }
+        100.0 * count as f64 / self.attacks as f64
+    }
+
+    pub fn hit_percent(&self) -> f64 {
+        self.percent(self.hits)
+    }
+
+    pub fn crit_percent(&self) -> f64 {
+        self.percent(self.crits)
+    }
+
+    pub fn miss_percent(&self) -> f64 {
+        self.percent(self.misses)
+    }
+
+    pub fn fizzle_percent(&self) -> f64 {
+        self.percent(self.fizzles)
+    }
+
+    fn count(&mut self, event: Option<BattleEventType>) {
+        self.attacks += 1;
+        match event {
+            Some(BattleEventType::Hit) => self.hits += 1,
+            Some(BattleEventType::Crit) => self.crits += 1,
+            Some(BattleEventType::Miss) => self.misses += 1,
+            // Ranged spells that fizzle don't make a missile so there's no event
+            None | Some(BattleEventType::Fizzle) => self.fizzles += 1,
+            Some(_) => {}
+        }
+    }
+}
+
+/// The result of `simulate`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub fights: u32,
+    pub wins: u32,
+    pub losses: u32,
+    /// Fights where nobody died in time
+    pub draws: u32,
+    /// Game time in ms over all fights
+    pub time: f64,
+    /// Game time in ms over the fights the player won
+    pub win_time: f64,
+    pub player: SideStats,
+    pub creature: SideStats,
+}
+
+impl Report {
+    pub fn win_percent(&self) -> f64 {
+        if self.fights == 0 {
+            return 0.0;
+        }
+        100.0 * self.wins as f64 / self.fights as f64
+    }
+
+    /// Mean seconds for the player to kill the creature, if they ever did
+    pub fn time_to_kill(&self) -> Option<f64> {
+        if self.wins == 0 {
+            return None;
+        }
+        Some(self.win_time / 1000.0 / self.wins as f64)
+    }
+
+    fn dps(&self, side: &SideStats) -> f64 {
+        if self.time == 0.0 {
+            return 0.0;
+        }
+        side.damage as f64 / (self.time / 1000.0)
+    }
+
+    /// Damage per second the player does while fighting
+    pub fn player_dps(&self) -> f64 {
+        self.dps(&self.player)
+    }
+
+    /// Damage per second the creature does while fighting
+    pub fn creature_dps(&self) -> f64 {
+        self.dps(&self.creature)
+    }
+}
+
+enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// Fights `build` against the creature `prop_id` at `level` `fights` times. Runs with the same
+/// `seed` play out the same way.
+pub fn simulate(
+    build: &Build,
+    prop_id: u16,
+    level: i32,
+    fights: u32,
+    seed: u64,
+) -> Result<Report, InvalidDataError> {
+    check_build(build)?;
+    match PROPS.get(&prop_id.to_string()).map(|prop| &prop.kind) {
+        Some(PropTypeRes::Creature { .. } | PropTypeRes::User { .. }) => {}
+        _ => {
+            return Err(InvalidDataError::new(&format!(
+                "{} is not a creature",
+                prop_id
+            )))
+        }
+    }
+    if level < 1 {
+        return Err(InvalidDataError::new("level must be at least 1"));
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut report = Report::default();
+    for _ in 0..fights {
+        let (outcome, time) = fight(build, prop_id, level, &mut report, &mut rng);
+        report.fights += 1;
+        report.time += time;
+        match outcome {
+            Outcome::Win => {
+                report.wins += 1;
+                report.win_time += time;
+            }
+            Outcome::Loss => report.losses += 1,
+            Outcome::Draw => report.draws += 1,
+        }
+    }
+    Ok(report)
+}
+
+fn check_build(build: &Build) -> Result<(), InvalidDataError> {
+    if build.level < 1 {
+        return Err(InvalidDataError::new("level must be at least 1"));
+    }
+    if let Some(weapon) = build.weapon {
+        match PROPS.get(&weapon.to_string()).map(|prop| &prop.kind) {
+            Some(PropTypeRes::Weapon { .. }) => {}
+            _ => {
+                return Err(InvalidDataError::new(&format!(
+                    "{} is not a weapon",
+                    weapon
+                )))
+            }
+        }
+    }
+    for armor in &build.armor {
+        match PROPS.get(&armor.to_string()).map(|prop| &prop.kind) {
+            Some(PropTypeRes::Armor { .. }) => {}
+            _ => return Err(InvalidDataError::new(&format!("{} is not armor", armor))),
+        }
+    }
+    Ok(())
+}
+
+fn player(build: &Build, console: &Console) -> Rc<Body> {
+    let player = Rc::new(Body::new(
+        "Player".to_string(),
+        Some(0),
+        PLAYER_PROP,
+        0.0,
+        0.0,
+    ));
+    player.set_console(console);
+    player.set_team(Team::PLAYER);
+    player.base_str.set(build.strength);
+    player.base_dex.set(build.dexterity);
+    player.base_vit.set(build.vitality);
+    player.base_int.set(build.intelligence);
+    player.base_wis.set(build.wisdom);
+    player.base_luck.set(build.luck);
+    player.class.set(build.class);
+    // Equipment is forced on, designers may want to try builds the game wouldn't allow
+    for prop_id in build.weapon.iter().chain(build.armor.iter()) {
+        let item = player.give_item(*prop_id);
+        player.force_equip(0.0, item);
+    }
+    // After equipping so items that raise vitality count
+    player.set_level(build.level);
+    player
+}
+
+fn creature(prop_id: u16, level: i32, console: &Console) -> Rc<Body> {
+    let name = PROPS[&prop_id.to_string()].name.clone();
+    let creature = Rc::new(Body::new(name, None, prop_id, 1.0, 0.0));
+    creature.set_console(console);
+    creature.set_team(Team::ENEMY);
+    creature.equip_default(0.0);
+    creature.set_level(level);
+    creature
+}
+
+/// One fight to the death, returning how it ended and how long it took
+fn fight(
+    build: &Build,
+    prop_id: u16,
+    level: i32,
+    report: &mut Report,
+    rng: &mut StdRng,
+) -> (Outcome, f64) {
+    let console = Console::new(1);
+    let player = player(build, &console);
+    let creature = creature(prop_id, level, &console);
+
+    // Both sides swing as soon as the fight starts, the player first. Without a weapon there's
+    // no attacking at all, like in the game.
+    let mut player_next = player.attack_delay().map(|_| 0.0);
+    let mut creature_next = creature.attack_delay().map(|_| 0.0);
+    let mut now = 0.0;
+    loop {
+        if player.get_health() == 0 {
+            return (Outcome::Loss, now);
+        }
+        if creature.get_health() == 0 {
+            return (Outcome::Win, now);
+        }
+        if now > MAX_FIGHT_TIME {
+            return (Outcome::Draw, now);
+        }
+        if let Some(next) = player_next.filter(|next| *next <= now) {
+            let event = swing(now, &player, &creature, report, rng);
+            report.player.count(event);
+            player_next = player.attack_delay().map(|delay| next + TICK * delay);
+        }
+        if creature.get_health() == 0 {
+            continue;
+        }
+        if let Some(next) = creature_next.filter(|next| *next <= now) {
+            let event = swing(now, &creature, &player, report, rng);
+            report.creature.count(event);
+            creature_next = creature.attack_delay().map(|delay| next + TICK * delay);
+        }
+        now += TICK;
+        let (player_health, creature_health) = (player.get_health(), creature.get_health());
+        player.update(now, rng);
+        creature.update(now, rng);
+        report.player.damage += (creature_health - creature.get_health()).max(0) as i64;
+        report.creature.damage += (player_health - player.get_health()).max(0) as i64;
+    }
+}
+
+/// One attack from `attacker`, returning what happened to the weapon's strike
+fn swing(
+    now: f64,
+    attacker: &Rc<Body>,
+    target: &Rc<Body>,
+    report: &mut Report,
+    rng: &mut StdRng,
+) -> Option<BattleEventType> {
+    let before = (attacker.get_health(), target.get_health());
+    let console = attacker.console();
+    let mut event = None;
+    for attack in make_attack(now, attacker.clone(), target.clone(), rng, &console) {
+        match attack {
+            Attack::Melee(strike) => {
+                event.get_or_insert(strike.event);
+                apply(now, attacker, target, &MissileEffect::Strike(strike));
+            }
+            Attack::Range(missile) => {
+                for effect in missile.effects() {
+                    if let MissileEffect::Strike(strike) = effect {
+                        event.get_or_insert(strike.event);
+                    }
+                    apply(now, attacker, target, effect);
+                }
+            }
+        }
+    }
+    let (attacker_health, target_health) = (attacker.get_health(), target.get_health());
+    let (attacker_side, target_side) = if attacker.is_player() {
+        (&mut report.player, &mut report.creature)
+    } else {
+        (&mut report.creature, &mut report.player)
+    };
+    attacker_side.damage += (before.1 - target_health).max(0) as i64;
+    target_side.damage += (before.0 - attacker_health).max(0) as i64;
+    event
+}
+
+/// Applies an attack's effect as if it landed on `target`. There's nobody else around, so splash
+/// and explosions only hit `target` and corpse spells do nothing.
+fn apply(now: f64, attacker: &Rc<Body>, target: &Rc<Body>, effect: &MissileEffect) {
+    match effect {
+        MissileEffect::Strike(strike) => strike.target.take_attack(
+            now,
+            strike.event,
+            strike.damage,
+            strike.damage_type,
+            Some(attacker.clone()),
+        ),
+        MissileEffect::SplashDamage {
+            amount,
+            damage_type,
+        } => target.take_attack(
+            now,
+            BattleEventType::Hit,
+            *amount,
+            *damage_type,
+            Some(attacker.clone()),
+        ),
+        MissileEffect::Explosion {
+            splash_damage,
+            splash_kind,
+            ..
+        } => target.take_attack(
+            now,
+            BattleEventType::Hit,
+            *splash_damage,
+            splash_kind.damage_type(),
+            Some(attacker.clone()),
+        ),
+        MissileEffect::Heal { target, amount, .. } => target.heal(*amount),
+        MissileEffect::Condition { target, condition } => target.add_condition(condition.clone()),
+        MissileEffect::CurePoison { target } => target.cure_poison(),
+        MissileEffect::Spell { .. }
+        | MissileEffect::AnimateDead
+        | MissileEffect::DetonateCorpse { .. } => {}
+    }
+}
@@ -0,0 +1,153 @@
+//! Disassembles the actors in maps.json into text and assembles edited text back into byte code,
+//! so quests can be written and patched without hex editing. See `aldonlib::bytecode` for the
+//! syntax.
+//!
+//! Usage: aldon-actors disasm [--maps FILE] [MAP_ID [ACTOR_ID]]
+//!        aldon-actors asm FILE
+//!
+//! `disasm` prints every actor (or just those on MAP_ID) from the built in maps.json, or from
+//! FILE. Each actor starts with a header line naming the map and actor:
+//! ```text
+//! @actor 2 48 Locked Chest
+//! 000=CREATESELF 64 // speaker, nonblocker
+//! ```
+//! `asm` reads the same format back and prints `<map id> <actor id> <base64>` for each actor,
+//! ready to be pasted into the actor's `actions`. A file without any header is assembled as a
+//! single actor and only the base64 is printed. Lines starting with '#' are ignored.
+use aldonlib::{
+    bytecode,
+    data::{WorldRes, WORLD},
+};
+use std::{env, error::Error, fmt, fs, process};
+
+const USAGE: &str = "usage: aldon-actors disasm [--maps FILE] [MAP_ID [ACTOR_ID]]
+       aldon-actors asm FILE";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("disasm") => disasm(&args[1..]),
+        Some("asm") => asm(&args[1..]),
+        _ => Err(Box::new(CliError::new("expected disasm or asm")) as Box<dyn Error>),
+    };
+    if let Err(err) = result {
+        eprintln!("aldon-actors: {}\n{}", err, USAGE);
+        process::exit(1);
+    }
+}
+
+fn disasm(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut maps_file = None;
+    let mut ids = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--maps" => maps_file = Some(args.next().ok_or(CliError::new("--maps needs a file"))?),
+            _ => ids.push(
+                arg.parse::<u16>()
+                    .map_err(|_| CliError::new(&format!("'{}' is not an id", arg)))?,
+            ),
+        }
+    }
+    let loaded: WorldRes;
+    let world = match maps_file {
+        Some(path) => {
+            loaded = serde_json::from_str(&fs::read_to_string(path)?)?;
+            &loaded
+        }
+        None => &*WORLD,
+    };
+
+    let mut map_ids: Vec<u16> = world
+        .maps
+        .keys()
+        .map(|id| id.parse())
+        .collect::<Result<_, _>>()?;
+    map_ids.sort();
+    for map_id in map_ids {
+        if ids.first().is_some_and(|id| *id != map_id) {
+            continue;
+        }
+        let map = &world.maps[&map_id.to_string()];
+        for actor in map.actors.iter() {
+            if ids.get(1).is_some_and(|id| *id != actor.id) {
+                continue;
+            }
+            let Some(actions) = &actor.actions else {
+                continue;
+            };
+            let text = bytecode::disassemble(actions).map_err(|err| {
+                CliError::new(&format!("map {} actor {}: {}", map_id, actor.id, err))
+            })?;
+            println!("@actor {} {} {}", map_id, actor.id, actor.name);
+            println!("{}", text);
+        }
+    }
+    Ok(())
+}
+
+fn asm(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = match args {
+        [path] => path,
+        _ => return Err(Box::new(CliError::new("asm takes one FILE"))),
+    };
+    let text = fs::read_to_string(path)?;
+
+    // (header, text) for each actor in the file. Each section's text is padded with blank lines
+    // so errors have the line number in the file.
+    let mut sections: Vec<(Option<String>, String)> = vec![(None, String::new())];
+    for (i, line) in text.lines().enumerate() {
+        if let Some(header) = line.strip_prefix("@actor") {
+            sections.push((Some(header.trim().to_string()), "\n".repeat(i + 1)));
+            continue;
+        }
+        let line = if line.trim_start().starts_with('#') {
+            ""
+        } else {
+            line
+        };
+        let section = sections.last_mut().unwrap();
+        section.1.push_str(line);
+        section.1.push('\n');
+    }
+    if sections[0].1.trim().is_empty() {
+        sections.remove(0);
+    }
+
+    for (header, text) in sections {
+        let actions = bytecode::assemble(&text)?;
+        match header {
+            Some(header) => {
+                let mut words = header.split_whitespace();
+                let map_id = words.next().ok_or(CliError::new("@actor needs a map id"))?;
+                let actor_id = words
+                    .next()
+                    .ok_or(CliError::new("@actor needs an actor id"))?;
+                println!("{} {} {}", map_id, actor_id, actions);
+            }
+            None => println!("{}", actions),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct CliError {
+    message: String,
+}
+
+impl CliError {
+    fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CliError {}
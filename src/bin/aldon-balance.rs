@@ -0,0 +1,158 @@
+//! Fights a player build against a creature thousands of times and prints how it went, for
+//! balancing the numbers in stats.rs and props.json.
+//!
+//! Usage: aldon-balance [--class CLASS] [--level N] [--str N] [--dex N] [--vit N] [--int N]
+//!                      [--wis N] [--luck N] [--weapon PROP] [--armor PROP]... [--fights N]
+//!                      [--seed N] CREATURE [LEVEL]
+//!
+//! CREATURE is a creature prop id, fought at LEVEL (1 by default). The player is a level 1
+//! fighter with 10 in every stat unless told otherwise. CLASS is fighter, spellcaster, priest,
+//! thief or journeyman. `--armor` can be given more than once. 1000 fights are run by default,
+//! and runs with the same --seed play out the same way.
+//!
+//! e.g. `aldon-balance --level 3 --str 14 --weapon 1 --armor 25 --armor 23 29 2`
+use aldonlib::{
+    balance::{self, Build, Report, SideStats},
+    data::PROPS,
+    thrift::save::ClassType,
+};
+use std::{env, error::Error, process, str::FromStr};
+
+const USAGE: &str = "usage: aldon-balance [--class CLASS] [--level N] [--str N] [--dex N] \
+                     [--vit N] [--int N] [--wis N] [--luck N] [--weapon PROP] [--armor PROP]... \
+                     [--fights N] [--seed N] CREATURE [LEVEL]";
+
+struct Args {
+    build: Build,
+    creature: u16,
+    level: i32,
+    fights: u32,
+    seed: u64,
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|args| {
+        let report = balance::simulate(
+            &args.build,
+            args.creature,
+            args.level,
+            args.fights,
+            args.seed,
+        )?;
+        Ok((args, report))
+    });
+    match result {
+        Ok((args, report)) => print_report(&args, &report),
+        Err(err) => {
+            eprintln!("aldon-balance: {}\n{}", err, USAGE);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, Box<dyn Error>> {
+    let mut build = Build::default();
+    let mut fights = 1000;
+    let mut seed = 0;
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--class" => {
+                build.class = match args.next().as_deref() {
+                    Some("fighter") => ClassType::FIGHTER,
+                    Some("spellcaster") => ClassType::SPELLCASTER,
+                    Some("priest") => ClassType::PRIEST,
+                    Some("thief") => ClassType::THIEF,
+                    Some("journeyman") => ClassType::JOURNEYMAN,
+                    _ => {
+                        return Err(
+                            "--class must be fighter, spellcaster, priest, thief or journeyman"
+                                .into(),
+                        )
+                    }
+                }
+            }
+            "--level" => build.level = parse_number(&arg, args.next())?,
+            "--str" => build.strength = parse_number(&arg, args.next())?,
+            "--dex" => build.dexterity = parse_number(&arg, args.next())?,
+            "--vit" => build.vitality = parse_number(&arg, args.next())?,
+            "--int" => build.intelligence = parse_number(&arg, args.next())?,
+            "--wis" => build.wisdom = parse_number(&arg, args.next())?,
+            "--luck" => build.luck = parse_number(&arg, args.next())?,
+            "--weapon" => build.weapon = Some(parse_number(&arg, args.next())?),
+            "--armor" => build.armor.push(parse_number(&arg, args.next())?),
+            "--fights" => fights = parse_number(&arg, args.next())?,
+            "--seed" => seed = parse_number(&arg, args.next())?,
+            _ if arg.starts_with("--") => return Err(format!("unknown flag {}", arg).into()),
+            _ => positional.push(arg),
+        }
+    }
+    let (creature, level) = match positional.as_slice() {
+        [creature] => (creature, "1"),
+        [creature, level] => (creature, level.as_str()),
+        [] => return Err("missing CREATURE".into()),
+        _ => return Err("too many arguments".into()),
+    };
+    Ok(Args {
+        build,
+        creature: parse_number("CREATURE", Some(creature.clone()))?,
+        level: parse_number("LEVEL", Some(level.to_string()))?,
+        fights,
+        seed,
+    })
+}
+
+fn parse_number<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, Box<dyn Error>> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or(format!("{} needs a number", flag).into())
+}
+
+fn prop_name(prop_id: u16) -> &'static str {
+    PROPS[&prop_id.to_string()].name.as_str()
+}
+
+fn print_report(args: &Args, report: &Report) {
+    let class: &str = args.build.class.into();
+    let weapon = args.build.weapon.map(prop_name).unwrap_or("nothing");
+    println!(
+        "level {} {} with {} vs level {} {}",
+        args.build.level,
+        class,
+        weapon,
+        args.level,
+        prop_name(args.creature)
+    );
+    for armor in &args.build.armor {
+        println!("  wearing {}", prop_name(*armor));
+    }
+    println!(
+        "{} fights: {} won, {} lost, {} drawn ({:.1}% win rate)",
+        report.fights,
+        report.wins,
+        report.losses,
+        report.draws,
+        report.win_percent()
+    );
+    match report.time_to_kill() {
+        Some(seconds) => println!("time to kill: {:.1}s", seconds),
+        None => println!("time to kill: never"),
+    }
+    println!("              attacks    hit   crit   miss fizzle      dps");
+    print_side("player", &report.player, report.player_dps());
+    print_side("creature", &report.creature, report.creature_dps());
+}
+
+fn print_side(name: &str, side: &SideStats, dps: f64) {
+    println!(
+        "  {:<10} {:>8} {:>5.1}% {:>5.1}% {:>5.1}% {:>5.1}% {:>8.2}",
+        name,
+        side.attacks,
+        side.hit_percent(),
+        side.crit_percent(),
+        side.miss_percent(),
+        side.fizzle_percent(),
+        dps
+    );
+}
@@ -0,0 +1,71 @@
+//! Prints the experience, gold and loot for killing each creature at each level, for balancing
+//! the rewards in props.json.
+//!
+//! Usage: aldon-rewards [MAX_LEVEL]
+//!
+//! Levels go from 1 to MAX_LEVEL, 10 by default. Rewards marked '*' were seen in the original
+//! game, the rest are guesses.
+use aldonlib::data::{PropTypeRes, RewardRes, PROPS};
+use std::{env, process};
+
+const DEFAULT_MAX_LEVEL: i32 = 10;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let max_level = match args.as_slice() {
+        [] => DEFAULT_MAX_LEVEL,
+        [max_level] => match max_level.parse::<i32>() {
+            Ok(max_level) if max_level > 0 => max_level,
+            _ => {
+                eprintln!(
+                    "aldon-rewards: '{}' is not a level\nusage: aldon-rewards [MAX_LEVEL]",
+                    max_level
+                );
+                process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("usage: aldon-rewards [MAX_LEVEL]");
+            process::exit(1);
+        }
+    };
+
+    let mut creatures: Vec<(u16, &str, &RewardRes)> = PROPS
+        .iter()
+        .filter_map(|(prop_id, prop)| match &prop.kind {
+            PropTypeRes::Creature {
+                reward: Some(reward),
+                ..
+            }
+            | PropTypeRes::User {
+                reward: Some(reward),
+                ..
+            } => Some((prop_id.parse().unwrap(), prop.name.as_str(), reward)),
+            _ => None,
+        })
+        .collect();
+    creatures.sort_by_key(|(prop_id, _, _)| *prop_id);
+
+    for (prop_id, name, reward) in creatures {
+        let observed = if reward.observed { "*" } else { "" };
+        println!("{} {}{}", prop_id, name, observed);
+        if let Some(note) = &reward.note {
+            println!("  ({})", note);
+        }
+        println!("  level    exp       gold");
+        for level in 1..=max_level {
+            let (gold_min, gold_max) = reward.gold(level);
+            println!(
+                "  {:>5} {:>6} {:>10}",
+                level,
+                reward.exp(level),
+                format!("{}-{}", gold_min, gold_max)
+            );
+        }
+        for loot in &reward.loot {
+            let item = &PROPS[&loot.prop_id.to_string()];
+            println!("  drops {} ({}%)", item.name, loot.chance);
+        }
+        println!();
+    }
+}
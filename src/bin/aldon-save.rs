@@ -0,0 +1,87 @@
+//! Dumps saves as JSON and packs edited JSON back into saves. See `aldonlib::save_json` for what
+//! the JSON looks like.
+//!
+//! Usage: aldon-save dump SAVE
+//!        aldon-save pack JSON OUT [--browser]
+//!        aldon-save code SAVE [--chunks LEN]
+//!
+//! SAVE can be raw save bytes, base64, a save code, or the comma separated bytes the browser
+//! keeps in the `data` of each save in local storage. `pack` writes raw bytes, or the browser's
+//! format with `--browser`. `code` prints the save as a text code, split into chunks of at most
+//! LEN characters with `--chunks`.
+use aldonlib::{save_code, save_json};
+use base64::{engine::general_purpose, Engine as _};
+use std::{env, error::Error, fs, process};
+
+const USAGE: &str = "usage: aldon-save dump SAVE
+       aldon-save pack JSON OUT [--browser]
+       aldon-save code SAVE [--chunks LEN]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["dump", path] => dump(path),
+        ["pack", json, out] => pack(json, out, false),
+        ["pack", json, out, "--browser"] => pack(json, out, true),
+        ["code", path] => code(path, None),
+        ["code", path, "--chunks", len] => match len.parse() {
+            Ok(len) => code(path, Some(len)),
+            Err(_) => Err(format!("bad chunk length '{}'", len).into()),
+        },
+        _ => Err("unexpected arguments".into()),
+    };
+    if let Err(err) = result {
+        eprintln!("aldon-save: {}\n{}", err, USAGE);
+        process::exit(1);
+    }
+}
+
+fn dump(path: &str) -> Result<(), Box<dyn Error>> {
+    let bytes = read_save(&fs::read(path)?)?;
+    let value = save_json::to_json(&bytes)?;
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+fn pack(json: &str, out: &str, browser: bool) -> Result<(), Box<dyn Error>> {
+    let value = serde_json::from_str(&fs::read_to_string(json)?)?;
+    let bytes = save_json::from_json(&value)?;
+    if browser {
+        let text: Vec<String> = bytes.iter().map(u8::to_string).collect();
+        fs::write(out, text.join(","))?;
+    } else {
+        fs::write(out, bytes)?;
+    }
+    Ok(())
+}
+
+fn code(path: &str, chunk_len: Option<usize>) -> Result<(), Box<dyn Error>> {
+    let code = save_code::encode(&read_save(&fs::read(path)?)?);
+    match chunk_len {
+        Some(len) => {
+            for chunk in save_code::chunks(&code, len) {
+                println!("{}", chunk);
+            }
+        }
+        None => println!("{}", code),
+    }
+    Ok(())
+}
+
+/// Saves copied out of the browser are text, anything else is taken as raw bytes
+fn read_save(file: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let Ok(text) = std::str::from_utf8(file) else {
+        return Ok(file.to_vec());
+    };
+    if save_code::is_code(file) {
+        return Ok(save_code::decode(text)?);
+    }
+    let text = text.trim();
+    let browser: Result<Vec<u8>, _> = text.split(',').map(|byte| byte.trim().parse()).collect();
+    if let Ok(bytes) = browser {
+        return Ok(bytes);
+    }
+    Ok(general_purpose::STANDARD
+        .decode(text)
+        .unwrap_or_else(|_| file.to_vec()))
+}
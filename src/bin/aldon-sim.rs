@@ -0,0 +1,430 @@
+//! Runs aldon's crossing without a browser. A new character is created and then driven by a
+//! script of timed inputs, printing the game state every tick. Useful for reproducing bugs.
+//!
+//! Usage: aldon-sim [--tick MS] [--duration MS] [--seed N] [--name NAME] [--race RACE] [--verbose]
+//!                  [--world ID] [--record FILE] SCRIPT
+//!        aldon-sim [--verbose] --replay FILE
+//!
+//! Runs with the same --seed play out the same way. `--world` picks a built in world, e.g.
+//! fathers_day. `--record FILE` writes a replay of the run,
+//! and `aldon-sim --replay FILE` plays a replay back (e.g. one attached to a bug report),
+//! printing the game state after every update and failing if it doesn't end the same way.
+//!
+//! Each line of the script is `<time in ms> <event> <args...>`. Blank lines and lines starting
+//! with '#' are ignored. Events are:
+//! ```text
+//! 500 input_stage 13.5 3.5        // tap the stage at game coordinates (x, y)
+//! 900 input_buttons 1.5 2.5       // touch down on the buttons at (x, y)
+//! 950 input_buttons 1.5 2.5 up    // touch up on the buttons at (x, y)
+//! 1200 send_response 7 0          // respond to actor 7 with choice A (0), B (1) or C (2)
+//! 1500 save                       // save the game in memory
+//! 1600 load                       // load the last save
+//! ```
+use aldonlib::{
+    body::Body,
+    buttons::Button,
+    data::World,
+    game::{AldonGame, Dialog, TransactionType},
+    replay::{self, Input, Replay},
+    stats::PlayerStats,
+    thrift::save::RaceType,
+};
+use std::{cell::RefCell, env, error::Error, fmt, fs, process, rc::Rc};
+
+const USAGE: &str = "usage: aldon-sim [--tick MS] [--duration MS] [--seed N] [--name NAME] \
+                     [--race RACE] [--verbose] [--world ID] [--record FILE] SCRIPT
+       aldon-sim [--verbose] --replay FILE";
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if args.verbose {
+        aldonlib::set_log_sink(|s| eprintln!("log: {}", s));
+    }
+    let result = match &args.replay {
+        Some(path) => run_replay(path),
+        None => run(args),
+    };
+    if let Err(err) = result {
+        eprintln!("aldon-sim: {}", err);
+        process::exit(1);
+    }
+}
+
+struct Args {
+    script: String,
+    record: Option<String>,
+    replay: Option<String>,
+    tick: f64,
+    duration: Option<f64>,
+    seed: Option<u64>,
+    name: String,
+    race: RaceType,
+    world: World,
+    verbose: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, SimError> {
+        let mut script = None;
+        let mut tick = 50.0;
+        let mut duration = None;
+        let mut seed = None;
+        let mut name = "Sim".to_string();
+        let mut race = RaceType::HUMAN;
+        let mut world = World::default();
+        let mut verbose = false;
+        let mut record = None;
+        let mut replay = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tick" => tick = parse_number(&arg, args.next())?,
+                "--duration" => duration = Some(parse_number(&arg, args.next())?),
+                "--seed" => {
+                    let value = args.next().and_then(|value| value.parse().ok());
+                    seed = Some(value.ok_or(SimError::new("--seed needs a number"))?);
+                }
+                "--name" => name = args.next().ok_or(SimError::new("--name needs a value"))?,
+                "--race" => {
+                    race = match args.next().as_deref() {
+                        Some("human") => RaceType::HUMAN,
+                        Some("elf") => RaceType::ELF,
+                        Some("dwarf") => RaceType::DWARF,
+                        _ => return Err(SimError::new("--race must be human, elf or dwarf")),
+                    }
+                }
+                "--world" => {
+                    let id = args.next().ok_or(SimError::new("--world needs an id"))?;
+                    world = World::built_in(&id).ok_or(SimError::new(&format!(
+                        "no built in world '{}', expected one of {}",
+                        id,
+                        World::BUILT_IN.join(", ")
+                    )))?;
+                }
+                "--verbose" => verbose = true,
+                "--record" => {
+                    record = Some(args.next().ok_or(SimError::new("--record needs a file"))?)
+                }
+                "--replay" => {
+                    replay = Some(args.next().ok_or(SimError::new("--replay needs a file"))?)
+                }
+                _ if arg.starts_with("--") => {
+                    return Err(SimError::new(&format!("unknown flag {}", arg)))
+                }
+                _ => script = Some(arg),
+            }
+        }
+        if tick <= 0.0 {
+            return Err(SimError::new("--tick must be positive"));
+        }
+        let script = match (script, &replay) {
+            (Some(script), None) => script,
+            (None, Some(_)) => String::new(),
+            (Some(_), Some(_)) => return Err(SimError::new("--replay doesn't take a SCRIPT")),
+            (None, None) => return Err(SimError::new("missing SCRIPT")),
+        };
+        Ok(Self {
+            script,
+            record,
+            replay,
+            tick,
+            duration,
+            seed,
+            name,
+            race,
+            world,
+            verbose,
+        })
+    }
+}
+
+fn parse_number(flag: &str, value: Option<String>) -> Result<f64, SimError> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or(SimError::new(&format!("{} needs a number", flag)))
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let text = fs::read_to_string(&args.script)?;
+    let events = parse_script(&text)?;
+    let last_event = events.last().map(|event| event.time).unwrap_or(0.0);
+    let duration = args.duration.unwrap_or(last_event + 1000.0);
+
+    let dialog = Rc::new(HeadlessDialog::new(args.world));
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed={}", seed);
+    let mut game = AldonGame::with_world(dialog, seed, args.world);
+    if args.record.is_some() {
+        game.start_recording()?;
+    }
+    game.new_game(args.name, args.race, 600, 10, 10, 10, 10, 10, 10);
+
+    let mut events = events.into_iter().peekable();
+    let mut save: Option<Vec<u8>> = None;
+    let mut logged = game.console().logged();
+    let mut now = 0.0;
+
+    while now <= duration {
+        while let Some(event) = events.next_if(|event| event.time <= now) {
+            println!("t={} event: {}", now, event.kind);
+            match event.kind {
+                EventKind::InputStage(x, y) => game.input_stage(x, y),
+                EventKind::InputButtons(x, y, touch_up) => game.input_buttons(x, y, touch_up),
+                EventKind::SendResponse(actor_id, response) => {
+                    game.send_response(actor_id, response)
+                }
+                EventKind::Save => save = Some(game.save()?),
+                EventKind::Load => {
+                    let bytes = save.clone().ok_or(SimError::new("load before save"))?;
+                    game.load_save(bytes)?;
+                }
+            }
+        }
+        game.update(now);
+        print_tick(&game, now, &mut logged);
+        if game.game_over() {
+            break;
+        }
+        now += args.tick;
+    }
+    if let Some(path) = args.record {
+        let replay = game
+            .stop_recording()
+            .ok_or(SimError::new("not recording"))?;
+        fs::write(&path, replay.to_bytes())?;
+        println!("recorded {} inputs to {}", replay.inputs.len(), path);
+    }
+    Ok(())
+}
+
+/// Plays back a replay, printing the state after every update like a scripted run does
+fn run_replay(path: &str) -> Result<(), Box<dyn Error>> {
+    let replay = Replay::from_bytes(&fs::read(path)?)?;
+    println!("seed={}", replay.seed);
+
+    let world = World::built_in(&replay.world_id).ok_or(SimError::new(&format!(
+        "replay is from world '{}' which isn't built in",
+        replay.world_id
+    )))?;
+    let dialog = Rc::new(HeadlessDialog::new(world));
+    let mut game = AldonGame::with_world(dialog, replay.seed, world);
+    let mut logged = game.console().logged();
+    for (now, input) in replay.inputs.iter() {
+        replay::apply(&mut game, *now, input)?;
+        match input {
+            Input::Update => print_tick(&game, *now, &mut logged),
+            Input::LoadSave(bytes) => println!("t={} event: LoadSave({} bytes)", now, bytes.len()),
+            _ => println!("t={} event: {:?}", now, input),
+        }
+    }
+    replay::check_end_state(&game, &replay)?;
+    println!("replay ok: {} inputs", replay.inputs.len());
+    Ok(())
+}
+
+/// Prints the game state after an update and any new console lines
+fn print_tick(game: &AldonGame, now: f64, logged: &mut usize) {
+    let position = match game.player_position() {
+        Some((x, y)) => format!("({:.2}, {:.2})", x, y),
+        None => "none".to_string(),
+    };
+    println!("t={} map={} player={}", now, game.map_id(), position);
+
+    let console = game.console();
+    let lines = console.lines();
+    let new = (console.logged() - *logged).min(lines.len());
+    for line in &lines[lines.len() - new..] {
+        println!("  | {}", line);
+    }
+    *logged = console.logged();
+    if game.game_over() {
+        println!("t={} game over", now);
+    }
+}
+
+struct Event {
+    time: f64,
+    kind: EventKind,
+}
+
+enum EventKind {
+    InputStage(f64, f64),
+    InputButtons(f64, f64, bool),
+    SendResponse(u16, u8),
+    Save,
+    Load,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventKind::InputStage(x, y) => write!(f, "input_stage {} {}", x, y),
+            EventKind::InputButtons(x, y, false) => write!(f, "input_buttons {} {}", x, y),
+            EventKind::InputButtons(x, y, true) => write!(f, "input_buttons {} {} up", x, y),
+            EventKind::SendResponse(actor_id, response) => {
+                write!(f, "send_response {} {}", actor_id, response)
+            }
+            EventKind::Save => write!(f, "save"),
+            EventKind::Load => write!(f, "load"),
+        }
+    }
+}
+
+/// Parses a script into events ordered by time
+fn parse_script(text: &str) -> Result<Vec<Event>, SimError> {
+    let mut events = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let event =
+            parse_event(line).map_err(|err| SimError::new(&format!("line {}: {}", i + 1, err)))?;
+        events.push(event);
+    }
+    // Stable, so events at the same time keep their order in the script
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(events)
+}
+
+fn parse_event(line: &str) -> Result<Event, SimError> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let arg = |i: usize| -> Result<&str, SimError> {
+        words
+            .get(i)
+            .copied()
+            .ok_or_else(|| SimError::new(&format!("too few arguments in '{}'", line)))
+    };
+    let number = |i: usize| -> Result<f64, SimError> {
+        let word = arg(i)?;
+        word.parse()
+            .map_err(|_| SimError::new(&format!("'{}' is not a number", word)))
+    };
+    let time = number(0)?;
+    let kind = match arg(1)? {
+        "input_stage" => EventKind::InputStage(number(2)?, number(3)?),
+        "input_buttons" => {
+            let touch_up = match words.get(4) {
+                None => false,
+                Some(&"up") => true,
+                Some(word) => return Err(SimError::new(&format!("expected 'up', got '{}'", word))),
+            };
+            EventKind::InputButtons(number(2)?, number(3)?, touch_up)
+        }
+        "send_response" => EventKind::SendResponse(number(2)? as u16, number(3)? as u8),
+        "save" => EventKind::Save,
+        "load" => EventKind::Load,
+        other => return Err(SimError::new(&format!("unknown event '{}'", other))),
+    };
+    Ok(Event { time, kind })
+}
+
+/// A dialog that prints what the browser would show and otherwise just keeps track of the
+/// current transaction
+struct HeadlessDialog {
+    world: World,
+    transaction: RefCell<Vec<Rc<Body>>>,
+}
+
+impl HeadlessDialog {
+    fn new(world: World) -> Self {
+        Self {
+            world,
+            transaction: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn show_items(&self, title: &str, body: &Body, items: Vec<Rc<Body>>) {
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        println!(
+            "  dialog: {} for {}: [{}]",
+            title,
+            body.name,
+            names.join(", ")
+        );
+        *self.transaction.borrow_mut() = items;
+    }
+}
+
+impl Dialog for HeadlessDialog {
+    fn tell_message(&self, title: &str, _portrait_id: u16, msg_id: u16, from_actor: u16) {
+        let msg = &self.world.res.messages[&msg_id.to_string()];
+        println!("  dialog: {} (actor {}): {}", title, from_actor, msg.value);
+        let choices = [&msg.choice_a, &msg.choice_b, &msg.choice_c];
+        for (response, choice) in choices.iter().enumerate() {
+            if let Some(choice) = choice {
+                println!("    {}: {}", response, choice);
+            }
+        }
+    }
+
+    fn execute_trade(&self, kind: TransactionType, body: Rc<Body>, items: Vec<Rc<Body>>) {
+        self.show_items(&format!("{:?}", kind), &body, items);
+    }
+
+    fn pickup(&self, body: Rc<Body>, items: Vec<Rc<Body>>) {
+        self.show_items("PickUp", &body, items);
+    }
+
+    fn buy_sell(&self, body: Rc<Body>, items: Vec<Rc<Body>>, kind: TransactionType) {
+        self.show_items(&format!("{:?}", kind), &body, items);
+    }
+
+    fn pick_button(&self, button_idx: usize, buttons: Vec<Button>) {
+        println!("  dialog: pick button {} from {:?}", button_idx, buttons);
+    }
+
+    fn stats(&self, stats: &PlayerStats) {
+        println!(
+            "  dialog: stats {} level {} {} hp {}/{} mp {}/{} exp {} gp {}",
+            stats.name,
+            stats.level,
+            stats.class,
+            stats.hp,
+            stats.hp_max,
+            stats.mp,
+            stats.mp_max,
+            stats.exp,
+            stats.gp
+        );
+    }
+
+    fn spellbook(&self, spells: &[u16]) {
+        println!("  dialog: spellbook {:?}", spells);
+    }
+
+    fn get_transaction(&self) -> Vec<Rc<Body>> {
+        self.transaction.borrow().clone()
+    }
+
+    fn remove_item(&self, index: usize) -> Rc<Body> {
+        self.transaction.borrow_mut().remove(index)
+    }
+}
+
+#[derive(Debug)]
+struct SimError {
+    message: String,
+}
+
+impl SimError {
+    fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for SimError {}
@@ -0,0 +1,45 @@
+//! Checks the world data for broken actors and references, printing every problem found.
+//!
+//! Usage: aldon-validate [--maps FILE]
+//!
+//! Checks the built in maps.json, or FILE. Exits with 1 if there are any problems.
+use aldonlib::{
+    data::{WorldRes, WORLD},
+    validate,
+};
+use std::{env, error::Error, fs, process};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(0) => println!("no problems"),
+        Ok(count) => {
+            println!("{} problem{}", count, if count == 1 { "" } else { "s" });
+            process::exit(1);
+        }
+        Err(err) => {
+            eprintln!(
+                "aldon-validate: {}\nusage: aldon-validate [--maps FILE]",
+                err
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<usize, Box<dyn Error>> {
+    let loaded: WorldRes;
+    let world = match args {
+        [] => &*WORLD,
+        [flag, path] if flag == "--maps" => {
+            loaded = serde_json::from_str(&fs::read_to_string(path)?)?;
+            &loaded
+        }
+        _ => return Err("unexpected arguments".into()),
+    };
+    let problems = validate::validate(world);
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    Ok(problems.len())
+}
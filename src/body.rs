@@ -4,8 +4,10 @@ use crate::{
     aldon_log,
     combat::{dir, monster_reward, BattleEvent, BattleEventType, Motion},
     condition::{self, Condition},
-    data::{PropTypeRes, PROPS, SPELLS},
-    game::{EquipType, InvalidDataError, CONSOLE},
+    console::Console,
+    data::{DamageType, EffectRes, PropTypeRes, PROPS, SPELLS},
+    events::{EventBody, EventQueue, GameEvent},
+    game::{EquipType, InvalidDataError},
     js, stats,
     stats::PlayerStats,
     thrift::{
@@ -13,12 +15,11 @@ use crate::{
         util::box_vec,
     },
 };
-use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
 use std::{
     cell::{Cell, RefCell},
     cmp::{max, min, Ordering},
     collections::HashMap,
-    fmt::Write,
     rc::Rc,
     vec::Vec,
 };
@@ -62,6 +63,10 @@ pub struct Body {
     pub(crate) base_vit: Cell<i32>,
     pub(crate) base_luck: Cell<i32>,
     pub(crate) battle_events: RefCell<Vec<BattleEvent>>,
+    /// The events queue of the stage the body is on
+    events: RefCell<EventQueue>,
+    /// The console of the game whose stage the body is on
+    console: RefCell<Console>,
     pub(crate) death_time: Cell<f64>,
     pub(crate) prefer_melee: Cell<bool>,
 
@@ -159,6 +164,8 @@ impl Body {
             attack: RefCell::new(None),
             last_attack_time: Cell::new(0.0),
             battle_events: RefCell::new(Vec::new()),
+            events: RefCell::new(EventQueue::default()),
+            console: RefCell::new(Console::default()),
             death_time: Cell::new(0.0),
             hostile_to: Cell::new(None),
             prefer_melee: Cell::new(true),
@@ -521,6 +528,8 @@ impl Body {
             attack: RefCell::new(None),
             last_attack_time: Cell::new(0.0),
             battle_events: RefCell::new(Vec::new()),
+            events: RefCell::new(EventQueue::default()),
+            console: RefCell::new(Console::default()),
             death_time: Cell::new(0.0),
             hostile_to: Cell::new(save.hostile_to),
             prefer_melee: Cell::new(prefer_melee),
@@ -799,15 +808,14 @@ impl Body {
         value
     }
 
-    pub(crate) fn attack_damage(&self, prop_id: u16, opponent_level: i32) -> i32 {
+    pub(crate) fn attack_damage(&self, prop_id: u16, opponent_level: i32, rng: &mut StdRng) -> i32 {
         let factor = max(1, (self.level() - opponent_level) / 2);
-        if matches!(prop_id, 244 | 314 | 392 | 237) {
-            let mut rng = rand::thread_rng();
+        let prop = &PROPS[&prop_id.to_string()];
+        if prop.spell_id().is_some() {
             let damage_min = 1;
             let damage_max = 2 + 3 * self.level();
             return rng.gen_range(damage_min..=damage_max) * factor;
         }
-        let prop = &PROPS[&prop_id.to_string()];
 
         if let PropTypeRes::Weapon {
             damage_min,
@@ -815,7 +823,6 @@ impl Body {
             ..
         } = prop.kind
         {
-            let mut rng = rand::thread_rng();
             let dmg =
                 rng.gen_range(damage_min..=damage_max) + stats::strength_to_damage(self.strength());
             return factor * max(dmg, 1);
@@ -833,7 +840,7 @@ impl Body {
         if let PropTypeRes::Weapon { delay, .. } = &PROPS[&prop_id.to_string()].kind {
             return Some(*delay as f64);
         }
-        js::log(&format!(
+        self.console().debug(&format!(
             "Warning, {} was equiped as a weapon but is not a weapon",
             prop_id
         ));
@@ -1051,7 +1058,7 @@ impl Body {
         self.motion.set(Some(motion));
     }
 
-    pub(crate) fn update(&self, now: f64) {
+    pub(crate) fn update(&self, now: f64, rng: &mut StdRng) {
         if self.health.get() == 0 {
             self.clear_attack();
             self.clear_walk_goal();
@@ -1076,10 +1083,11 @@ impl Body {
             }
         } else if self.attack.borrow().is_none() && self.wanderer.borrow().is_some() {
             let mut wanderer = self.wanderer.borrow_mut();
-            let (x, y) = wanderer
-                .as_mut()
-                .unwrap()
-                .update(now, self.x().floor(), self.y().floor());
+            let (x, y) =
+                wanderer
+                    .as_mut()
+                    .unwrap()
+                    .update(now, self.x().floor(), self.y().floor(), rng);
 
             self.walk_to(x, y);
         }
@@ -1095,13 +1103,17 @@ impl Body {
 
         let mut conditions = self.conditions.borrow_mut();
         for condition in conditions.iter() {
-            condition.update(self, now);
+            condition.update(self, now, rng);
         }
         conditions.retain(|condition| {
             let finished = condition.finished(now);
             if finished {
                 let cond: &str = condition.kind.into();
-                aldon_log!("*{} has worn off.*", cond);
+                aldon_log!(self.console(), "*{} has worn off.*", cond);
+                self.emit(GameEvent::ConditionWornOff {
+                    body: self.into(),
+                    condition: condition.kind,
+                });
             }
             !finished
         });
@@ -1155,7 +1167,7 @@ impl Body {
         }
         equiped.insert(equip_to, body.clone());
 
-        if let Some(condition) = condition::for_item(now, body) {
+        for condition in condition::for_item(now, body) {
             self.add_condition_no_log(condition);
         }
         true
@@ -1172,7 +1184,7 @@ impl Body {
         let mut equiped = self.equiped.borrow_mut();
         equiped.insert(equip_to, body.clone());
 
-        if let Some(condition) = condition::for_item(now, body) {
+        for condition in condition::for_item(now, body) {
             self.add_condition(condition);
         }
     }
@@ -1204,10 +1216,10 @@ impl Body {
         let idx = inventory.iter().position(|b| Rc::ptr_eq(&body, b));
         if let Some(i) = idx {
             inventory.remove(i);
-            js::log("droppable");
+            self.console().debug("droppable");
             true
         } else {
-            js::log("cannot drop");
+            self.console().debug("cannot drop");
             false
         }
     }
@@ -1224,7 +1236,7 @@ impl Body {
         let item = Rc::new(inner_item);
         self.give_body_item(item.clone());
         if self.is_player() {
-            aldon_log!("(you receive a {}.)", prop.name);
+            aldon_log!(self.console(), "(you receive a {}.)", prop.name);
         }
         item
     }
@@ -1232,7 +1244,7 @@ impl Body {
     pub fn give_gold(&self, gold: i32) {
         self.gold.set(self.gold.get() + gold);
         if self.is_player() {
-            aldon_log!("(you receive {} gold.)", gold);
+            aldon_log!(self.console(), "(you receive {} gold.)", gold);
         }
     }
 
@@ -1246,12 +1258,16 @@ impl Body {
             let level = stats::max_level(body.exp());
             if body.level() < level {
                 body.set_level(level);
-                aldon_log!("*Pet {} gained a level!*", body.name);
+                aldon_log!(self.console(), "*Pet {} gained a level!*", body.name);
+                body.emit(GameEvent::LevelUp {
+                    body: body.as_ref().into(),
+                    level,
+                });
             }
         }
         self.exp.set(self.exp.get() + exp);
         if self.is_player() {
-            aldon_log!("(you receive {} exp.)", exp);
+            aldon_log!(self.console(), "(you receive {} exp.)", exp);
         }
     }
 
@@ -1263,7 +1279,7 @@ impl Body {
         let mut inventory = self.inventory.borrow_mut();
         if body.groupable() {
             for item in inventory.iter() {
-                js::log(&item.name);
+                self.console().debug(&item.name);
                 let item = item;
                 if item.prop_id == body.prop_id {
                     item.quantity.set(min(item.quantity.get() + 1, 10));
@@ -1307,7 +1323,7 @@ impl Body {
         }
         if self.is_player() {
             let prop = &PROPS[&prop_id.to_string()];
-            aldon_log!("(you lost your {}.)", prop.name);
+            aldon_log!(self.console(), "(you lost your {}.)", prop.name);
         }
     }
 
@@ -1330,7 +1346,7 @@ impl Body {
     pub(crate) fn heal(&self, delta: i32) {
         let delta = self.heal_no_log(delta);
         if delta != 0 {
-            aldon_log!("*{} gains {} health*", self.name, delta);
+            aldon_log!(self.console(), "*{} gains {} health*", self.name, delta);
         }
     }
 
@@ -1348,21 +1364,28 @@ impl Body {
     }
 
     pub(crate) fn cure_poison(&self) {
-        self.conditions
-            .borrow_mut()
-            .retain(|c| c.kind != save::ConditionType::POISON);
-
-        aldon_log!("*Poison has worn off.*");
+        let removed = self.remove_condition_no_log(save::ConditionType::POISON);
+        aldon_log!(self.console(), "*Poison has worn off.*");
+        if removed {
+            self.emit(GameEvent::ConditionWornOff {
+                body: self.into(),
+                condition: save::ConditionType::POISON,
+            });
+        }
     }
 
     pub(crate) fn add_condition(&self, condition: Condition) {
         let kind = condition.kind.clone();
         let condition_str: String = (&condition).into();
         self.add_condition_no_log(condition);
+        self.emit(GameEvent::ConditionGained {
+            body: self.into(),
+            condition: kind,
+        });
         if matches!(kind, ConditionType::SNEAKING | ConditionType::HIDDEN) {
             return;
         }
-        aldon_log!("*{} recieves {}*", self.name, condition_str);
+        aldon_log!(self.console(), "*{} recieves {}*", self.name, condition_str);
     }
 
     pub(crate) fn add_condition_no_log(&self, condition: Condition) {
@@ -1378,24 +1401,52 @@ impl Body {
 
     /// Returns true if conditions were removed
     pub(crate) fn remove_condition(&self, kind: save::ConditionType) -> bool {
-        let mut conditions = self.conditions.borrow_mut();
-        let len_before = conditions.len();
-        conditions.retain(|c| c.kind != kind);
-
-        let removed = len_before != conditions.len();
+        let removed = self.remove_condition_no_log(kind);
+        if removed {
+            self.emit(GameEvent::ConditionWornOff {
+                body: self.into(),
+                condition: kind,
+            });
+        }
         if removed && self.is_player() {
             let cond: &str = kind.into();
-            aldon_log!("*{} has worn off.*", cond);
+            aldon_log!(self.console(), "*{} has worn off.*", cond);
         }
         removed
     }
 
+    fn remove_condition_no_log(&self, kind: save::ConditionType) -> bool {
+        let mut conditions = self.conditions.borrow_mut();
+        let len_before = conditions.len();
+        conditions.retain(|c| c.kind != kind);
+        len_before != conditions.len()
+    }
+
     pub(crate) fn battle_event(&self, now: f64, event: BattleEventType) {
         self.battle_events
             .borrow_mut()
             .push(BattleEvent::new(now, event));
     }
 
+    pub(crate) fn console(&self) -> Console {
+        self.console.borrow().clone()
+    }
+
+    /// Logs to `console` from now on, set by the stage the body is placed on
+    pub(crate) fn set_console(&self, console: &Console) {
+        *self.console.borrow_mut() = console.clone();
+    }
+
+    /// Queues events on `events` from now on, set by the stage the body is placed on
+    pub(crate) fn set_events(&self, events: &EventQueue) {
+        *self.events.borrow_mut() = events.clone();
+    }
+
+    /// Queues an event for the host, see `Stage::drain_events`
+    pub(crate) fn emit(&self, event: GameEvent) {
+        self.events.borrow().push(event);
+    }
+
     // TODO: maybe check on_death here so enemies that die from poison
     // will reward the player?
     pub(crate) fn take_attack(
@@ -1403,11 +1454,31 @@ impl Body {
         now: f64,
         battle_event: BattleEventType,
         damage: i32,
+        damage_type: DamageType,
         maybe_attacker: Option<Rc<Body>>,
     ) {
+        let resisted = damage * self.resistance(damage_type) / 100;
+        if resisted > 0 {
+            aldon_log!(self.console(), "-{} resists {} dmg-", self.name, resisted);
+        } else if resisted < 0 {
+            aldon_log!(
+                self.console(),
+                "-{} takes {} extra dmg-",
+                self.name,
+                -resisted
+            );
+        }
+        let damage = damage - resisted;
         let health = max(0, self.health.get() - damage);
         self.health.set(health);
         self.battle_event(now, battle_event);
+        self.emit(GameEvent::Damage {
+            target: self.into(),
+            attacker: maybe_attacker.as_deref().map(EventBody::from),
+            kind: battle_event,
+            amount: damage,
+            damage_type,
+        });
 
         if self.health.get() <= 0 {
             self.death_time.set(now);
@@ -1415,7 +1486,7 @@ impl Body {
         if (battle_event == BattleEventType::Hit || battle_event == BattleEventType::Crit)
             && damage > 0
         {
-            aldon_log!("-{} takes {} dmg-", self.name, damage);
+            aldon_log!(self.console(), "-{} takes {} dmg-", self.name, damage);
         }
         if self.needs_attack_update().is_some() {
             // don't attack back if you're already attacking something
@@ -1428,6 +1499,33 @@ impl Body {
         }
     }
 
+    /// The health `damage` of `damage_type` would take after resistance
+    pub(crate) fn damage_taken(&self, damage: i32, damage_type: DamageType) -> i32 {
+        damage - damage * self.resistance(damage_type) / 100
+    }
+
+    /// Percent less damage of `damage_type` taken, from the body's own prop and what it has
+    /// equipped, between -100 and 100
+    pub fn resistance(&self, damage_type: DamageType) -> i32 {
+        let own = &PROPS[&self.prop_id.to_string()];
+        let equiped = self.equiped.borrow();
+        let items = equiped
+            .values()
+            .map(|item| &PROPS[&item.prop_id.to_string()]);
+        let percent: i32 = std::iter::once(own)
+            .chain(items)
+            .flat_map(|prop| prop.effects.iter())
+            .map(|effect| match effect {
+                EffectRes::Resistance {
+                    damage_type: kind,
+                    percent,
+                } if *kind == damage_type => *percent,
+                _ => 0,
+            })
+            .sum();
+        percent.clamp(-100, 100)
+    }
+
     pub fn follow(&self, body: Rc<Body>) {
         *self.follow.borrow_mut() = Some(body);
     }
@@ -1468,9 +1566,10 @@ impl Body {
         self.set_health(i32::MAX);
     }
 
-    /// Reward a player (and maybe their pet) for killing a monster
-    pub(crate) fn monster_reward(&self, prop_id: u16, level: i32) {
-        let (mut exp, gp) = monster_reward(prop_id, level);
+    /// Reward a player (and maybe their pet) for killing a monster. Returns the player's exp and
+    /// gold.
+    pub(crate) fn monster_reward(&self, prop_id: u16, level: i32, rng: &mut StdRng) -> (i32, i32) {
+        let (mut exp, gp) = monster_reward(prop_id, level, rng);
 
         let pet = self.pet();
         if pet.is_some() {
@@ -1481,25 +1580,29 @@ impl Body {
 
             if pet.level() < level {
                 pet.set_level(level);
-                aldon_log!("*Pet {} gained a level!*", pet.name);
+                aldon_log!(self.console(), "*Pet {} gained a level!*", pet.name);
+                pet.emit(GameEvent::LevelUp {
+                    body: pet.as_ref().into(),
+                    level,
+                });
             }
         }
-        aldon_log!("(you receive {}xp and {}gp.)", exp, gp);
+        aldon_log!(self.console(), "(you receive {}xp and {}gp.)", exp, gp);
         self.exp.set(self.exp.get() + exp);
         self.gold.set(self.gold.get() + gp);
+        (exp, gp)
     }
 
-    pub fn sneak(&self, now: f64) {
+    pub fn sneak(&self, now: f64, rng: &mut StdRng) {
         let chance = stats::sneak_chance(self.dexterity(), self.level());
-        let mut rng = rand::thread_rng();
         let roll: i32 = rng.gen_range(1..=100);
-        aldon_log!("*Chance:{} Roll: {}*", chance, roll);
+        aldon_log!(self.console(), "*Chance:{} Roll: {}*", chance, roll);
 
         if roll >= chance {
-            aldon_log!("*You fail to sneak.*");
+            aldon_log!(self.console(), "*You fail to sneak.*");
             return;
         }
-        aldon_log!("*You begin sneaking.*");
+        aldon_log!(self.console(), "*You begin sneaking.*");
         let c = condition::sneaking(now, chance);
         self.add_condition(c);
     }
@@ -1509,16 +1612,15 @@ impl Body {
         self.remove_condition(save::ConditionType::HIDDEN);
     }
 
-    pub fn hide(&self) {
+    pub fn hide(&self, rng: &mut StdRng) {
         let chance = stats::hide_chance(self.dexterity(), self.level());
-        let mut rng = rand::thread_rng();
         let roll: i32 = rng.gen_range(1..=100);
-        aldon_log!("*Chance:{} Roll: {}*", chance, roll);
+        aldon_log!(self.console(), "*Chance:{} Roll: {}*", chance, roll);
         if roll >= chance {
-            aldon_log!("*You fail to hide yourself.*");
+            aldon_log!(self.console(), "*You fail to hide yourself.*");
             return;
         }
-        aldon_log!("*You have hidden yourself*");
+        aldon_log!(self.console(), "*You have hidden yourself*");
         let c = condition::hidden();
         self.add_condition(c);
     }
@@ -1537,13 +1639,13 @@ impl Body {
 
     /// Attempts to cast a spell, returns true on success. This may fail if the spell hasn't cooled
     /// down yet, out of mana, or fizzles.
-    pub(crate) fn try_spell(&self, now: f64, spell_id: u16) -> bool {
+    pub(crate) fn try_spell(&self, now: f64, spell_id: u16, rng: &mut StdRng) -> bool {
         if !self.can_cast_spell(now, spell_id) {
             return false;
         }
         let spell = &SPELLS[&spell_id.to_string()];
         if self.magic() < spell.cost {
-            aldon_log!("-Out of mana.-");
+            aldon_log!(self.console(), "-Out of mana.-");
             return false;
         }
         self.set_magic(self.magic() - spell.cost);
@@ -1552,12 +1654,17 @@ impl Body {
                 + stats::luck_to_modifier(self.luck()),
             99,
         );
-        let mut rng = rand::thread_rng();
         let roll: i32 = rng.gen_range(0..=99);
-        aldon_log!("-{} needs < {}, rolls {}-", self.name, cast_success, roll);
+        aldon_log!(
+            self.console(),
+            "-{} needs < {}, rolls {}-",
+            self.name,
+            cast_success,
+            roll
+        );
 
         if roll >= cast_success {
-            aldon_log!("-{} fizzles-", self.name);
+            aldon_log!(self.console(), "-{} fizzles-", self.name);
             return false;
         }
         self.set_action_state(ActionState::Attack);
@@ -1693,7 +1800,6 @@ struct Wanderer {
     x_max: f64,
     y_max: f64,
     deadline: f64,
-    rng: ThreadRng,
 }
 
 impl Wanderer {
@@ -1704,23 +1810,22 @@ impl Wanderer {
             y_min,
             y_max,
             deadline: 0.0,
-            rng: rand::thread_rng(),
         }
     }
 
-    fn update(&mut self, now: f64, x: f64, y: f64) -> (f64, f64) {
+    fn update(&mut self, now: f64, x: f64, y: f64, rng: &mut StdRng) -> (f64, f64) {
         if now < self.deadline {
             return (x, y);
         }
-        if self.rng.gen_ratio(1, 2) {
-            self.deadline = now + self.rng.gen_range(1000.0..1500.0);
+        if rng.gen_ratio(1, 2) {
+            self.deadline = now + rng.gen_range(1000.0..1500.0);
             return (x, y);
         }
-        let mut dx = self.rng.gen_range(-1..=1) as f64;
+        let mut dx = rng.gen_range(-1..=1) as f64;
         if x + dx > self.x_max || x + dx < self.x_min {
             dx = 0.0;
         }
-        let mut dy = self.rng.gen_range(-1..=1) as f64;
+        let mut dy = rng.gen_range(-1..=1) as f64;
         if y + dy > self.y_max || y + dy < self.y_min {
             dy = 0.0;
         }
@@ -1764,7 +1869,6 @@ impl Wanderer {
             x_max,
             y_max,
             deadline: now + rest_time,
-            rng: rand::thread_rng(),
         };
         Ok(wanderer)
     }
@@ -1858,7 +1962,6 @@ const PET_NAMES: [&str; 25] = [
     "Riker",
 ];
 
-pub fn pet_name() -> &'static str {
-    let mut rng = rand::thread_rng();
-    PET_NAMES.choose(&mut rng).unwrap()
+pub fn pet_name(rng: &mut StdRng) -> &'static str {
+    PET_NAMES.choose(rng).unwrap()
 }
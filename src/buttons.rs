@@ -93,9 +93,9 @@ impl Buttons {
                     }
                 }
                 Button::Hide { toggled: true } => {
-                    js::log("hidden down");
+                    self.stage.console().debug("hidden down");
                     if !self.stage.get_player().hidden() {
-                        js::log("unhide button");
+                        self.stage.console().debug("unhide button");
                         *button = Button::Hide { toggled: false };
                     }
                 }
@@ -129,10 +129,12 @@ impl Buttons {
         }
     }
 
+    #[cfg(feature = "web")]
     pub fn tab(&self) -> usize {
         self.tab
     }
 
+    #[cfg(feature = "web")]
     pub fn idx_toggled(&self, idx: usize) -> bool {
         self.buttons[self.tab][idx].toggled()
     }
@@ -161,19 +163,19 @@ impl Buttons {
             return;
         }
         if rect_contains(2.4375, 1.8125, 0.3125, 1.5625, x, y) && self.tab != 0 {
-            js::log("tab1");
+            self.stage.console().debug("tab1");
             self.untoggle_all();
             self.tab = 0;
             return;
         }
         if rect_contains(2.4375, 3.5, 0.3125, 1.5625, x, y) && self.tab != 1 {
-            js::log("tab2");
+            self.stage.console().debug("tab2");
             self.untoggle_all();
             self.tab = 1;
             return;
         }
         if rect_contains(2.4375, 5.1875, 0.3125, 1.5625, x, y) && self.tab != 2 {
-            js::log("tab3");
+            self.stage.console().debug("tab3");
             self.untoggle_all();
             self.tab = 2;
             return;
@@ -324,7 +326,7 @@ impl Buttons {
                 if *toggled {
                     player.reveal();
                 } else {
-                    player.sneak(now);
+                    player.sneak(now, &mut self.stage.rng());
                 }
                 *toggled = !*toggled;
             }
@@ -333,7 +335,7 @@ impl Buttons {
                 if *toggled {
                     player.reveal();
                 } else {
-                    player.hide();
+                    player.hide(&mut self.stage.rng());
                 }
                 *toggled = !*toggled;
             }
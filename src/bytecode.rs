@@ -0,0 +1,219 @@
+//! Converts actor byte code to and from the text the actors were originally written in. Each
+//! line is one action, `<state>=<ACTION> <params...>`, e.g.
+//! ```text
+//! 000=CREATESELF 64 // speaker, nonblocker
+//! 001=SETMESSAGE 67 // This chest is locked.
+//! 040=GIVEEXP 0 50
+//! ```
+//! Parameters are the raw u16s the action pops off the stack. Anything after `//` is a comment.
+//! The byte code is a list of actions, each a big endian u16 state, u16 action id, then the
+//! action's parameters, all base64 encoded.
+use crate::{
+    actor::ACTION_SIZE,
+    data::{PROPS, WORLD},
+    game::InvalidDataError,
+};
+use base64::{engine::general_purpose, Engine as _};
+use std::fmt::Write as _;
+
+/// The name of every action in the text syntax, by action id
+const ACTION_NAMES: [(u16, &str); 37] = [
+    (0x0006, "ATTACK"),
+    (0x010a, "SETINTEL"),
+    (0x0206, "CREATESELF"),
+    (0x0308, "GIVEITEM"),
+    (0x0408, "SETTEAM"),
+    (0x050e, "WAIT"),
+    (0x060e, "IF"),
+    (0x0808, "SETSTATE"),
+    (0x0a06, "SETMESSAGE"),
+    (0x0b06, "TELLMESSAGE"),
+    (0x0c06, "ADDVAR"),
+    (0x0d06, "SUBVAR"),
+    (0x0e06, "SETVAR"),
+    (0x0f08, "MOVE"),
+    (0x1008, "DROPITEM"),
+    (0x1108, "TAKEITEM"),
+    (0x1208, "SETLOC"),
+    (0x1306, "ADDSELLITEM"),
+    (0x1406, "EXECUTETRADE"),
+    (0x1506, "REMOVE"),
+    (0x1606, "FREEZE"),
+    (0x1706, "UNFREEZE"),
+    (0x1808, "GIVEGOLD"),
+    (0x1908, "GIVEEXP"),
+    (0x1a08, "GIVEHENCHMEN"),
+    (0x1b08, "SETHEALTH"),
+    (0x1c08, "SPAWN"),
+    (0x1d08, "SETPORTRAIT"),
+    (0x1e0a, "PATROL"),
+    (0x1f0a, "WANDER"),
+    (0x2008, "SETLEVEL"),
+    (0x2106, "ADDQUESTLOG"),
+    (0x2206, "REMOVEQUESTLOG"),
+    (0x2308, "TAKEGOLD"),
+    (0x2608, "GIVEQUESTPET"),
+    (0x2708, "TAKEPET"),
+    (0x2808, "MAPSETLOC"),
+];
+
+/// One decoded action of an actor program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Action {
+    pub state: u16,
+    pub id: u16,
+    pub params: Vec<u16>,
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        action_name(self.id).unwrap()
+    }
+}
+
+/// The text name of an action id, e.g. 0x0206 is CREATESELF
+pub fn action_name(id: u16) -> Option<&'static str> {
+    ACTION_NAMES
+        .iter()
+        .find(|(action_id, _)| *action_id == id)
+        .map(|(_, name)| *name)
+}
+
+/// The action id of a text name, e.g. CREATESELF is 0x0206
+pub fn action_id(name: &str) -> Option<u16> {
+    ACTION_NAMES
+        .iter()
+        .find(|(_, action_name)| action_name.eq_ignore_ascii_case(name))
+        .map(|(id, _)| *id)
+}
+
+/// Decodes base64 byte code into actions
+pub fn decode(actions: &str) -> Result<Vec<Action>, InvalidDataError> {
+    let bytes = general_purpose::STANDARD
+        .decode(actions)
+        .map_err(|err| InvalidDataError::new(&format!("bad base64: {}", err)))?;
+    if bytes.len() % 2 != 0 {
+        return Err(InvalidDataError::new(
+            "byte code has an odd number of bytes",
+        ));
+    }
+    let words: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| (pair[0] as u16) << 8 | pair[1] as u16)
+        .collect();
+
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        if i + 2 > words.len() {
+            return Err(InvalidDataError::new(
+                "byte code ends in the middle of an action",
+            ));
+        }
+        let state = words[i];
+        let id = words[i + 1];
+        let size = *ACTION_SIZE
+            .get(&id)
+            .ok_or_else(|| InvalidDataError::new(&format!("unknown action {:#06x}", id)))?
+            as usize;
+        let params = words
+            .get(i + 2..i + 2 + size)
+            .ok_or_else(|| InvalidDataError::new("byte code ends in the middle of an action"))?;
+        result.push(Action {
+            state,
+            id,
+            params: params.to_vec(),
+        });
+        i += 2 + size;
+    }
+    Ok(result)
+}
+
+/// Encodes actions into base64 byte code
+pub fn encode(actions: &[Action]) -> String {
+    let mut bytes = Vec::new();
+    for action in actions {
+        let words = [action.state, action.id].into_iter();
+        for word in words.chain(action.params.iter().copied()) {
+            bytes.extend_from_slice(&word.to_be_bytes());
+        }
+    }
+    general_purpose::STANDARD.encode(bytes)
+}
+
+/// Turns base64 byte code into text, one action per line. Messages and props are named in
+/// comments.
+pub fn disassemble(actions: &str) -> Result<String, InvalidDataError> {
+    let mut text = String::new();
+    for action in decode(actions)? {
+        write!(text, "{:03}={}", action.state, action.name()).unwrap();
+        for param in action.params.iter() {
+            write!(text, " {}", param).unwrap();
+        }
+        if let Some(comment) = comment(&action) {
+            write!(text, " // {}", comment).unwrap();
+        }
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Turns text back into base64 byte code. Errors name the line they happened on.
+pub fn assemble(text: &str) -> Result<String, InvalidDataError> {
+    let mut actions = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split("//").next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let action = parse_line(line)
+            .map_err(|err| InvalidDataError::new(&format!("line {}: {}", i + 1, err)))?;
+        actions.push(action);
+    }
+    Ok(encode(&actions))
+}
+
+fn parse_line(line: &str) -> Result<Action, InvalidDataError> {
+    let (state, rest) = line
+        .split_once('=')
+        .ok_or_else(|| InvalidDataError::new("expected <state>=<ACTION>"))?;
+    let state = state
+        .trim()
+        .parse()
+        .map_err(|_| InvalidDataError::new(&format!("bad state '{}'", state)))?;
+
+    let mut words = rest.split_whitespace();
+    let name = words.next().unwrap_or("");
+    let id = action_id(name)
+        .ok_or_else(|| InvalidDataError::new(&format!("unknown action '{}'", name)))?;
+    let params = words
+        .map(|word| {
+            word.parse()
+                .map_err(|_| InvalidDataError::new(&format!("bad parameter '{}'", word)))
+        })
+        .collect::<Result<Vec<u16>, _>>()?;
+
+    let size = ACTION_SIZE[&id] as usize;
+    if params.len() != size {
+        return Err(InvalidDataError::new(&format!(
+            "{} takes {} parameters, got {}",
+            name.to_ascii_uppercase(),
+            size,
+            params.len()
+        )));
+    }
+    Ok(Action { state, id, params })
+}
+
+/// What a parameter refers to, for actions where that's unambiguous
+fn comment(action: &Action) -> Option<String> {
+    let param = *action.params.first()?;
+    match action.name() {
+        "SETMESSAGE" | "TELLMESSAGE" | "ADDQUESTLOG" | "REMOVEQUESTLOG" => {
+            let msg = WORLD.messages.get(&param.to_string())?;
+            Some(msg.value.replace('\n', " "))
+        }
+        "CREATESELF" | "ADDSELLITEM" => Some(PROPS.get(&param.to_string())?.name.clone()),
+        _ => None,
+    }
+}
@@ -1,12 +1,14 @@
-//! A simple async runtime for actor state machines
+//! A simple async runtime for actor state machines. Running actors can be inspected with
+//! `AldonGame::debug_actors`, paused at a state with `set_breakpoint` and run an action at a time
+//! with `step_actor`, for a dev panel.
 use crate::{
-    actor::Actor,
-    data::WORLD,
+    actor::{Actor, ActorError},
     game::{Dialog, InvalidDataError},
     js,
     stage::Stage,
     thrift::save::{self, ClassType, RaceType},
 };
+use serde_derive::Serialize;
 use std::{
     boxed::Box,
     cell::RefCell,
@@ -37,11 +39,14 @@ impl Cast {
             futures: VecDeque::new(),
             state: Rc::new(RefCell::new(SharedGameState {
                 now: 0.0,
-                vars: [0; 255],
+                vars: [0; VAR_COUNT],
                 response_by_actor: HashMap::new(),
                 quest_log: HashSet::new(),
                 state_interrupt_by_actor: HashMap::new(),
                 map_change_request: None,
+                breakpoints: HashSet::new(),
+                paused_actors: HashSet::new(),
+                stepping_actors: HashSet::new(),
             })),
             stage,
             dialog,
@@ -63,7 +68,7 @@ impl Cast {
             .as_ref()
             .ok_or(InvalidDataError::new("vars field missing"))?;
 
-        let mut vars: [u8; 255] = [0; 255];
+        let mut vars = [0; VAR_COUNT];
         for (i, var) in vars_vec.iter().enumerate() {
             vars[i] = *var as u8;
         }
@@ -81,7 +86,7 @@ impl Cast {
             .try_into()
             .map_err(|_| InvalidDataError::new("map_id not u16"))?;
 
-        let mut actor_save_by_id: HashMap<u16, save::Actor> = save
+        let actor_save_by_id: HashMap<u16, save::Actor> = save
             .actor_save_by_id
             .as_ref()
             .ok_or(InvalidDataError::new("actor_save_by_id field missing"))?
@@ -89,20 +94,6 @@ impl Cast {
             .map(|(actor_id, save)| (*actor_id as u16, save.clone()))
             .collect();
 
-        let bye_save_state = if let Some(bye_save) = actor_save_by_id.get(&173) {
-            bye_save.state
-        } else {
-            None
-        };
-
-        if let Some(elemental_save) = actor_save_by_id.get_mut(&171) {
-            let has_body = stage.get_body(171).is_some();
-            if !has_body && matches!(bye_save_state, Some(1)) {
-                elemental_save.state = Some(0);
-                elemental_save.dead = Some(false);
-            }
-        }
-
         let mut cast = Self {
             map_id,
             actors: Vec::new(),
@@ -114,6 +105,9 @@ impl Cast {
                 response_by_actor: HashMap::new(),
                 state_interrupt_by_actor: HashMap::new(),
                 map_change_request: None,
+                breakpoints: HashSet::new(),
+                paused_actors: HashSet::new(),
+                stepping_actors: HashSet::new(),
             })),
             stage,
             dialog,
@@ -132,7 +126,7 @@ impl Cast {
 
         // update with currently running actors
         for actor in &self.actors {
-            js::log(&format!(
+            self.stage.console().debug(&format!(
                 "{}: has state {:?}",
                 actor.res.name, actor.resume_state
             ));
@@ -156,6 +150,8 @@ impl Cast {
         let mut state = self.state.borrow_mut();
         state.response_by_actor.clear();
         state.state_interrupt_by_actor.clear();
+        state.paused_actors.clear();
+        state.stepping_actors.clear();
         drop(state);
 
         // save the state of actors in the previous map, to be restored on re-entry
@@ -163,10 +159,12 @@ impl Cast {
             self.actor_save_by_id.insert(actor.id(), actor.save());
         }
         self.actors.clear();
-        let name = &WORLD.maps[&map_id.to_string()].name;
-        js::log(&format!("loaded map: {}, {}", map_id, name));
+        let map = &self.stage.world().res.maps[&map_id.to_string()];
+        self.stage
+            .console()
+            .debug(&format!("loaded map: {}, {}", map_id, map.name));
 
-        for actor in &WORLD.maps[&map_id.to_string()].actors {
+        for actor in &map.actors {
             if let None = actor.actions {
                 continue;
             }
@@ -187,19 +185,25 @@ impl Cast {
                 let state: u16 = save.state.unwrap().try_into().unwrap();
 
                 if from_save {
-                    js::log(&format!("{}: resume to {}", actor.res.name, state));
+                    self.stage
+                        .console()
+                        .debug(&format!("{}: resume to {}", actor.res.name, state));
                     actor.resume(state);
                 } else {
-                    js::log(&format!("{}: resume_after_init", actor.res.name));
+                    self.stage
+                        .console()
+                        .debug(&format!("{}: resume_after_init", actor.res.name));
                     actor.resume_after_init(state);
                 }
-                js::log(&format!(
+                self.stage.console().debug(&format!(
                     "{}: constructed with state {:?}",
                     actor.res.name, actor.resume_state
                 ));
                 Rc::new(actor)
             } else {
-                js::log(&format!("{}: constructed without state", actor.name,));
+                self.stage
+                    .console()
+                    .debug(&format!("{}: constructed without state", actor.name,));
                 Rc::new(Actor::new(
                     self.state.clone(),
                     self.stage.clone(),
@@ -255,6 +259,75 @@ impl Cast {
     }
 }
 
+/// Debugging actors. Breakpoints and stepping pause an actor between actions, so an actor
+/// waiting on a condition or a response only stops once it's done waiting.
+impl Cast {
+    /// The actors on the map that haven't finished
+    pub fn debug_actors(&self) -> Vec<ActorInfo> {
+        self.actors
+            .iter()
+            .filter(|actor| !actor.dead.get())
+            .map(|actor| actor.info())
+            .collect()
+    }
+
+    /// Pauses `actor_id` before it runs the first action of `state`
+    pub fn set_breakpoint(&self, actor_id: u16, state: u16) {
+        self.state
+            .borrow_mut()
+            .breakpoints
+            .insert((actor_id, state));
+    }
+
+    pub fn clear_breakpoint(&self, actor_id: u16, state: u16) {
+        self.state
+            .borrow_mut()
+            .breakpoints
+            .remove(&(actor_id, state));
+    }
+
+    /// Every (actor id, state) with a breakpoint
+    pub fn breakpoints(&self) -> Vec<(u16, u16)> {
+        let mut breakpoints: Vec<_> = self.state.borrow().breakpoints.iter().copied().collect();
+        breakpoints.sort();
+        breakpoints
+    }
+
+    /// Lets a paused actor run one action, then pauses it again. A running actor is paused
+    /// before its next action.
+    pub fn step_actor(&self, actor_id: u16) {
+        let mut state = self.state.borrow_mut();
+        if !state.paused_actors.insert(actor_id) {
+            state.stepping_actors.insert(actor_id);
+        }
+    }
+
+    /// Lets a paused actor run freely again
+    pub fn continue_actor(&self, actor_id: u16) {
+        let mut state = self.state.borrow_mut();
+        state.paused_actors.remove(&actor_id);
+        state.stepping_actors.remove(&actor_id);
+    }
+
+    /// The vars shared by all actors
+    pub fn debug_vars(&self) -> Vec<u8> {
+        self.state.borrow().vars.to_vec()
+    }
+}
+
+/// What an actor is doing, for debugging
+#[derive(Debug, Clone, Serialize)]
+pub struct ActorInfo {
+    pub id: u16,
+    pub name: String,
+    pub state: u16,
+    /// The condition or response the actor is waiting on, if any
+    pub waiting_on: Option<String>,
+    /// Parameters left on the stack, the next one to be popped first
+    pub stack: Vec<u16>,
+    pub paused: bool,
+}
+
 /// Conditions that can be expressed in actor state machines with `wait <condition>` or `if <condition>`
 #[derive(Debug)]
 pub(crate) enum Cond {
@@ -276,8 +349,16 @@ pub(crate) enum Cond {
 }
 
 impl Cond {
-    pub fn new(cond_type: u16, param_a: u16, param_b: u16) -> Self {
-        match cond_type {
+    pub fn new(cond_type: u16, param_a: u16, param_b: u16) -> Result<Self, ActorError> {
+        // vars and races are packed in the high byte of param_a
+        let var = (param_a >> 8) as usize;
+        if (5..=7).contains(&cond_type) && var >= VAR_COUNT {
+            return Err(ActorError::BadCondition(format!(
+                "var {} out of range",
+                var
+            )));
+        }
+        let cond = match cond_type {
             0 => Cond::PickedUp { actor_id: param_a },
             1 => Cond::Dead { actor_id: param_a },
             2 => Cond::Level {
@@ -303,7 +384,15 @@ impl Cond {
             },
             9 => Cond::Class {
                 actor_id: param_a,
-                kind: ClassType::try_from((param_b >> 8) as i32).unwrap(),
+                kind: match ClassType((param_b >> 8) as i32) {
+                    kind if ClassType::ENUM_VALUES.contains(&kind) => kind,
+                    kind => {
+                        return Err(ActorError::BadCondition(format!(
+                            "unknown class {:?}",
+                            kind
+                        )))
+                    }
+                },
             },
             11 => Cond::PlayerHasGold {
                 gold: param_a as i32,
@@ -311,7 +400,12 @@ impl Cond {
             12 => Cond::PlayerHenchmen { prop_id: param_a },
             13 => Cond::PlayerSummoned { prop_id: param_a },
             14 => Cond::Race {
-                kind: RaceType::from_u16(param_a >> 8),
+                kind: match param_a >> 8 {
+                    0 => RaceType::HUMAN,
+                    1 => RaceType::ELF,
+                    2 => RaceType::DWARF,
+                    race => return Err(ActorError::BadCondition(format!("unknown race {}", race))),
+                },
             },
             15 => Cond::PlayerQuestPet { prop_id: param_a },
             16 => Cond::NotLocation {
@@ -319,8 +413,14 @@ impl Cond {
                 x: (param_b >> 8) as f64,
                 y: (param_b & 0xff) as f64,
             },
-            _ => panic!("Unrecognized cond type {}", cond_type),
-        }
+            _ => {
+                return Err(ActorError::BadCondition(format!(
+                    "unknown type {}",
+                    cond_type
+                )))
+            }
+        };
+        Ok(cond)
     }
 
     pub fn satisfied(&self, stage: &Stage, state: &mut SharedGameState) -> bool {
@@ -466,6 +566,31 @@ impl Future for WaitResponseFuture {
     }
 }
 
+/// Async implementation for waiting while the debugger has paused an actor
+pub(crate) struct DebugPauseFuture {
+    pub state: Rc<RefCell<SharedGameState>>,
+    pub actor_id: u16,
+}
+
+impl Future for DebugPauseFuture {
+    type Output = ();
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
+
+        if !state.paused_actors.contains(&self.actor_id)
+            || state.stepping_actors.remove(&self.actor_id)
+        {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
 /// Useful to allow all actors to init before allowing any one to run to completion
 pub(crate) async fn yield_now() {
     YieldNowFuture { yielded: false }.await;
@@ -518,12 +643,15 @@ impl From<u8> for Response {
     }
 }
 
+/// The number of vars shared by all actors
+pub(crate) const VAR_COUNT: usize = 255;
+
 pub struct SharedGameState {
     // TODO: make these all Cells and RefCells
     pub now: f64,
 
     /// State machine's have 255 variables to represent things like progress on quests
-    pub vars: [u8; 255],
+    pub vars: [u8; VAR_COUNT],
 
     /// This is used as a channel to notify an actor that the player has given a response to it
     pub response_by_actor: HashMap<u16, Response>,
@@ -535,4 +663,13 @@ pub struct SharedGameState {
 
     // This acts as a channel for actors to set the state of other actors
     pub state_interrupt_by_actor: HashMap<u16, u16>,
+
+    /// Actors pause before running the first action of these (actor id, state)
+    pub breakpoints: HashSet<(u16, u16)>,
+
+    /// Actors stopped by the debugger before their next action
+    pub paused_actors: HashSet<u16>,
+
+    /// Paused actors allowed to run one more action
+    pub stepping_actors: HashSet<u16>,
 }
@@ -0,0 +1,94 @@
+//! Game time. Hosts pass `AldonGame::update` the wall clock; the game runs on its own clock that
+//! can be paused and sped up or slowed down, and that catches up on a long frame a step at a time
+//! instead of in one jump. Past 5 seconds the rest of a frame is skipped, so a tab coming back
+//! from the background doesn't jump ahead.
+
+/// Slowest and fastest the game can run
+pub const MIN_SPEED: f64 = 0.5;
+pub const MAX_SPEED: f64 = 4.0;
+
+/// Frames longer than this (in game ms) are simulated in steps of `STEP`
+const MAX_FRAME: f64 = 250.0;
+const STEP: f64 = 50.0;
+
+/// The most game time one update catches up on. Past this, e.g. after a tab was in the
+/// background, the rest of the time is skipped as though the game was paused.
+const MAX_CATCH_UP: f64 = 5000.0;
+
+#[derive(Debug, Clone)]
+pub(crate) struct GameClock {
+    /// The wall time of the last update, None before the first
+    wall: Option<f64>,
+    now: f64,
+    paused: bool,
+    speed: f64,
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        Self {
+            wall: None,
+            now: 0.0,
+            paused: false,
+            speed: 1.0,
+        }
+    }
+
+    /// The game time, which starts at the wall time of the first update
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    /// The wall time of the last update
+    pub fn wall(&self) -> f64 {
+        self.wall.unwrap_or(0.0)
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Runs the game at `speed` times the wall clock, clamped to `MIN_SPEED..=MAX_SPEED`
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = if speed.is_nan() {
+            1.0
+        } else {
+            speed.clamp(MIN_SPEED, MAX_SPEED)
+        };
+    }
+
+    /// Moves the wall clock to `wall` and returns the game times to update the game at, oldest
+    /// first. None while paused.
+    pub fn advance(&mut self, wall: f64) -> Vec<f64> {
+        let Some(last) = self.wall.replace(wall) else {
+            self.now = wall;
+            return vec![wall];
+        };
+        if self.paused {
+            return Vec::new();
+        }
+        let delta = ((wall - last).max(0.0) * self.speed).min(MAX_CATCH_UP);
+        let target = self.now + delta;
+        if delta <= MAX_FRAME {
+            self.now = target;
+            return vec![target];
+        }
+
+        let mut steps = Vec::new();
+        while self.now + STEP < target {
+            self.now += STEP;
+            steps.push(self.now);
+        }
+        self.now = target;
+        steps.push(target);
+        steps
+    }
+}
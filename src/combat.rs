@@ -3,29 +3,33 @@ use crate::{
     aldon_log,
     body::Body,
     condition::{self, Condition},
-    data::{PropTypeRes, SpellTarget, PROPS, SPELLS},
-    game::CONSOLE,
+    console::Console,
+    data::{
+        DamageType, EffectRes, ProcAction, ProcTrigger, PropTypeRes, SpellTarget, PROPS, SPELLS,
+    },
+    events::GameEvent,
     js,
     stats::{intelligence_to_chance_cast, luck_to_modifier, strength_to_damage},
     thrift::save::ConditionType,
 };
-use rand::{seq::SliceRandom, Rng};
-use std::{cmp::max, fmt::Write, rc::Rc, vec::Vec};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use serde_derive::Serialize;
+use std::{cmp::max, rc::Rc, vec::Vec};
 
 fn weapon_damage(
     prop_id: u16,
     attacker_level: i32,
     attacker_strength: i32,
     opponent_level: i32,
+    rng: &mut StdRng,
 ) -> i32 {
     let factor = max(1, (attacker_level - opponent_level) / 2);
-    if matches!(prop_id, 244 | 314 | 392 | 237) {
-        let mut rng = rand::thread_rng();
+    let prop = &PROPS[&prop_id.to_string()];
+    if prop.spell_id().is_some() {
         let damage_min = 1;
         let damage_max = 2 + 3 * attacker_level;
         return rng.gen_range(damage_min..=damage_max) * factor;
     }
-    let prop = &PROPS[&prop_id.to_string()];
 
     if let PropTypeRes::Weapon {
         damage_min,
@@ -33,7 +37,6 @@ fn weapon_damage(
         ..
     } = prop.kind
     {
-        let mut rng = rand::thread_rng();
         let dmg = rng.gen_range(damage_min..=damage_max) + strength_to_damage(attacker_strength);
         return factor * max(dmg, 1);
     }
@@ -44,34 +47,70 @@ fn weapon_damage(
     0
 }
 
-pub(crate) fn spell_damage(spell_id: u16, attacker_level: i32, opponent_level: i32) -> i32 {
+/// Spell weapons do the damage of their spell
+fn weapon_damage_type(prop_id: u16) -> DamageType {
+    let prop = &PROPS[&prop_id.to_string()];
+    if let Some(spell_id) = prop.spell_id() {
+        return SPELLS[&spell_id.to_string()].damage_type;
+    }
+    match prop.kind {
+        PropTypeRes::Weapon { damage_type, .. } => damage_type,
+        _ => DamageType::Physical,
+    }
+}
+
+pub(crate) fn spell_damage(
+    spell_id: u16,
+    attacker_level: i32,
+    opponent_level: i32,
+    rng: &mut StdRng,
+) -> i32 {
     let factor = max(1, (attacker_level - opponent_level) / 2) * max(1, attacker_level / 2);
     let dmg_max = spell_base_damage(spell_id);
     if dmg_max == 0 {
         // gen_range(1..=0) panics
         return 0;
     }
-    let mut rng = rand::thread_rng();
     rng.gen_range(1..=dmg_max) * factor
 }
 
-fn make_spell_attack(spell_id: u16, attacker: &Body, target: &Body) -> (i32, BattleEventType) {
+fn make_spell_attack(
+    spell_id: u16,
+    attacker: &Body,
+    target: &Body,
+    rng: &mut StdRng,
+    console: &Console,
+) -> (i32, BattleEventType, DamageType) {
+    let damage_type = SPELLS[&spell_id.to_string()].damage_type;
     let chance_hit = intelligence_to_chance_cast(attacker.inteligence());
-    let mut rng = rand::thread_rng();
     let roll: i32 = rng.gen_range(1..=100);
-    aldon_log!("-{} needs < {}, rolls {}-", attacker.name, chance_hit, roll);
+    aldon_log!(
+        console,
+        "-{} needs < {}, rolls {}-",
+        attacker.name,
+        chance_hit,
+        roll
+    );
 
     if roll > chance_hit {
-        aldon_log!("-{} fizzles-", attacker.name);
-        return (0, BattleEventType::Fizzle);
+        aldon_log!(console, "-{} fizzles-", attacker.name);
+        return (0, BattleEventType::Fizzle, damage_type);
     }
     return (
-        spell_damage(spell_id, attacker.level(), target.level()),
+        spell_damage(spell_id, attacker.level(), target.level(), rng),
         BattleEventType::Hit,
+        damage_type,
     );
 }
 
-fn make_weapon_attack(prop_id: u16, attacker: &Body, target: &Body) -> (i32, BattleEventType) {
+fn make_weapon_attack(
+    prop_id: u16,
+    attacker: &Body,
+    target: &Body,
+    rng: &mut StdRng,
+    console: &Console,
+) -> (i32, BattleEventType, DamageType) {
+    let damage_type = weapon_damage_type(prop_id);
     let bonus = attacker.level() / 2;
     let chance_hit = clamp(
         54 + attacker.attack_hit_bonus() - target.armor_class()
@@ -81,47 +120,70 @@ fn make_weapon_attack(prop_id: u16, attacker: &Body, target: &Body) -> (i32, Bat
         5,
         99,
     );
-    let mut rng = rand::thread_rng();
     let roll: i32 = rng.gen_range(1..=100);
-    aldon_log!("-{} needs < {}, rolls {}-", attacker.name, chance_hit, roll);
+    aldon_log!(
+        console,
+        "-{} needs < {}, rolls {}-",
+        attacker.name,
+        chance_hit,
+        roll
+    );
     let crit_chance = max(1, 5 + luck_to_modifier(attacker.luck()));
 
     if roll <= crit_chance {
-        aldon_log!("*{} CRITICALLY HITS {}*", attacker.name, target.name);
-        let dmg = 2 * attacker.attack_damage(prop_id, target.level());
-        (dmg, BattleEventType::Crit)
+        aldon_log!(
+            console,
+            "*{} CRITICALLY HITS {}*",
+            attacker.name,
+            target.name
+        );
+        let dmg = 2 * attacker.attack_damage(prop_id, target.level(), rng);
+        (dmg, BattleEventType::Crit, damage_type)
     } else if roll < chance_hit {
         let dmg = weapon_damage(
             prop_id,
             attacker.level(),
             attacker.strength(),
             target.level(),
+            rng,
         );
-        (dmg, BattleEventType::Hit)
+        (dmg, BattleEventType::Hit, damage_type)
     } else {
-        (0, BattleEventType::Miss)
+        (0, BattleEventType::Miss, damage_type)
     }
 }
 
-fn make_attack_impl(attacker: &Body, target: &Body) -> (i32, BattleEventType) {
+fn make_attack_impl(
+    attacker: &Body,
+    target: &Body,
+    rng: &mut StdRng,
+    console: &Console,
+) -> (i32, BattleEventType, DamageType) {
     let Some(weapon) = attacker.equiped_weapon() else {
-        return (0, BattleEventType::Miss);
+        return (0, BattleEventType::Miss, DamageType::Physical);
     };
-    if let Some(spell_id) = weapon_to_spell(weapon) {
-        return make_spell_attack(spell_id, attacker, target);
+    if let Some(spell_id) = PROPS[&weapon.to_string()].spell_id() {
+        return make_spell_attack(spell_id, attacker, target, rng, console);
     }
-    return make_weapon_attack(weapon, attacker, target);
+    return make_weapon_attack(weapon, attacker, target, rng, console);
 }
 
-pub fn make_attack(now: f64, attacker: Rc<Body>, target: Rc<Body>) -> Vec<Attack> {
+pub fn make_attack(
+    now: f64,
+    attacker: Rc<Body>,
+    target: Rc<Body>,
+    rng: &mut StdRng,
+    console: &Console,
+) -> Vec<Attack> {
     let mut result = Vec::new();
     let Some(prop_id) = attacker.equiped_weapon() else {
         return result;
     };
-    let (damage, event) = make_attack_impl(&attacker, &target);
+    let (damage, event, damage_type) = make_attack_impl(&attacker, &target, rng, console);
     let strike = Strike {
         target: target.clone(),
         damage,
+        damage_type,
         event,
     };
     if attacker.is_attack_ranged() {
@@ -142,39 +204,83 @@ pub fn make_attack(now: f64, attacker: Rc<Body>, target: Rc<Body>) -> Vec<Attack
         return result;
     }
     // On a hit some weapons have special missiles
-    // blood blade
-    if prop_id == 248 {
-        let effect = MissileEffect::Heal {
+    let prop = &PROPS[&prop_id.to_string()];
+    let damage = target.damage_taken(damage, damage_type);
+    let killed = target.get_health() <= damage;
+    for effect in &prop.effects {
+        let EffectRes::Proc {
+            trigger,
+            chance,
+            always,
+            action,
+        } = effect
+        else {
+            continue;
+        };
+        if *trigger == ProcTrigger::Kill && !killed {
+            continue;
+        }
+        if !always {
+            let chance = clamp(chance + luck_to_modifier(attacker.luck()), 1, 100);
+            let roll: i32 = rng.gen_range(1..=100);
+            if roll > chance {
+                continue;
+            }
+        }
+        aldon_log!(console, "*{}'s {} flares!*", attacker.name, prop.name);
+        attacker.battle_event(now, BattleEventType::Proc);
+        attacker.emit(GameEvent::Proc {
+            body: attacker.as_ref().into(),
+            prop_id,
+        });
+        let missile = proc_missile(now, *action, attacker.clone(), target.clone(), damage, rng);
+        result.push(Attack::Range(missile));
+    }
+    result
+}
+
+/// `damage` is what the hit took from `target`, after its resistance
+fn proc_missile(
+    now: f64,
+    action: ProcAction,
+    attacker: Rc<Body>,
+    target: Rc<Body>,
+    damage: i32,
+    rng: &mut StdRng,
+) -> Missile {
+    let effect = match action {
+        ProcAction::CastSpell { spell_id } => {
+            return match SPELLS[&spell_id.to_string()].target {
+                SpellTarget::Enemy => targeted_spell(now, spell_id, attacker, target, rng),
+                SpellTarget::Friend => {
+                    targeted_spell(now, spell_id, attacker.clone(), attacker, rng)
+                }
+                SpellTarget::None | SpellTarget::Corpse => {
+                    let (x, y) = target.moving_to();
+                    spell(now, spell_id, attacker, x, y)
+                }
+            };
+        }
+        ProcAction::Poison { random } => MissileEffect::Condition {
+            target: target.clone(),
+            condition: if random {
+                condition::random_poison(now)
+            } else {
+                condition::poison(now)
+            },
+        },
+        ProcAction::Drain => MissileEffect::Heal {
             target: attacker.clone(),
             amount: damage / 2,
             show_animation: false,
-        };
-        let missile = Missile::from_combatants(now, attacker.clone(), target.clone(), vec![effect]);
-        let attack = Attack::Range(missile);
-        result.push(attack);
-    }
-    // storm sword, storm hammer
-    if matches!(prop_id, 390 | 249) && target.get_health() <= damage {
-        let effect = MissileEffect::Explosion {
-            splash_damage: 21,
+        },
+        ProcAction::Explode { damage } => MissileEffect::Explosion {
+            splash_damage: damage,
             splash_kind: MissileType::Fire,
             size: ExplosionSize::Medium,
-        };
-        let missile = Missile::from_combatants(now, attacker.clone(), target.clone(), vec![effect]);
-        let attack = Attack::Range(missile);
-        result.push(attack);
-    }
-    // Bite, Poison
-    if prop_id == 198 {
-        let effect = MissileEffect::Condition {
-            target: target.clone(),
-            condition: condition::random_poison(now),
-        };
-        let missile = Missile::from_combatants(now, attacker.clone(), target.clone(), vec![effect]);
-        let attack = Attack::Range(missile);
-        result.push(attack);
-    }
-    result
+        },
+    };
+    Missile::from_combatants(now, attacker, target, vec![effect])
 }
 
 // TODO: should be part of static resources
@@ -205,6 +311,17 @@ pub enum MissileType {
     Poison,
 }
 
+impl MissileType {
+    pub fn damage_type(&self) -> DamageType {
+        match self {
+            MissileType::Fire | MissileType::Bonfire => DamageType::Fire,
+            MissileType::Ice => DamageType::Ice,
+            MissileType::Poison => DamageType::Poison,
+            MissileType::Rock | MissileType::Magic => DamageType::Physical,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum ExplosionSize {
     Medium,
@@ -215,6 +332,7 @@ pub enum MissileEffect {
     Strike(Strike),
     SplashDamage {
         amount: i32,
+        damage_type: DamageType,
     },
     Explosion {
         splash_damage: i32,
@@ -250,6 +368,7 @@ pub enum Attack {
 
 pub struct Strike {
     pub damage: i32,
+    pub damage_type: DamageType,
     pub event: BattleEventType,
     pub target: Rc<Body>,
 }
@@ -259,11 +378,14 @@ pub struct Missile {
     x: f64,
     y: f64,
     attacker: Rc<Body>,
+    // Only drawn by the renderer
+    #[cfg_attr(not(feature = "web"), allow(dead_code))]
     kind: MissileType,
     effects: Vec<MissileEffect>,
 }
 
 /// Stripped representation to hand over to rendering
+#[cfg(feature = "web")]
 pub struct MissileInfo {
     pub x: f64,
     pub y: f64,
@@ -343,6 +465,7 @@ impl Missile {
         (self.x, self.y) = self.motion.tween(now);
     }
 
+    #[cfg(feature = "web")]
     pub fn info(&self) -> MissileInfo {
         MissileInfo {
             x: self.x,
@@ -352,189 +475,22 @@ impl Missile {
     }
 }
 
-/// How much experience the player gets for defeating a monster
-pub(crate) fn monster_reward(prop_id: u16, level: i32) -> (i32, i32) {
-    // * = observered directly, otherwise I'm just guessing
-    // TODO: should be part of static resource
-    let base = match prop_id {
-        // troll*
-        112 => 40,
-
-        // spider*
-        200 => 31,
-
-        // lesser fire elemental*
-        236 => 32,
-
-        // lesser air elemental*
-        239 => 32,
-
-        // lesser ice elemental*
-        228 => 35,
-
-        // lesser earth elemental*
-        229 => 36,
-
-        // greater air elemental
-        238 => 37,
-
-        // greater fire elemental
-        235 => 37,
-
-        // greater ice elemental
-        224 => 40,
-
-        // greater earth elemental
-        225 => 41,
-
-        // large rat*
-        30 => 30,
-
-        // demon
-        361 => 40,
-
-        // dragon*
-        362 => 37,
-
-        // dragonling*
-        364 => 34,
-
-        // large dog
-        61 => 30,
-
-        // large cat
-        63 => 30,
-
-        // goblin*
-        29 => 33,
-
-        // goblin lobber*
-        110 => 33,
-
-        // goblin shaman*
-        // lvl 1 gp: 63
-        319 => 31,
-
-        // goblin chieftan
-        115 => 34,
-
-        // orc*
-        81 => 36,
-
-        // orc warlord*
-        113 => 37,
-
-        // bandit*
-        114 => 32,
-
-        // ogre*
-        118 => 36,
-
-        // ogre lobber*
-        119 => 36,
-
-        // female human
-        41 => 30,
-
-        // female human fighter
-        40 => 32,
-
-        // female human thief
-        43 => 32,
-
-        // female human priest
-        42 => 32,
-
-        // female human mage
-        31 => 32,
-
-        // female dwarf
-        35 => 30,
-
-        // female dwarf fighter
-        32 => 32,
-
-        // female dwarf thief
-        34 => 32,
-
-        // femal dwarf priest
-        33 => 32,
-
-        // female elf
-        38 => 30,
-
-        // female elf fighter,
-        37 => 32,
-
-        // female elf mage,
-        36 => 32,
-
-        // female elf thief
-        39 => 32,
-
-        // female dark mage
-        336 => 40,
-
-        // male human
-        55 => 30,
-
-        // male human fighter
-        54 => 32,
-
-        // male human thief
-        88 => 32,
-
-        // male human priest*
-        56 => 32,
-
-        // male human mage
-        45 => 32,
-
-        // male dwarf
-        49 => 30,
-
-        // male dwarf fighter
-        46 => 32,
-
-        // male dwarf thief
-        48 => 32,
-
-        // male dwarf priest
-        47 => 32,
-
-        // male elf
-        52 => 30,
-
-        // male elf fighter
-        51 => 32,
-
-        // male elf thief
-        53 => 32,
-
-        // male elf mage
-        50 => 32,
-
-        // death knight
-        395 => 40,
-
-        // assassin
-        402 => 40,
-
-        // giant
-        44 => 40,
-
-        // skeleton*
-        57 => 33,
-
-        // zombie*
-        59 => 33,
+/// The exp and gold for killing `prop_id` at `level`, from its reward in props.json
+pub(crate) fn monster_reward(prop_id: u16, level: i32, rng: &mut StdRng) -> (i32, i32) {
+    let reward = PROPS[&prop_id.to_string()].reward();
+    let (gold_min, gold_max) = reward.gold(level);
+    (reward.exp(level), rng.gen_range(gold_min..=gold_max))
+}
 
-        _ => 2, // minimum that generates a valid range below
-    };
-    let exp = base * level;
-    let mut rng = rand::thread_rng();
-    let gp = rng.gen_range(1..=exp / 2);
-    (exp, gp)
+/// The items `prop_id` drops when it's killed
+pub(crate) fn monster_loot(prop_id: u16, rng: &mut StdRng) -> Vec<u16> {
+    let reward = PROPS[&prop_id.to_string()].reward();
+    reward
+        .loot
+        .iter()
+        .filter(|loot| rng.gen_range(1..=100) <= loot.chance)
+        .map(|loot| loot.prop_id)
+        .collect()
 }
 
 // TODO: should be part of static resource
@@ -574,25 +530,6 @@ fn spell_base_damage(spell_id: u16) -> i32 {
     }
 }
 
-fn weapon_to_spell(prop_id: u16) -> Option<u16> {
-    let spell = match prop_id {
-        // Life Strike
-        244 => 14, // Life Drain
-
-        // Ice Strike
-        314 => 4, // Ice Spear
-
-        // Blizzard Staff
-        392 => 5, // Ice Storm
-
-        // Fire Strike
-        237 => 0, // Fire Bolt
-
-        _ => return None,
-    };
-    Some(spell)
-}
-
 pub(crate) fn spell_to_missile_type(spell_id: u16) -> MissileType {
     match spell_id {
         // Fire Bolt, Fire Ball, Meteor
@@ -624,14 +561,16 @@ impl BattleEvent {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub(crate) enum BattleEventType {
+#[derive(Serialize, Debug, Copy, Clone, PartialEq)]
+pub enum BattleEventType {
     Hit,
     Miss,
     Crit,
     Fizzle,
     Condition1,
     Condition2,
+    /// A weapon's chance to do something more on a hit or kill came up
+    Proc,
 }
 
 /// Behavior for anything that moves from point a to point b
@@ -716,23 +655,25 @@ pub(crate) fn targeted_spell(
     spell_id: u16,
     caster: Rc<Body>,
     target: Rc<Body>,
+    rng: &mut StdRng,
 ) -> Missile {
-    let damage = spell_damage(spell_id, caster.level(), target.level());
+    let damage = spell_damage(spell_id, caster.level(), target.level(), rng);
     let spell = &SPELLS[&spell_id.to_string()];
     let mut effects = Vec::new();
     if matches!(spell.target, SpellTarget::Enemy) {
         let strike = MissileEffect::Strike(Strike {
             damage,
+            damage_type: spell.damage_type,
             event: BattleEventType::Hit,
             target: target.clone(),
         });
         effects.push(strike);
     }
     if spell_id == 14 {
-        // Life Drain
+        // Life Drain, half what the target loses
         let heal = MissileEffect::Heal {
             target: caster.clone(),
-            amount: damage / 2,
+            amount: target.damage_taken(damage, spell.damage_type) / 2,
             show_animation: false,
         };
         effects.push(heal);
@@ -941,8 +882,7 @@ pub(crate) fn targeted_spell(
 }
 
 // TODO: is this right?
-pub(crate) fn summon(spell_id: u16) -> u16 {
-    let mut rng = rand::thread_rng();
+pub(crate) fn summon(spell_id: u16, rng: &mut StdRng) -> u16 {
     let prop_ids = match spell_id {
         // Second Summon
         9 => vec![29, 110, 81], // goblin, goblin lobber, orc
@@ -961,5 +901,5 @@ pub(crate) fn summon(spell_id: u16) -> u16 {
         // First Summon
         8 | _ => vec![30, 29], // rat, goblin
     };
-    *prop_ids.choose(&mut rng).unwrap()
+    *prop_ids.choose(rng).unwrap()
 }
@@ -3,19 +3,20 @@ use crate::{
     aldon_log,
     body::Body,
     combat::BattleEventType,
-    game::{InvalidDataError, CONSOLE},
+    data::{DamageType, EffectRes, PROPS},
+    game::InvalidDataError,
     js,
     thrift::save::{self, ConditionSource, ConditionType},
 };
 use core::fmt::Debug;
-use rand::Rng;
-use std::{cell::Cell, fmt::Write, rc::Rc};
+use rand::{rngs::StdRng, Rng};
+use std::{cell::Cell, rc::Rc};
 use thrift::OrderedFloat;
 
 /// Behavior for how a condition changes over time, like the "course" of a disease
 trait Course: CloneBox {
     fn finished(&self, now: f64) -> bool;
-    fn update(&self, body: &Body, now: f64) -> bool;
+    fn update(&self, body: &Body, now: f64, rng: &mut StdRng) -> bool;
     fn save(&self, now: f64) -> Option<save::Course>;
 }
 
@@ -35,8 +36,8 @@ impl Condition {
         self.course.finished(now)
     }
 
-    pub fn update(&self, body: &Body, now: f64) {
-        if !self.course.update(body, now) {
+    pub fn update(&self, body: &Body, now: f64, rng: &mut StdRng) {
+        if !self.course.update(body, now, rng) {
             return;
         }
         match self.kind {
@@ -54,6 +55,7 @@ impl Condition {
                     now,
                     BattleEventType::Hit,
                     self.magnitude,
+                    DamageType::Poison,
                     None, /* attacker */
                 )
             }
@@ -157,7 +159,7 @@ impl Course for TimedCourse {
         now >= self.deadline
     }
 
-    fn update(&self, _body: &Body, _now: f64) -> bool {
+    fn update(&self, _body: &Body, _now: f64, _rng: &mut StdRng) -> bool {
         false
     }
 
@@ -208,7 +210,7 @@ impl Course for PeriodicCourse {
         false
     }
 
-    fn update(&self, _body: &Body, now: f64) -> bool {
+    fn update(&self, _body: &Body, now: f64, _rng: &mut StdRng) -> bool {
         if now < self.next_update.get() {
             return false;
         }
@@ -265,8 +267,8 @@ impl Course for PeriodicTimedCourse {
         self.timed.finished(now)
     }
 
-    fn update(&self, body: &Body, now: f64) -> bool {
-        self.periodic.update(body, now)
+    fn update(&self, body: &Body, now: f64, rng: &mut StdRng) -> bool {
+        self.periodic.update(body, now, rng)
     }
 
     fn save(&self, now: f64) -> Option<save::Course> {
@@ -306,7 +308,7 @@ impl Course for MovementCanceledCourse {
         self.moved.get()
     }
 
-    fn update(&self, body: &Body, _now: f64) -> bool {
+    fn update(&self, body: &Body, _now: f64, _rng: &mut StdRng) -> bool {
         if (body.x(), body.y()) != body.moving_to() {
             self.moved.set(true);
         }
@@ -340,7 +342,7 @@ impl Course for EquipedCourse {
         !self.equiped.get()
     }
 
-    fn update(&self, body: &Body, _now: f64) -> bool {
+    fn update(&self, body: &Body, _now: f64, _rng: &mut StdRng) -> bool {
         if body.is_equiped(self.item.clone()).is_none() {
             self.equiped.set(false);
             return false;
@@ -375,8 +377,8 @@ impl Course for EquipedPeriodicCourse {
         self.equiped.finished(now)
     }
 
-    fn update(&self, body: &Body, now: f64) -> bool {
-        self.equiped.update(body, now) && self.periodic.update(body, now)
+    fn update(&self, body: &Body, now: f64, rng: &mut StdRng) -> bool {
+        self.equiped.update(body, now, rng) && self.periodic.update(body, now, rng)
     }
 
     fn save(&self, _now: f64) -> Option<save::Course> {
@@ -430,13 +432,12 @@ impl Course for RandomCourse {
         self.finished.get()
     }
 
-    fn update(&self, body: &Body, now: f64) -> bool {
-        if !self.periodic.update(body, now) {
+    fn update(&self, body: &Body, now: f64, rng: &mut StdRng) -> bool {
+        if !self.periodic.update(body, now, rng) {
             return false;
         }
-        let mut rng = rand::thread_rng();
         let roll: i32 = rng.gen_range(1..=100);
-        aldon_log!("*Chance:{} Roll: {}*", self.chance, roll);
+        aldon_log!(body.console(), "*Chance:{} Roll: {}*", self.chance, roll);
 
         if roll >= self.chance {
             self.finished.set(true);
@@ -465,12 +466,13 @@ pub(crate) fn potion(now: f64, kind: ConditionType) -> Condition {
     }
 }
 
-pub(crate) fn potion_regen(now: f64) -> Condition {
+/// Health or mana back every 2 seconds for 90 seconds
+pub(crate) fn potion_regen(now: f64, kind: ConditionType) -> Condition {
     let course =
         PeriodicTimedCourse::new(now, 2.0 /* period_sec */, 90.0 /* duration_sec */);
 
     Condition {
-        kind: ConditionType::HEALTH,
+        kind,
         source: ConditionSource::POTION,
         course: Box::new(course),
         magnitude: 1,
@@ -507,10 +509,10 @@ pub(crate) fn item(kind: ConditionType, magnitude: i32, body: Rc<Body>) -> Condi
     }
 }
 
-pub(crate) fn item_regen(now: f64, body: Rc<Body>) -> Condition {
+pub(crate) fn item_regen(now: f64, kind: ConditionType, body: Rc<Body>) -> Condition {
     let course = EquipedPeriodicCourse::new(now, body, 2.0 /* period_sec */);
     Condition {
-        kind: ConditionType::HEALTH,
+        kind,
         source: ConditionSource::ITEM,
         course: Box::new(course),
         magnitude: 1,
@@ -601,73 +603,20 @@ pub(crate) fn stat(now: f64, kind: ConditionType, magnitude: i32) -> Condition {
         course: Box::new(course),
     }
 }
-pub(crate) fn for_item(now: f64, body: Rc<Body>) -> Option<Condition> {
-    let effect = match body.prop_id {
-        // Ring of Regeneration
-        322 => item_regen(now, body),
-
-        // ring of force
-        173 => item(save::ConditionType::STRENGTH, 1 /* manitude */, body),
-
-        // ring of alacrity
-        174 => item(save::ConditionType::DEXTERITY, 1 /* manitude */, body),
-
-        // ring of intellect
-        175 => item(
-            save::ConditionType::INTELIGENCE,
-            1, /* manitude */
-            body,
-        ),
-
-        // Ring of Thieves
-        177 => item(save::ConditionType::DEXTERITY, 2 /* manitude */, body),
-
-        // Ring of Battle
-        178 => item(save::ConditionType::STRENGTH, 2 /* manitude */, body),
-
-        // Mystic Ring
-        188 => item(
-            save::ConditionType::INTELIGENCE,
-            2, /* manitude */
-            body,
-        ),
-
-        // Guantlets of power
-        259 => item(save::ConditionType::STRENGTH, 1 /* manitude */, body),
-
-        // Lucky charm
-        262 => item(save::ConditionType::LUCK, 1 /* manitude */, body),
-
-        // boots, speed
-        120 => item(save::ConditionType::SPEED, 1 /* manitude */, body),
-
-        // Prayer Beads
-        375 => item(
-            save::ConditionType::INTELIGENCE,
-            2, // magnitude
-            body,
-        ),
-
-        // Holy Breastplate
-        253 => item(
-            save::ConditionType::INTELIGENCE,
-            1, // magnitude
-            body,
-        ),
-
-        // Holy Ring
-        189 => item(
-            save::ConditionType::INTELIGENCE,
-            1, // magnitude
-            body,
-        ),
-
-        // TODO: Silvery Bracers
-        // TODO: Spirit Cloak
-        // TODO: Blessed Ring
-        _ => return None,
-    };
-    Some(effect)
+
+/// The conditions an item gives while it's equipped, from its `effects` in props.json
+pub(crate) fn for_item(now: f64, body: Rc<Body>) -> Vec<Condition> {
+    let prop = &PROPS[&body.prop_id.to_string()];
+    prop.effects
+        .iter()
+        .filter_map(|effect| match effect {
+            EffectRes::StatBonus { stat, magnitude } => {
+                Some(item((*stat).into(), *magnitude, body.clone()))
+            }
+            EffectRes::Regen { stat } => Some(item_regen(now, (*stat).into(), body.clone())),
+            _ => None,
+        })
+        .collect()
 }
 
 impl Into<&str> for ConditionType {
@@ -0,0 +1,100 @@
+//! The aldon game console: the lines like "(you receive 12 exp.)" under the map, and the debug
+//! logs behind them. Each game has its own, shared with its stage and the bodies on it. The last
+//! 100 lines are kept for scrolling back and the debug logs since the last map change go with bug
+//! reports.
+use crate::js;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+/// Lines kept by default. About 10 fit on screen, the rest are scrollback.
+pub const DEFAULT_CAPACITY: usize = 100;
+
+/// A handle to a console, clones write to the same one
+#[derive(Clone, Debug)]
+pub struct Console(Rc<RefCell<ConsoleLines>>);
+
+#[derive(Debug)]
+struct ConsoleLines {
+    lines: VecDeque<String>,
+    capacity: usize,
+    /// Lines ever logged, including the ones dropped to stay under capacity
+    logged: usize,
+    debug_logs: Vec<String>,
+}
+
+impl Console {
+    /// A console keeping the last `capacity` lines
+    pub fn new(capacity: usize) -> Self {
+        Self(Rc::new(RefCell::new(ConsoleLines {
+            lines: VecDeque::new(),
+            capacity: capacity.max(1),
+            logged: 0,
+            debug_logs: Vec::new(),
+        })))
+    }
+
+    /// Adds a line for the player, which is also a debug log
+    pub fn log(&self, line: &str) {
+        let mut console = self.0.borrow_mut();
+        if console.lines.len() == console.capacity {
+            console.lines.pop_front();
+        }
+        console.lines.push_back(line.to_string());
+        console.logged += 1;
+        drop(console);
+        self.debug(line);
+    }
+
+    /// Records a debug log and passes it on to `js::log`
+    pub fn debug(&self, s: &str) {
+        self.0.borrow_mut().debug_logs.push(s.to_string());
+        js::log(s);
+    }
+
+    /// The lines kept, oldest first
+    pub fn lines(&self) -> Vec<String> {
+        self.0.borrow().lines.iter().cloned().collect()
+    }
+
+    /// How many lines have been logged, for telling which of `lines` are new
+    pub fn logged(&self) -> usize {
+        self.0.borrow().logged
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.0.borrow().capacity
+    }
+
+    /// Keeps the last `capacity` lines from now on, dropping older ones
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut console = self.0.borrow_mut();
+        console.capacity = capacity.max(1);
+        while console.lines.len() > console.capacity {
+            console.lines.pop_front();
+        }
+    }
+
+    /// Debug logs since the last map change, not just the console lines
+    pub fn debug_logs(&self) -> String {
+        self.0.borrow().debug_logs.join("\n")
+    }
+
+    pub fn clear_debug_logs(&self) {
+        self.0.borrow_mut().debug_logs.clear();
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+/// Convenience macro to log to an aldon game console, `aldon_log!(console, "...", args)`
+mod macros {
+    #[macro_export]
+    macro_rules! aldon_log {
+        ($console:expr, $($arg:tt)*) => {
+            $console.log(&format!($($arg)*))
+        };
+    }
+}
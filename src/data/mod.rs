@@ -1,10 +1,27 @@
-use crate::game::EquipType;
-use crate::thrift::save::ClassType;
+//! The game's resources, parsed from the json next to this file into `*Res` structs.
+//!
+//! What items do comes from the `effects` of each prop in props.json: stat bonuses and
+//! regeneration while equipped, heals, cures, potions and traps when used, guilds, training, the
+//! inn and pets when bought, and spells cast by weapons, e.g.
+//! `{"effect": "stat_bonus", "stat": "strength", "magnitude": 1}`. Weapons can also have a `proc`,
+//! a chance improved by luck to cast a spell, poison, drain life or explode on a hit or kill, or
+//! `always` to do it every time.
+//!
+//! Weapons and spells do a `DamageType` of damage, physical by default. A `resistance` effect on
+//! a creature or the armor it wears takes a percent off damage of that type, or adds to it when
+//! below zero.
+//!
+//! A creature's `reward` is its experience per level, an optional gold range per level and a
+//! `loot` table of items with a percent chance to drop at the corpse. The loot tables are new,
+//! the original game dropped nothing.
+use crate::game::{EquipType, InvalidDataError};
+use crate::thrift::save::{ClassType, ConditionType, RaceType, TrapKind};
+use crate::validate;
 use base64::{engine::general_purpose, Engine as _};
 use once_cell::sync::Lazy;
 use serde::{self, de, Deserializer};
 use serde_derive::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{cmp::max, collections::HashMap, fmt, sync::Mutex};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SpriteSheetRes {
@@ -56,11 +73,22 @@ pub struct MapRes {
     pub spawners: Vec<SpawnerRes>,
 }
 
+/// Tiles are packed two to a byte and base64 encoded. Older files like the Father's Day maps
+/// list them one per tile instead.
 fn from_base64<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: &str = serde::Deserialize::deserialize(deserializer)?;
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tiles {
+        Packed(String),
+        Unpacked(Vec<u8>),
+    }
+    let s = match serde::Deserialize::deserialize(deserializer)? {
+        Tiles::Packed(s) => s,
+        Tiles::Unpacked(tiles) => return Ok(tiles),
+    };
     let array_u8 = general_purpose::STANDARD
         .decode(s)
         .map_err(de::Error::custom)?;
@@ -131,6 +159,25 @@ pub enum SpellTarget {
     None,
 }
 
+/// What kind of damage a weapon, spell or explosion does, for resistances
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Ice,
+    Poison,
+    Holy,
+    Necrotic,
+}
+
+impl DamageType {
+    pub fn is_physical(&self) -> bool {
+        *self == DamageType::Physical
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SpellRes {
     pub name: String,
@@ -141,6 +188,9 @@ pub struct SpellRes {
 
     #[serde(deserialize_with = "to_spell_target")]
     pub target: SpellTarget,
+
+    #[serde(default, skip_serializing_if = "DamageType::is_physical")]
+    pub damage_type: DamageType,
 }
 
 fn to_spell_target<'de, D>(deserializer: D) -> Result<SpellTarget, D::Error>
@@ -177,10 +227,249 @@ pub struct PropRes {
     pub sight_blocker: bool,
     pub draw_depth: u8,
 
+    /// What the prop does when it's equipped, used or bought, see `effects`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub effects: Vec<EffectRes>,
+
     #[serde(flatten)]
     pub kind: PropTypeRes,
 }
 
+/// One thing an item does, e.g. `{"effect": "heal", "amount": 10}`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum EffectRes {
+    /// While equipped
+    StatBonus {
+        stat: StatRes,
+        magnitude: i32,
+    },
+    /// While equipped, restores 1 health or mana every 2 seconds
+    Regen {
+        stat: StatRes,
+    },
+
+    /// When used
+    Heal {
+        amount: i32,
+    },
+    CurePoison,
+    /// When used, a bonus to `stat` for 90 seconds. Health and mana regenerate instead.
+    Condition {
+        stat: StatRes,
+    },
+    PlaceTrap {
+        trap: TrapRes,
+    },
+    /// When used, nothing but using it up. The mana renewal potion has always been this.
+    UsedUp,
+
+    /// When bought
+    JoinGuild {
+        class: GuildRes,
+        /// Only journeymen can join, others have to quit their guild first
+        #[serde(default)]
+        journeyman_only: bool,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        barred_races: Vec<RaceRes>,
+    },
+    QuitGuild,
+    Train,
+    Rest,
+    /// The prop, which must be a creature, follows the buyer as their pet
+    GrantPet,
+
+    /// A weapon that attacks by casting a spell
+    CastSpell {
+        spell_id: u16,
+    },
+    /// While equipped, or always for a creature, takes `percent` less damage of a type. Below
+    /// zero it takes more.
+    Resistance {
+        damage_type: DamageType,
+        percent: i32,
+    },
+    /// A weapon's percent chance, plus the attacker's luck modifier, of doing `action` to the
+    /// target when it hits or kills. An `always` proc, like the Blood Blade's drain, skips the
+    /// roll and happens every time whatever the luck.
+    Proc {
+        trigger: ProcTrigger,
+        #[serde(default)]
+        chance: i32,
+        #[serde(default)]
+        always: bool,
+        action: ProcAction,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcTrigger {
+    Hit,
+    Kill,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcAction {
+    /// Free of mana and without the chance to fizzle
+    CastSpell { spell_id: u16 },
+    /// `random` poison only hurts on some ticks, like a bite
+    Poison {
+        #[serde(default)]
+        random: bool,
+    },
+    /// Heals the attacker half the damage done
+    Drain,
+    /// A fire explosion where the target stood that hurts everything around it
+    Explode { damage: i32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatRes {
+    Strength,
+    Dexterity,
+    Intelligence,
+    Luck,
+    Speed,
+    Armor,
+    Health,
+    Mana,
+}
+
+impl From<StatRes> for ConditionType {
+    fn from(stat: StatRes) -> Self {
+        match stat {
+            StatRes::Strength => ConditionType::STRENGTH,
+            StatRes::Dexterity => ConditionType::DEXTERITY,
+            StatRes::Intelligence => ConditionType::INTELIGENCE,
+            StatRes::Luck => ConditionType::LUCK,
+            StatRes::Speed => ConditionType::SPEED,
+            StatRes::Armor => ConditionType::ARMOR,
+            StatRes::Health => ConditionType::HEALTH,
+            StatRes::Mana => ConditionType::MANA,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrapRes {
+    Spark1,
+    Spark2,
+    Spark3,
+    Flame1,
+    Flame2,
+    Slowness,
+    Weakness,
+    Poison,
+}
+
+impl From<TrapRes> for TrapKind {
+    fn from(trap: TrapRes) -> Self {
+        match trap {
+            TrapRes::Spark1 => TrapKind::SPARK1,
+            TrapRes::Spark2 => TrapKind::SPARK2,
+            TrapRes::Spark3 => TrapKind::SPARK3,
+            TrapRes::Flame1 => TrapKind::FLAME1,
+            TrapRes::Flame2 => TrapKind::FLAME2,
+            TrapRes::Slowness => TrapKind::SLOWNESS,
+            TrapRes::Weakness => TrapKind::WEAKNESS,
+            TrapRes::Poison => TrapKind::POISON,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GuildRes {
+    Fighter,
+    Thief,
+    Priest,
+    Mage,
+}
+
+impl From<GuildRes> for ClassType {
+    fn from(guild: GuildRes) -> Self {
+        match guild {
+            GuildRes::Fighter => ClassType::FIGHTER,
+            GuildRes::Thief => ClassType::THIEF,
+            GuildRes::Priest => ClassType::PRIEST,
+            GuildRes::Mage => ClassType::SPELLCASTER,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RaceRes {
+    Human,
+    Elf,
+    Dwarf,
+}
+
+impl From<RaceRes> for RaceType {
+    fn from(race: RaceRes) -> Self {
+        match race {
+            RaceRes::Human => RaceType::HUMAN,
+            RaceRes::Elf => RaceType::ELF,
+            RaceRes::Dwarf => RaceType::DWARF,
+        }
+    }
+}
+
+/// What the player gets for killing a creature
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RewardRes {
+    /// Experience per level of the creature
+    pub exp: i32,
+    /// Gold per level, from the first to the second. Without it, 1 up to half the experience.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gold: Option<[i32; 2]>,
+    /// Seen in the original game rather than guessed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub observed: bool,
+    /// What else was seen in the original game, e.g. "lvl 1 gp: 63"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Not from the original game, which dropped nothing on kills. Each item takes a roll of the
+    /// game's rng on every kill.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub loot: Vec<LootRes>,
+}
+
+/// An item a creature drops when it's killed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LootRes {
+    pub prop_id: u16,
+    /// Percent chance it drops
+    pub chance: i32,
+}
+
+/// For creatures without a reward, the least experience that still gives gold
+static MIN_REWARD: RewardRes = RewardRes {
+    exp: 2,
+    gold: None,
+    observed: false,
+    note: None,
+    loot: Vec::new(),
+};
+
+impl RewardRes {
+    pub fn exp(&self, level: i32) -> i32 {
+        self.exp * level
+    }
+
+    /// The least and most gold for killing a creature at `level`
+    pub fn gold(&self, level: i32) -> (i32, i32) {
+        match self.gold {
+            Some([min, max]) => (min * level, max * level),
+            None => (1, max(1, self.exp(level) / 2)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum PropTypeRes {
     Item {
@@ -211,6 +500,8 @@ pub enum PropTypeRes {
         buy_cost: i32,
         sell_cost: i32,
         equip_to: String,
+        #[serde(default, skip_serializing_if = "DamageType::is_physical")]
+        damage_type: DamageType,
     },
     Armor {
         frame: u16,
@@ -236,6 +527,8 @@ pub enum PropTypeRes {
         wisdom: i32,
         vitality: i32,
         luck: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reward: Option<RewardRes>,
     },
     User {
         frames: Vec<u16>,
@@ -248,6 +541,8 @@ pub enum PropTypeRes {
         wisdom: i32,
         vitality: i32,
         luck: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reward: Option<RewardRes>,
     },
     Physical {
         frame: u16,
@@ -280,6 +575,29 @@ impl PropRes {
         }
     }
 
+    /// What killing the prop gives the player, a token amount for creatures without one
+    pub fn reward(&self) -> &RewardRes {
+        match &self.kind {
+            PropTypeRes::Creature {
+                reward: Some(reward),
+                ..
+            }
+            | PropTypeRes::User {
+                reward: Some(reward),
+                ..
+            } => reward,
+            _ => &MIN_REWARD,
+        }
+    }
+
+    /// The spell a weapon casts instead of striking, if any
+    pub fn spell_id(&self) -> Option<u16> {
+        self.effects.iter().find_map(|effect| match effect {
+            EffectRes::CastSpell { spell_id } => Some(*spell_id),
+            _ => None,
+        })
+    }
+
     pub fn buy_cost(&self) -> i32 {
         // special cases
         match self.str_id.as_str() {
@@ -523,11 +841,118 @@ impl PropRes {
     }
 }
 
+/// The maps and messages the game was released with
 pub static WORLD: Lazy<WorldRes> = Lazy::new(|| {
     let json = include_str!("maps.json");
     return serde_json::from_str(json).unwrap();
 });
 
+/// The Father's Day map pack
+static FATHERS_DAY_WORLD: Lazy<WorldRes> = Lazy::new(|| {
+    let json = include_str!("maps_fathers_day.json");
+    serde_json::from_str(json).unwrap()
+});
+
+/// A world loaded with `World::from_json` and the json it was loaded from
+struct LoadedWorld {
+    json: Vec<u8>,
+    world: World,
+}
+
+static LOADED_WORLDS: Lazy<Mutex<HashMap<String, LoadedWorld>>> = Lazy::new(Default::default);
+
+/// The maps and messages a game is played in, and the id saves refer to it by
+#[derive(Clone, Copy)]
+pub struct World {
+    pub id: &'static str,
+    pub res: &'static WorldRes,
+}
+
+impl World {
+    /// The ids of the worlds built into the game
+    pub const BUILT_IN: [&'static str; 2] = ["aldon", "fathers_day"];
+
+    pub fn built_in(id: &str) -> Option<World> {
+        let res = match id {
+            "aldon" => &*WORLD,
+            "fathers_day" => &*FATHERS_DAY_WORLD,
+            _ => return None,
+        };
+        Some(World {
+            id: Self::BUILT_IN.iter().find(|built_in| **built_in == id)?,
+            res,
+        })
+    }
+
+    /// A world supplied by the host, in the same format as maps.json. Errors if it refers to
+    /// maps, props or messages that don't exist. Actors hold on to the world for as long as they
+    /// run, so it's never freed: loading the same json under the same id again gives back the
+    /// world already loaded, and loading different json under an id that's taken is an error.
+    pub fn from_json(id: &str, json: &[u8]) -> Result<World, InvalidDataError> {
+        if Self::built_in(id).is_some() {
+            return Err(InvalidDataError::new(&format!(
+                "'{}' is the id of a built in world",
+                id
+            )));
+        }
+        let mut loaded = LOADED_WORLDS.lock().unwrap();
+        if let Some(existing) = loaded.get(id) {
+            if existing.json == json {
+                return Ok(existing.world);
+            }
+        }
+        let res: WorldRes = serde_json::from_slice(json)
+            .map_err(|err| InvalidDataError::new(&format!("bad world: {}", err)))?;
+        // New games start on map 1
+        if !res.maps.contains_key("1") {
+            return Err(InvalidDataError::new("world has no map 1"));
+        }
+        // Anything that would panic once the player gets to it. Actors that misbehave are
+        // left for `aldon-validate` to point out.
+        let problems: Vec<String> = validate::validate(&res)
+            .iter()
+            .filter(|problem| problem.fatal)
+            .map(|problem| problem.to_string())
+            .collect();
+        if !problems.is_empty() {
+            return Err(InvalidDataError::new(&format!(
+                "bad world: {}",
+                problems.join("; ")
+            )));
+        }
+        if loaded.contains_key(id) {
+            return Err(InvalidDataError::new(&format!(
+                "a different world '{}' is already loaded",
+                id
+            )));
+        }
+        let world = World {
+            id: Box::leak(id.to_string().into_boxed_str()),
+            res: Box::leak(Box::new(res)),
+        };
+        loaded.insert(
+            id.to_string(),
+            LoadedWorld {
+                json: json.to_vec(),
+                world,
+            },
+        );
+        Ok(world)
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::built_in(Self::BUILT_IN[0]).unwrap()
+    }
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "World({})", self.id)
+    }
+}
+
 pub static SPRITES: Lazy<SpriteSheetRes> = Lazy::new(|| {
     let json = include_str!("spritesheet.json");
     return serde_json::from_str(json).unwrap();
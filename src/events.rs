@@ -0,0 +1,119 @@
+//! What happened in the game, for the host to react to without parsing the console. The stage
+//! and the bodies on it share one queue that events go on as they happen, and
+//! `AldonGame::drain_events` hands them over oldest first. In the browser `drain_events` returns
+//! them as JSON.
+use crate::{body::Body, data::DamageType, thrift::save::ConditionType};
+use serde::Serializer;
+use serde_derive::Serialize;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+pub use crate::combat::BattleEventType;
+
+/// Events kept for a host that doesn't drain them. The oldest are dropped past this.
+const MAX_EVENTS: usize = 1000;
+
+/// The body an event happened to
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EventBody {
+    pub name: String,
+    pub actor_id: Option<u16>,
+    pub prop_id: u16,
+}
+
+impl EventBody {
+    pub fn is_player(&self) -> bool {
+        self.actor_id == Some(0)
+    }
+}
+
+impl From<&Body> for EventBody {
+    fn from(body: &Body) -> Self {
+        Self {
+            name: body.name.clone(),
+            actor_id: body.actor_id,
+            prop_id: body.prop_id,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum GameEvent {
+    /// An attack or poison landed, or missed. Damage the player took has them as the target and
+    /// damage they dealt has them as the attacker.
+    Damage {
+        target: EventBody,
+        attacker: Option<EventBody>,
+        kind: BattleEventType,
+        /// After resistances
+        amount: i32,
+        damage_type: DamageType,
+    },
+    /// A proc of the weapon `prop_id` that `body` wields went off
+    Proc {
+        body: EventBody,
+        prop_id: u16,
+    },
+    /// `exp` and `gold` are what the player got for it, nothing unless they or a pet killed
+    Kill {
+        target: EventBody,
+        killer: EventBody,
+        exp: i32,
+        gold: i32,
+    },
+    LevelUp {
+        body: EventBody,
+        level: i32,
+    },
+    Pickup {
+        body: EventBody,
+        prop_id: u16,
+        quantity: u8,
+    },
+    ConditionGained {
+        body: EventBody,
+        #[serde(serialize_with = "condition_name")]
+        condition: ConditionType,
+    },
+    ConditionWornOff {
+        body: EventBody,
+        #[serde(serialize_with = "condition_name")]
+        condition: ConditionType,
+    },
+    MapChanged {
+        map_id: u16,
+    },
+    QuestLogAdded {
+        msg_id: u16,
+    },
+    QuestLogRemoved {
+        msg_id: u16,
+    },
+}
+
+fn condition_name<S: Serializer>(
+    condition: &ConditionType,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let name: &str = (*condition).into();
+    serializer.serialize_str(name)
+}
+
+/// A handle to a queue of events that keeps only the newest `MAX_EVENTS`, clones push to the same
+/// one
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EventQueue(Rc<RefCell<VecDeque<GameEvent>>>);
+
+impl EventQueue {
+    pub fn push(&self, event: GameEvent) {
+        let mut events = self.0.borrow_mut();
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    pub fn drain(&self) -> Vec<GameEvent> {
+        self.0.borrow_mut().drain(..).collect()
+    }
+}
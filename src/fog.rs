@@ -2,10 +2,39 @@
 use crate::{game::InvalidDataError, thrift::save};
 use std::collections::{BTreeMap, HashMap};
 
+/// Tiles on a map
+const TILES: usize = 576;
+
 pub(crate) struct Fog {
     last_look: Option<(u16, u16)>,
     current_map: u16,
-    fog_by_map: HashMap<u16, [bool; 576]>,
+    fog_by_map: HashMap<u16, FogBits>,
+}
+
+/// Which tiles of a map are still fogged, a bit per tile
+#[derive(Clone, Copy)]
+struct FogBits([u8; TILES / 8]);
+
+impl FogBits {
+    #[cfg(feature = "web")]
+    fn get(&self, i: usize) -> bool {
+        self.0[i / 8] & (1 << (i % 8)) != 0
+    }
+
+    fn clear(&mut self, i: usize) {
+        self.0[i / 8] &= !(1 << (i % 8));
+    }
+}
+
+/// Packs fog saved as a bool per tile into bits. Missing tiles are clear.
+pub(crate) fn pack(fogged: &[bool]) -> Vec<u8> {
+    let mut bits = FogBits([0; TILES / 8]);
+    for (i, fogged) in fogged.iter().take(TILES).enumerate() {
+        if *fogged {
+            bits.0[i / 8] |= 1 << (i % 8);
+        }
+    }
+    bits.0.to_vec()
 }
 
 impl Fog {
@@ -18,24 +47,28 @@ impl Fog {
     }
 
     pub fn save(&self) -> save::Fog {
-        //Some(self.fog_by_map.clone()))
-        let fog_by_map: BTreeMap<i32, Vec<bool>> = self
+        let fog_bits_by_map: BTreeMap<i32, Vec<u8>> = self
             .fog_by_map
             .iter()
-            .map(|(key, value)| (*key as i32, value.to_vec()))
+            .map(|(key, value)| (*key as i32, value.0.to_vec()))
             .collect();
 
-        save::Fog::new(self.current_map as i32, fog_by_map)
+        save::Fog::new(self.current_map as i32, None, fog_bits_by_map)
     }
 
     pub fn from_save(save: &save::Fog) -> Result<Self, InvalidDataError> {
-        let fog_by_map: HashMap<u16, [bool; 576]> = save
-            .fog_by_map
+        let fog_by_map: HashMap<u16, FogBits> = save
+            .fog_bits_by_map
             .as_ref()
-            .ok_or(InvalidDataError::new("fog_by_map field missing"))?
+            .ok_or(InvalidDataError::new("fog_bits_by_map field missing"))?
             .iter()
-            .map(|(key, value)| (*key as u16, to_array(value)))
-            .collect();
+            .map(|(key, value)| {
+                let bits = value.as_slice().try_into().map_err(|_| {
+                    InvalidDataError::new(&format!("fog for map {} isn't {} bytes", key, TILES / 8))
+                })?;
+                Ok((*key as u16, FogBits(bits)))
+            })
+            .collect::<Result<_, InvalidDataError>>()?;
 
         let current_map = save
             .current_map
@@ -76,7 +109,9 @@ impl Fog {
                     continue;
                 }
                 let i = Self::idx(xt, yt);
-                fog[i] &= !vision.visible(x, y);
+                if vision.visible(x, y) {
+                    fog.clear(i);
+                }
             }
         }
     }
@@ -91,16 +126,18 @@ impl Fog {
             }
         }
         self.last_look = Some((x, y));
+        // Looking past the top or left edge wraps around to a huge coordinate that look_eighth skips
         self.look_eighth(|xp, yp| (x + xp, y + yp), vision);
         self.look_eighth(|xp, yp| (x + yp, y + xp), vision);
-        self.look_eighth(|xp, yp| (x + xp, y - yp), vision);
-        self.look_eighth(|xp, yp| (x + yp, y - xp), vision);
-        self.look_eighth(|xp, yp| (x - yp, y - xp), vision);
-        self.look_eighth(|xp, yp| (x - xp, y - yp), vision);
-        self.look_eighth(|xp, yp| (x - xp, y + yp), vision);
-        self.look_eighth(|xp, yp| (x - yp, y + xp), vision);
+        self.look_eighth(|xp, yp| (x + xp, y.wrapping_sub(yp)), vision);
+        self.look_eighth(|xp, yp| (x + yp, y.wrapping_sub(xp)), vision);
+        self.look_eighth(|xp, yp| (x.wrapping_sub(yp), y.wrapping_sub(xp)), vision);
+        self.look_eighth(|xp, yp| (x.wrapping_sub(xp), y.wrapping_sub(yp)), vision);
+        self.look_eighth(|xp, yp| (x.wrapping_sub(xp), y + yp), vision);
+        self.look_eighth(|xp, yp| (x.wrapping_sub(yp), y + xp), vision);
     }
 
+    #[cfg(feature = "web")]
     pub fn occluded(&self, x: f64, y: f64) -> bool {
         let fog = self.fog_by_map.get(&self.current_map).expect(&format!(
             "to have fog for the current map ({})",
@@ -108,23 +145,28 @@ impl Fog {
         ));
 
         let i = Fog::idx(x as u16, y as u16);
-        return fog[i];
+        return fog.get(i);
     }
 
     pub fn load_map(&mut self, map_id: u16) {
         if !self.fog_by_map.contains_key(&map_id) {
-            self.fog_by_map.insert(map_id, [true; 576]);
+            self.fog_by_map.insert(map_id, FogBits([0xff; TILES / 8]));
         }
         self.current_map = map_id;
         self.last_look = None;
     }
 
-    pub fn current(&self) -> [bool; 576] {
+    #[cfg(feature = "web")]
+    pub fn current(&self) -> [bool; TILES] {
         let fog = self.fog_by_map.get(&self.current_map).expect(&format!(
             "to have fog for the current map ({})",
             self.current_map
         ));
-        *fog
+        let mut result = [false; TILES];
+        for (i, fogged) in result.iter_mut().enumerate() {
+            *fogged = fog.get(i);
+        }
+        result
     }
 }
 
@@ -227,11 +269,3 @@ where
         result
     }
 }
-
-fn to_array(slice: &[bool]) -> [bool; 576] {
-    let mut result = [false; 576];
-    for (i, value) in slice.iter().enumerate() {
-        result[i] = *value;
-    }
-    result
-}
@@ -3,31 +3,37 @@ use crate::{
     aldon_log,
     body::{self, Body},
     buttons::{Button, ButtonKind, Buttons},
-    cast::Cast,
+    cast::{ActorInfo, Cast},
+    clock::GameClock,
     condition,
-    data::{PROPS, WORLD},
+    console::Console,
+    data::{EffectRes, World, PROPS},
+    events::GameEvent,
     fog::Fog,
-    js,
+    migrate::{self, SAVE_VERSION},
+    replay::{Input, Recorder, Replay, ReplayError},
+    save_code, save_file,
+    save_store::{SaveStore, SlotInfo, AUTOSAVE_SLOT},
     stage::{PetKind, Stage},
     stats::{self, PlayerStats},
     thrift::save::{self, ClassType, IntelType, RaceType, Team},
 };
-use once_cell::sync::Lazy;
+use rand::Rng;
 use serde_derive::{Deserialize, Serialize};
 use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
     error::Error,
-    fmt::{self, Write as _},
+    fmt,
     rc::Rc,
-    sync::Mutex,
 };
 use thrift::protocol::{
     TCompactInputProtocol, TCompactOutputProtocol, TOutputProtocol, TSerializable,
 };
 use thrift::transport::{TBufferChannel, TBufferedReadTransport, TBufferedWriteTransport};
+use thrift::OrderedFloat;
 
 const MAX_INVENTORY_LEN: usize = 50;
-const CONSOLE_LEN: usize = 10;
-pub(crate) static CONSOLE: Lazy<Mutex<Console>> = Lazy::new(|| Mutex::new(Console::new()));
 
 pub struct AldonGame {
     pub(crate) stage: Rc<Stage>,
@@ -39,14 +45,38 @@ pub struct AldonGame {
     loaded: bool,
     player_on_map_edge: bool,
     prevent_teleport: Option<(f64, f64)>,
-    last_update: f64,
+    clock: GameClock,
     game_over: bool,
     input_cooldown_deadline: f64,
+    recorder: RefCell<Option<Recorder>>,
+    save_store: Option<Rc<dyn SaveStore>>,
+    /// ms spent playing, saved with the game
+    play_time: f64,
+    console: Console,
 }
 
 impl AldonGame {
     pub fn new(dialog: Rc<dyn Dialog>) -> AldonGame {
-        let stage = Rc::new(Stage::new(1, dialog.clone()));
+        Self::with_seed(dialog, rand::random())
+    }
+
+    /// A game where all randomness comes from `seed`, so the same inputs play out the same way
+    pub fn with_seed(dialog: Rc<dyn Dialog>, seed: u64) -> AldonGame {
+        Self::with_world(dialog, seed, World::default())
+    }
+
+    /// A game played in `world` instead of the default one
+    pub fn with_world(dialog: Rc<dyn Dialog>, seed: u64, world: World) -> AldonGame {
+        Self::with_console(dialog, seed, world, Console::default())
+    }
+
+    fn with_console(
+        dialog: Rc<dyn Dialog>,
+        seed: u64,
+        world: World,
+        console: Console,
+    ) -> AldonGame {
+        let stage = Rc::new(Stage::new(1, dialog.clone(), seed, world, console.clone()));
         let cast = Cast::new(stage.clone(), dialog.clone());
 
         AldonGame {
@@ -58,9 +88,13 @@ impl AldonGame {
             player_on_map_edge: false,
             prevent_teleport: None,
             fog: Fog::new(),
-            last_update: 0.0,
+            clock: GameClock::new(),
             game_over: false,
             input_cooldown_deadline: 0.0,
+            recorder: RefCell::new(None),
+            save_store: None,
+            play_time: 0.0,
+            console,
         }
     }
 
@@ -72,6 +106,44 @@ impl AldonGame {
         self.game_over
     }
 
+    /// What happened since the last call, oldest first. Hosts call this every frame.
+    pub fn drain_events(&self) -> Vec<GameEvent> {
+        self.stage.drain_events()
+    }
+
+    /// The game's console, with the lines shown to the player and the debug logs
+    pub fn console(&self) -> &Console {
+        &self.console
+    }
+
+    /// The seed the game's random number generator was last seeded with. Saving reseeds it.
+    pub fn seed(&self) -> u64 {
+        self.stage.seed()
+    }
+
+    /// The world the game is played in. Loading a save switches to the save's world.
+    pub fn world(&self) -> World {
+        self.stage.world()
+    }
+
+    /// The id of the map currently loaded on the stage
+    pub fn map_id(&self) -> u16 {
+        self.stage.map_id()
+    }
+
+    /// The player's body, if a game has been loaded
+    pub fn player(&self) -> Option<Rc<Body>> {
+        if !self.loaded {
+            return None;
+        }
+        Some(self.stage.get_player())
+    }
+
+    /// The (x, y) of the player, if a game has been loaded
+    pub fn player_position(&self) -> Option<(f64, f64)> {
+        self.player().map(|player| (player.x(), player.y()))
+    }
+
     pub fn new_game(
         &mut self,
         name: String,
@@ -84,7 +156,18 @@ impl AldonGame {
         wisdom: i32,
         luck: i32,
     ) {
-        *self = Self::new(self.dialog.clone());
+        self.record(Input::NewGame {
+            name: name.clone(),
+            race: race.0,
+            portrait,
+            strength,
+            dexterity,
+            vitality,
+            intelligence,
+            wisdom,
+            luck,
+        });
+        self.switch_to(self.next_game());
 
         let player_name = if name.len() > 0 {
             name
@@ -105,9 +188,9 @@ impl AldonGame {
         player.base_luck.set(luck);
         player.class.set(ClassType::JOURNEYMAN);
         player.set_level(1);
-        let c = condition::body_regen(self.last_update);
+        let c = condition::body_regen(self.clock.now());
         player.add_condition_no_log(c);
-        let c = condition::body_mana_regen(self.last_update);
+        let c = condition::body_mana_regen(self.clock.now());
         player.add_condition_no_log(c);
         /*
         player.give_exp(9999999);
@@ -127,12 +210,56 @@ impl AldonGame {
         */
 
         self.fog = Fog::new();
-        self.load_map(1, 12.0, 3.0);
+        self.load_map_impl(1, 12.0, 3.0);
+    }
+
+    /// Starts a new game in this game's world with the player from `hero`, which is from
+    /// `export_hero`, a save or a save code. The player keeps their stats, class, level,
+    /// inventory and pet; everything else starts over. Called on a game made `with_world` this
+    /// takes a hero to another world.
+    pub fn new_game_with_hero(&mut self, hero: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        self.record(Input::NewGameWithHero(hero.clone()));
+        let save = decode_save(&hero)?;
+        let hero = save
+            .stage
+            .ok_or(InvalidDataError::new("stage field missing"))?;
+
+        // Built apart so a hero that doesn't load leaves the current game alone
+        let game = self.next_game();
+        let player = game.stage.place_hero(&hero)?;
+        player.clear_attack();
+        self.switch_to(game);
+        self.load_map_impl(1, 12.0, 3.0);
+        Ok(())
+    }
+
+    /// A fresh game in the same world, seeded from this game's rng and logging to its console
+    fn next_game(&self) -> AldonGame {
+        let seed = self.stage.rng().gen();
+        Self::with_console(
+            self.dialog.clone(),
+            seed,
+            self.world(),
+            self.console.clone(),
+        )
+    }
+
+    /// Throws away this game for `game`, keeping the recorder and save store
+    fn switch_to(&mut self, mut game: AldonGame) {
+        game.recorder = RefCell::new(self.recorder.take());
+        game.clock = self.clock.clone();
+        game.save_store = self.save_store.take();
+        *self = game;
     }
 
     pub fn load_map(&mut self, map_id: u16, x: f64, y: f64) {
+        self.record(Input::LoadMap { map_id, x, y });
+        self.load_map_impl(map_id, x, y);
+    }
+
+    fn load_map_impl(&mut self, map_id: u16, x: f64, y: f64) {
         self.loaded = true;
-        js::clear_logs();
+        self.console.clear_debug_logs();
         let player = self.stage.get_player();
         player.set_x(x);
         player.set_y(y);
@@ -147,13 +274,28 @@ impl AldonGame {
 
         self.stage.load_map(map_id, false /*from_save*/);
         self.fog.load_map(map_id);
+        self.autosave();
     }
 
+    /// Runs the game up to the wall time `now`, in ms. The game's own clock is what everything
+    /// in the game goes by, see `now`.
     pub fn update(&mut self, now: f64) {
-        self.last_update = now;
-        if !self.loaded || self.game_over {
-            return;
+        let last = self.clock.now();
+        let steps = self.clock.advance(now);
+        if self.loaded && !self.game_over {
+            self.play_time += (self.clock.now() - last).max(0.0);
         }
+        self.record(Input::Update);
+        for now in steps {
+            if !self.loaded || self.game_over {
+                return;
+            }
+            self.step(now);
+        }
+    }
+
+    /// Runs the game to the game time `now`
+    fn step(&mut self, now: f64) {
         self.cast.act(now);
         self.stage.update(now);
         self.buttons.update();
@@ -180,7 +322,7 @@ impl AldonGame {
                     body.clear_attack();
                 }
             } else {
-                self.load_map(map_id, x, y);
+                self.load_map_impl(map_id, x, y);
             }
             return;
         }
@@ -198,19 +340,19 @@ impl AldonGame {
 
             if player.x() == 0.0 {
                 if let Some(map_id) = west {
-                    self.load_map(map_id, 23.0, player.y());
+                    self.load_map_impl(map_id, 23.0, player.y());
                 }
             } else if player.x() == 23.0 {
                 if let Some(map_id) = east {
-                    self.load_map(map_id, 0.0, player.y());
+                    self.load_map_impl(map_id, 0.0, player.y());
                 }
             } else if player.y() == 0.0 {
                 if let Some(map_id) = north {
-                    self.load_map(map_id, player.x(), 23.0);
+                    self.load_map_impl(map_id, player.x(), 23.0);
                 }
             } else if player.y() == 23.0 {
                 if let Some(map_id) = south {
-                    self.load_map(map_id, player.x(), 0.0);
+                    self.load_map_impl(map_id, player.x(), 0.0);
                 }
             }
         }
@@ -221,7 +363,7 @@ impl AldonGame {
 
         if let Some((map_id, x, y)) = map_change_request {
             self.prevent_teleport = Some((x, y));
-            self.load_map(map_id, x, y);
+            self.load_map_impl(map_id, x, y);
         }
         if player.get_health() <= 0 && player.death_time() + 100.0 < now {
             self.game_over = true;
@@ -231,12 +373,14 @@ impl AldonGame {
     /// Control input for anything that isn't the stage, i.e. the buttons for stats, inventory,
     /// etc. Uses game coordinates.
     pub fn input_buttons(&mut self, x: f64, y: f64, touch_up: bool) {
+        self.record(Input::InputButtons { x, y, touch_up });
         self.buttons.input(x, y, touch_up);
     }
 
     /// Control input for the stage, i.e. moving the player around. Uses game coordinates.
     pub fn input_stage(&mut self, x: f64, y: f64) {
-        if self.last_update < self.input_cooldown_deadline {
+        self.record(Input::InputStage { x, y });
+        if self.clock.now() < self.input_cooldown_deadline {
             return;
         }
         let player = self.stage.get_player();
@@ -252,9 +396,10 @@ impl AldonGame {
                         return;
                     }
                     if body.inventory_len() == 0 {
-                        aldon_log!("*Nothing in inventory*");
+                        aldon_log!(self.console, "*Nothing in inventory*");
                     } else {
-                        js::log(&format!("actor_id: {:?}", body.actor_id));
+                        self.console
+                            .debug(&format!("actor_id: {:?}", body.actor_id));
                         self.dialog.execute_trade(
                             TransactionType::Inventory,
                             body.clone(),
@@ -270,7 +415,7 @@ impl AldonGame {
                     }
                     let items = self.stage.pick_up_at(body.x(), body.y());
                     if items.len() == 0 {
-                        aldon_log!("*Nothing to pickup*");
+                        aldon_log!(self.console, "*Nothing to pickup*");
                     } else {
                         self.dialog.pickup(body.clone(), items);
                     }
@@ -293,7 +438,7 @@ impl AldonGame {
                 // after casting a spell a touch will still be held down
                 // for a couple of milliseconds, which will cause the player
                 // to move unless we stop taking input for a little bit
-                self.input_cooldown_deadline = self.last_update + 200.0;
+                self.input_cooldown_deadline = self.clock.now() + 200.0;
                 self.buttons.maybe_untoggle_spell(spell_id);
                 return;
             }
@@ -305,37 +450,68 @@ impl AldonGame {
         if !self.loaded() || self.game_over() {
             return Err(Box::new(GameNotLoadedError {}));
         }
+        // Saving reseeds the rng, so it has to be replayed too
+        self.record(Input::Save);
+        self.write_save(self.stage.save(self.clock.now()))
+    }
+
+    /// Just the player with their pet and inventories, as a save `new_game_with_hero` can start a
+    /// game around. Maps, quests and explored fog are left behind.
+    pub fn export_hero(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        if !self.loaded() || self.game_over() {
+            return Err(Box::new(GameNotLoadedError {}));
+        }
+        let save = save::AldonGame {
+            stage: Some(self.stage.save_hero(self.clock.now())),
+            world_id: Some(self.world().id.to_string()),
+            version: Some(SAVE_VERSION),
+            ..Default::default()
+        };
+        encode_save(&save)
+    }
+
+    /// Saves as a text code that can be copied to another device, see `save_code`
+    pub fn export_save(&self) -> Result<String, Box<dyn Error>> {
+        Ok(save_code::encode(&self.save()?))
+    }
+
+    /// The serialized state of the game, used to check a replay ends where it was recorded.
+    /// Unlike `save` it doesn't change the game and works after game over.
+    pub(crate) fn snapshot(&self) -> Vec<u8> {
+        if !self.loaded {
+            return Vec::new();
+        }
+        self.write_save(self.stage.snapshot(self.clock.now()))
+            .unwrap()
+    }
+
+    fn write_save(&self, stage: save::Stage) -> Result<Vec<u8>, Box<dyn Error>> {
         // shadows for each map
         // state of each actor
         // variables
         let cast = self.cast.save();
         let fog = self.fog.save();
         let buttons = self.buttons.save();
-        let stage = self.stage.save(self.last_update);
-        let save = save::AldonGame::new(stage, cast, fog, buttons);
-
-        let mut channel = TBufferChannel::with_capacity(
-            0,      // read_capacity
-            500000, // write_capacity
+        let world_id = self.world().id.to_string();
+        let save = save::AldonGame::new(
+            stage,
+            cast,
+            fog,
+            buttons,
+            world_id,
+            SAVE_VERSION,
+            OrderedFloat::from(self.play_time),
         );
-        let transport = TBufferedWriteTransport::new(&mut channel);
-        let mut protocol = TCompactOutputProtocol::new(transport);
-        save.write_to_out_protocol(&mut protocol)?;
-        protocol.flush().unwrap();
-        Ok(channel.write_bytes())
+
+        encode_save(&save)
     }
 
+    /// Loads a save from `save`, or the text code of one from `export_save`
     pub fn load_save(&mut self, save_bytes: Vec<u8>) -> Result<(), Box<dyn Error>> {
-        js::log(&format!("load: {}", save_bytes.len().to_string()));
-
-        let mut channel = TBufferChannel::with_capacity(
-            500000, // read_capacity
-            0,      // write_capacity
-        );
-        channel.set_readable_bytes(&save_bytes);
-        let transport = TBufferedReadTransport::new(channel);
-        let mut protocol = TCompactInputProtocol::new(transport);
-        let save = save::AldonGame::read_from_in_protocol(&mut protocol)?;
+        self.console
+            .debug(&format!("load: {}", save_bytes.len().to_string()));
+        self.record(Input::LoadSave(save_bytes.clone()));
+        let save = decode_save(&save_bytes)?;
 
         let save_stage = save
             .stage
@@ -351,8 +527,19 @@ impl AldonGame {
             .buttons
             .ok_or(InvalidDataError::new("buttons field missing"))?;
 
-        let stage = Stage::from_save(self.last_update, &save_stage, self.dialog.clone())
-            .map_err(|err| InvalidDataError::new(&format!("stage: {}", err)))?;
+        let world_id = save
+            .world_id
+            .ok_or(InvalidDataError::new("world_id field missing"))?;
+        let world = self.save_world(&world_id)?;
+
+        let stage = Stage::from_save(
+            self.clock.now(),
+            &save_stage,
+            self.dialog.clone(),
+            world,
+            self.console.clone(),
+        )
+        .map_err(|err| InvalidDataError::new(&format!("stage: {}", err)))?;
 
         let stage = Rc::new(stage);
 
@@ -369,24 +556,223 @@ impl AldonGame {
         self.stage = stage;
         self.cast = cast;
         self.fog = fog;
+        self.play_time = save.play_time.map_or(0.0, f64::from);
         self.loaded = true;
         self.game_over = false;
         Ok(())
     }
 
+    /// Loads what can be read of a save `load_save` refused. The player, their pets and
+    /// inventories have to be readable, anything else that isn't is started over. Returns
+    /// which of "cast", "fog" and "buttons" were lost.
+    pub fn recover_save(
+        &mut self,
+        save_bytes: Vec<u8>,
+    ) -> Result<Vec<&'static str>, Box<dyn Error>> {
+        self.console
+            .debug(&format!("recover: {}", save_bytes.len()));
+        self.record(Input::RecoverSave(save_bytes.clone()));
+
+        let mut save = save_file::read_partial(save_file::payload(&save_bytes));
+        if save_file::is_wrapped(&save_bytes) && save.version.is_none() {
+            save.version = Some(save_file::FIRST_ENVELOPED_VERSION);
+        }
+        migrate::migrate(&mut save)?;
+        let mut save_stage = save
+            .stage
+            .ok_or(InvalidDataError::new("the player couldn't be recovered"))?;
+        let world = match &save.world_id {
+            Some(world_id) => self.save_world(world_id)?,
+            None => self.world(),
+        };
+        let map_id = save_stage.map_id.unwrap_or(1) as u16;
+        let mut lost = Vec::new();
+
+        let now = self.clock.now();
+        let mut stage = Rc::new(Stage::from_save(
+            now,
+            &save_stage,
+            self.dialog.clone(),
+            world,
+            self.console.clone(),
+        )?);
+        let cast = save
+            .cast
+            .as_ref()
+            .map(|save_cast| Cast::from_save(save_cast, stage.clone(), self.dialog.clone()));
+        let cast = match cast {
+            Some(Ok(cast)) => cast,
+            _ => {
+                // Without the cast the actors start over, so only keep bodies they don't own
+                lost.push("cast");
+                save_stage.bodies = Some(Vec::new());
+                save_stage.traps = Some(Vec::new());
+                stage = Rc::new(Stage::from_save(
+                    now,
+                    &save_stage,
+                    self.dialog.clone(),
+                    world,
+                    self.console.clone(),
+                )?);
+                let save_cast =
+                    save::Cast::new(map_id as i32, BTreeSet::new(), BTreeMap::new(), Vec::new());
+                Cast::from_save(&save_cast, stage.clone(), self.dialog.clone())?
+            }
+        };
+
+        let fog = match save.fog.as_ref().map(Fog::from_save) {
+            Some(Ok(fog)) => fog,
+            _ => {
+                lost.push("fog");
+                let mut fog = Fog::new();
+                fog.load_map(map_id);
+                fog
+            }
+        };
+        let buttons = save.buttons.as_ref().map(|save_buttons| {
+            Buttons::from_save(save_buttons, stage.clone(), self.dialog.clone())
+        });
+        let buttons = match buttons {
+            Some(Ok(buttons)) => buttons,
+            _ => {
+                lost.push("buttons");
+                Buttons::new(stage.clone(), self.dialog.clone())
+            }
+        };
+
+        self.buttons = buttons;
+        self.stage = stage;
+        self.cast = cast;
+        self.fog = fog;
+        self.play_time = save.play_time.map_or(0.0, f64::from);
+        self.loaded = true;
+        self.game_over = false;
+        self.console
+            .debug(&format!("recovered save, lost: {:?}", lost));
+        Ok(lost)
+    }
+
+    /// The world a save with `world_id` is played in
+    fn save_world(&self, world_id: &str) -> Result<World, InvalidDataError> {
+        match world_id {
+            id if id == self.world().id => Ok(self.world()),
+            id => World::built_in(id).ok_or(InvalidDataError::new(&format!(
+                "save is from world '{}' which isn't loaded",
+                id
+            ))),
+        }
+    }
+
+    /// Where `save_slot`, `load_slot` and autosaves keep their saves
+    pub fn set_save_store(&mut self, store: Rc<dyn SaveStore>) {
+        self.save_store = Some(store);
+    }
+
+    fn save_store(&self) -> Result<&Rc<dyn SaveStore>, InvalidDataError> {
+        self.save_store
+            .as_ref()
+            .ok_or(InvalidDataError::new("no save store set"))
+    }
+
+    /// Saves the game to the named slot of the save store
+    pub fn save_slot(&self, slot: &str) -> Result<(), Box<dyn Error>> {
+        let store = self.save_store()?;
+        let bytes = self.save()?;
+        store.save_slot(self.slot_info(slot), &bytes)
+    }
+
+    /// Loads the newest save in `slot`, or the newest backup that loads if it's damaged
+    pub fn load_slot(&mut self, slot: &str) -> Result<(), Box<dyn Error>> {
+        let saves = self.save_store()?.load_slot(slot);
+        let mut result: Result<(), Box<dyn Error>> = Err(Box::new(InvalidDataError::new(
+            &format!("nothing saved in slot '{}'", slot),
+        )));
+        for (info, bytes) in saves {
+            result = self.load_save(bytes);
+            match &result {
+                Ok(()) => break,
+                Err(err) => self.console.debug(&format!(
+                    "couldn't load {} saved at {}: {}",
+                    slot, info.timestamp, err
+                )),
+            }
+        }
+        result
+    }
+
+    /// The newest save in each slot of the save store, most recent first
+    pub fn save_slots(&self) -> Vec<SlotInfo> {
+        self.save_store
+            .as_ref()
+            .map_or(Vec::new(), |store| store.slots())
+    }
+
+    pub fn delete_slot(&self, slot: &str) {
+        if let Some(store) = &self.save_store {
+            store.delete_slot(slot);
+        }
+    }
+
+    /// ms spent playing this game, including before it was saved
+    pub fn play_time(&self) -> f64 {
+        self.play_time
+    }
+
+    fn slot_info(&self, slot: &str) -> SlotInfo {
+        let player = self.stage.get_player();
+        let map_name = self
+            .world()
+            .res
+            .maps
+            .get(&self.map_id().to_string())
+            .map_or(String::new(), |map| map.name.clone());
+        SlotInfo {
+            slot: slot.to_string(),
+            player_name: player.name.clone(),
+            class: player.class.get().into(),
+            level: player.level(),
+            map_name,
+            play_time: self.play_time,
+            timestamp: 0.0, // set by the store
+        }
+    }
+
+    /// Saves to `AUTOSAVE_SLOT` when there's a save store. Like `snapshot` this leaves the rng
+    /// alone, so autosaving doesn't change how the game plays out or break replays.
+    fn autosave(&self) {
+        let Some(store) = &self.save_store else {
+            return;
+        };
+        if !self.loaded || self.game_over {
+            return;
+        }
+        let result = self
+            .write_save(self.stage.snapshot(self.clock.now()))
+            .and_then(|bytes| store.save_slot(self.slot_info(AUTOSAVE_SLOT), &bytes));
+        if let Err(err) = result {
+            self.console.debug(&format!("autosave failed: {}", err));
+        }
+    }
+
     pub fn send_response(&mut self, actor_id: u16, raw_response: u8) {
+        self.record(Input::SendResponse {
+            actor_id,
+            response: raw_response,
+        });
         self.cast.send_response(actor_id, raw_response.into());
     }
 
     /// Attempts to equip inventory item at idx to the player, returns success.
     pub fn equip(&mut self, body: &Body, index: usize) -> bool {
+        self.record_body(body, |body| Input::Equip { body, index });
         let transaction = self.dialog.get_transaction();
         let item = transaction[index].clone();
-        body.equip(self.last_update, item)
+        body.equip(self.clock.now(), item)
     }
 
     /// Attemps to sell inventory item at idx, returns success
     pub fn sell(&mut self, body: &Body, index: usize) -> bool {
+        self.record_body(body, |body| Input::Sell { body, index });
         let transaction = self.dialog.get_transaction();
         if index >= transaction.len() {
             return false;
@@ -407,6 +793,7 @@ impl AldonGame {
 
     /// Attempts to buy the item at idx. Returns success
     pub fn buy(&mut self, body: Rc<Body>, index: usize) -> bool {
+        self.record_body(&body, |body| Input::Buy { body, index });
         let item = self.dialog.get_transaction()[index].clone();
         let prop_id = item.prop_id;
         let prop = &PROPS[&prop_id.to_string()];
@@ -438,122 +825,107 @@ impl AldonGame {
     /// something else, like give you a pet or level you up.
     /// Returns (handled: bool, ok: bool) where handled is true if prop_id
     /// is a type of prop that needs to be handled by buy_special and ok represents
-    /// if the buy was succesful (implies handled is true).
+    /// if the buy was succesful (implies handled is true). Every effect is checked
+    /// before any is applied, so a buy that fails changes nothing.
     fn buy_special(&mut self, body: Rc<Body>, prop_id: u16) -> (bool, bool) {
-        // Join Fighter's Guild
-        if prop_id == 14 {
-            if body.class.get() == ClassType::FIGHTER {
-                return (true, false);
-            }
-            body.set_class(ClassType::FIGHTER);
-            return (true, true);
-        }
-        // Train if ready
-        if prop_id == 13 {
-            let level = body.level();
-            if level >= stats::max_level(body.exp()) {
-                return (true, false);
-            }
-            body.set_level(level + 1);
-            let stats = body.stats();
-            self.dialog.stats(&stats);
-            return (true, true);
-        }
-        // Rest at Inn
-        if prop_id == 90 {
-            body.set_health(i32::MAX);
-            for pet in body.henchmen() {
-                pet.set_health(i32::MAX);
-            }
-            return (true, true);
+        let prop = &PROPS[&prop_id.to_string()];
+        let effects: Vec<&EffectRes> = prop
+            .effects
+            .iter()
+            .filter(|effect| {
+                matches!(
+                    effect,
+                    EffectRes::JoinGuild { .. }
+                        | EffectRes::QuitGuild
+                        | EffectRes::Train
+                        | EffectRes::Rest
+                        | EffectRes::GrantPet
+                )
+            })
+            .collect();
+        if effects.is_empty() {
+            return (false, false);
         }
-        // Quit current guild
-        if prop_id == 18 {
-            if body.class.get() != ClassType::JOURNEYMAN {
-                body.set_level(1);
-            }
-            self.buttons.clear_class_specific();
-            body.set_class(ClassType::JOURNEYMAN);
-            return (true, true);
-        }
-        // Join the Thieves' Guild
-        if prop_id == 15 {
-            if body.class.get() != ClassType::JOURNEYMAN {
-                return (true, false);
-            }
-            self.buttons
-                .set_tab_button(0, 6, Button::Sneak { toggled: false });
-            self.buttons
-                .set_tab_button(0, 8, Button::Hide { toggled: false });
-            body.set_class(ClassType::THIEF);
-            return (true, true);
-        }
-        // Join the Priesthood
-        if prop_id == 16 {
-            if body.race.get() == Some(RaceType::ELF) {
-                return (true, false);
-            }
-            if body.class.get() != ClassType::JOURNEYMAN {
-                return (true, false);
-            }
-            self.buttons.set_tab_button(
-                0,
-                8,
-                Button::Spellbook {
-                    spell_id: None,
-                    toggled: false,
-                },
-            );
-
-            body.set_class(ClassType::PRIEST);
-            return (true, true);
-        }
-        // Join the Mages' Guild
-        if prop_id == 17 {
-            if body.race.get() == Some(RaceType::DWARF) {
-                return (true, false);
+        if !effects.iter().all(|effect| can_buy(&body, effect)) {
+            return (true, false);
+        }
+        for effect in effects {
+            match effect {
+                EffectRes::JoinGuild { class, .. } => self.join_guild(&body, (*class).into()),
+                EffectRes::QuitGuild => {
+                    if body.class.get() != ClassType::JOURNEYMAN {
+                        body.set_level(1);
+                    }
+                    self.buttons.clear_class_specific();
+                    body.set_class(ClassType::JOURNEYMAN);
+                }
+                // Train if ready
+                EffectRes::Train => {
+                    let level = body.level();
+                    body.set_level(level + 1);
+                    body.emit(GameEvent::LevelUp {
+                        body: body.as_ref().into(),
+                        level: level + 1,
+                    });
+                    let stats = body.stats();
+                    self.dialog.stats(&stats);
+                }
+                // Rest at Inn
+                EffectRes::Rest => {
+                    body.set_health(i32::MAX);
+                    for pet in body.henchmen() {
+                        pet.set_health(i32::MAX);
+                    }
+                }
+                EffectRes::GrantPet => {
+                    let name = body::pet_name(&mut self.stage.rng());
+                    self.stage.create_pet(
+                        name,
+                        prop_id,
+                        body.clone(),
+                        PetKind::Normal,
+                        None, // actor_id
+                    );
+                }
+                _ => {}
             }
-            if body.class.get() != ClassType::JOURNEYMAN {
-                return (true, false);
+        }
+        (true, true)
+    }
+
+    fn join_guild(&mut self, body: &Body, class: ClassType) {
+        match class {
+            ClassType::THIEF => {
+                self.buttons
+                    .set_tab_button(0, 6, Button::Sneak { toggled: false });
+                self.buttons
+                    .set_tab_button(0, 8, Button::Hide { toggled: false });
             }
-            self.buttons.set_tab_button(
-                0,
-                8,
-                Button::Spellbook {
-                    spell_id: None,
-                    toggled: false,
-                },
-            );
-
-            body.set_class(ClassType::SPELLCASTER);
-            return (true, true);
-        }
-        // dog, cat
-        if prop_id == 61 || prop_id == 63 {
-            if body.pet.borrow().is_some() {
-                return (true, false);
+            ClassType::PRIEST | ClassType::SPELLCASTER => {
+                self.buttons.set_tab_button(
+                    0,
+                    8,
+                    Button::Spellbook {
+                        spell_id: None,
+                        toggled: false,
+                    },
+                );
             }
-            let name = body::pet_name();
-            self.stage.create_pet(
-                name,
-                prop_id,
-                body,
-                PetKind::Normal,
-                None, // actor_id
-            );
-            return (true, true);
+            _ => {}
         }
-        return (false, false);
+        body.set_class(class);
     }
 
     /// unequips the inventory item at idx
     pub fn unequip(&mut self, body: &Body, index: usize) -> bool {
+        self.record_body(body, |body| Input::Unequip { body, index });
         let item = self.dialog.get_transaction()[index].clone();
         body.unequip(item)
     }
 
     /// attempts to drop inventory item at idx, returns success.
     pub fn relinquish(&mut self, body: &Body, index: usize) -> bool {
+        self.record_body(body, |body| Input::Relinquish { body, index });
         let transaction = self.dialog.get_transaction();
         let item = transaction[index].clone();
         let result = body.relinquish(item.clone());
@@ -572,6 +944,7 @@ impl AldonGame {
 
     /// attempts to pickup item at idx, returns success.
     pub fn pickup(&mut self, body: &Body, index: usize) -> bool {
+        self.record_body(body, |body| Input::Pickup { body, index });
         if body.inventory_len() < MAX_INVENTORY_LEN {
             let transaction = self.dialog.get_transaction();
             let item = &transaction[index];
@@ -584,7 +957,12 @@ impl AldonGame {
             let item = self.dialog.remove_item(index);
 
             body.give_body_item(item.clone());
-            self.stage.remove_body_ref(item);
+            self.stage.remove_body_ref(item.clone());
+            self.stage.emit(GameEvent::Pickup {
+                body: body.into(),
+                prop_id: item.prop_id,
+                quantity: item.quantity.get(),
+            });
             true
         } else {
             false
@@ -615,14 +993,16 @@ impl AldonGame {
     }
 
     pub fn log(&self, message: &str) {
-        aldon_log!("{}", message);
+        aldon_log!(self.console, "{}", message);
     }
 
     pub fn set_button(&mut self, button_idx: usize, button: Button) {
+        self.record(Input::SetButton { button_idx, button });
         self.buttons.set_button(button_idx, button);
     }
 
     pub fn use_transaction_item(&mut self, body: &Body, index: usize) {
+        self.record_body(body, |body| Input::UseTransactionItem { body, index });
         let transaction = self.dialog.get_transaction();
         if index >= transaction.len() {
             return;
@@ -630,18 +1010,20 @@ impl AldonGame {
         let item = transaction[index].clone();
         let prop_id = item.prop_id;
 
-        self.use_item(body, prop_id);
+        self.stage.use_item(body, prop_id);
 
         // If we use up the item we have to remove it in the front end, from the transaction, and
         // the inventory. Should probably make this simpler
         let quant = body.item_quantity(prop_id);
-        js::log(&format!("prop_id: {}, quantity: {}", prop_id, quant));
+        self.console
+            .debug(&format!("prop_id: {}, quantity: {}", prop_id, quant));
         if quant == 0 {
             self.dialog.remove_item(index);
         }
     }
 
     pub fn use_item(&mut self, body: &Body, prop_id: u16) {
+        self.record_body(body, |body| Input::UseItem { body, prop_id });
         self.stage.use_item(&body, prop_id);
     }
 
@@ -650,14 +1032,123 @@ impl AldonGame {
         state
             .quest_log
             .iter()
-            .map(|str_id| WORLD.messages[&str_id.to_string()].value.clone())
+            .map(|str_id| self.world().res.messages[&str_id.to_string()].value.clone())
             .collect()
     }
 
     /// Activates the first spellbook button and get ready to cast a spell
     pub fn set_spellbook_spell(&mut self, spell_id: u16) {
+        self.record(Input::SetSpellbookSpell { spell_id });
         self.buttons.set_spellbook_spell(spell_id);
     }
+
+    /// Start recording inputs so the game can be replayed with `replay::play`. Has to start
+    /// before a game is created or loaded since the replay starts from a fresh game.
+    pub fn start_recording(&mut self) -> Result<(), ReplayError> {
+        if self.loaded {
+            return Err(ReplayError::new(
+                "recording must start before a game is loaded",
+            ));
+        }
+        *self.recorder.borrow_mut() = Some(Recorder::new(self.seed(), self.world().id));
+        Ok(())
+    }
+
+    /// Stops recording and returns everything recorded along with the state the game ended in
+    pub fn stop_recording(&mut self) -> Option<Replay> {
+        let recorder = self.recorder.take()?;
+        Some(recorder.finish(self.snapshot()))
+    }
+
+    pub fn recording(&self) -> bool {
+        self.recorder.borrow().is_some()
+    }
+
+    /// The time passed to the last call to `update`
+    pub fn last_update(&self) -> f64 {
+        self.clock.wall()
+    }
+
+    /// The game time. It starts at the wall time of the first update and then runs at `speed`,
+    /// except while paused or skipping a frame too long to catch up on.
+    pub fn now(&self) -> f64 {
+        self.clock.now()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.clock.paused()
+    }
+
+    /// Stops the game until `resume`. Updates still record the wall time.
+    pub fn pause(&mut self) {
+        self.record(Input::SetPaused(true));
+        self.clock.set_paused(true);
+    }
+
+    pub fn resume(&mut self) {
+        self.record(Input::SetPaused(false));
+        self.clock.set_paused(false);
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.clock.speed()
+    }
+
+    /// Runs the game at `speed` times the wall clock, from `clock::MIN_SPEED` to `MAX_SPEED`
+    pub fn set_speed(&mut self, speed: f64) {
+        self.record(Input::SetSpeed(speed));
+        self.clock.set_speed(speed);
+    }
+
+    /// The actors on the current map that haven't finished. See `Cast` for the rest of the
+    /// debugger. Debugging isn't recorded, so a replay of a debugged game won't match.
+    pub fn debug_actors(&self) -> Vec<ActorInfo> {
+        self.cast.debug_actors()
+    }
+
+    pub fn set_breakpoint(&self, actor_id: u16, state: u16) {
+        self.cast.set_breakpoint(actor_id, state);
+    }
+
+    pub fn clear_breakpoint(&self, actor_id: u16, state: u16) {
+        self.cast.clear_breakpoint(actor_id, state);
+    }
+
+    pub fn breakpoints(&self) -> Vec<(u16, u16)> {
+        self.cast.breakpoints()
+    }
+
+    pub fn step_actor(&self, actor_id: u16) {
+        self.cast.step_actor(actor_id);
+    }
+
+    pub fn continue_actor(&self, actor_id: u16) {
+        self.cast.continue_actor(actor_id);
+    }
+
+    pub fn debug_vars(&self) -> Vec<u8> {
+        self.cast.debug_vars()
+    }
+
+    fn record(&self, input: Input) {
+        if let Some(recorder) = self.recorder.borrow_mut().as_mut() {
+            recorder.push(self.clock.wall(), input);
+        }
+    }
+
+    /// Records an input on `body`, which is identified by its index in the stage's bodies
+    fn record_body(&self, body: &Body, input: impl FnOnce(usize) -> Input) {
+        if !self.recording() {
+            return;
+        }
+        let bodies = self.stage.bodies();
+        match bodies.iter().position(|b| std::ptr::eq(b.as_ref(), body)) {
+            Some(index) => self.record(input(index)),
+            None => self
+                .console
+                .debug(&format!("{} isn't on the stage, can't record", body.name)),
+        }
+    }
 }
 
 /// The different dialogs that must be implemented
@@ -690,86 +1181,6 @@ pub enum TransactionType {
     Inventory,
 }
 
-/// Get debugging logs, not just the aldon game logs
-pub fn get_logs() -> String {
-    let logs = js::DEBUG_LOGS.lock().unwrap();
-    logs.join("\n")
-}
-
-/// Convenience macro to log to the aldon game console
-mod macros {
-    #[macro_export]
-    macro_rules! aldon_log {
-        ($($arg:tt)*) => {
-            let mut console = CONSOLE.lock().unwrap();
-            write!(console, $($arg)*).unwrap();
-            write!(console, "\n").unwrap();
-            js::log(&format!($($arg)*));
-            drop(console);
-        };
-    }
-}
-
-// The aldon game console (a circular buffer)
-#[derive(Debug)]
-pub(crate) struct Console {
-    lines: [Option<String>; CONSOLE_LEN],
-    idx: usize,
-}
-
-impl Console {
-    fn new() -> Self {
-        Self {
-            lines: [None, None, None, None, None, None, None, None, None, None],
-            idx: 0,
-        }
-    }
-
-    pub fn iter(&self) -> ConsoleIter {
-        ConsoleIter {
-            lines: &self.lines,
-            idx: self.idx,
-        }
-    }
-}
-
-impl fmt::Write for Console {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        if s.len() == 0 {
-            return Ok(());
-        }
-        if let Some(line) = &mut self.lines[self.idx] {
-            line.push_str(s);
-        } else {
-            self.lines[self.idx] = Some(String::from(s));
-        }
-        let last = s.chars().last().unwrap();
-        if last == '\n' {
-            self.idx = (self.idx + 1) % CONSOLE_LEN;
-            self.lines[self.idx] = None;
-        }
-        Ok(())
-    }
-}
-
-pub(crate) struct ConsoleIter<'a> {
-    lines: &'a [Option<String>],
-    idx: usize,
-}
-
-impl<'a> Iterator for ConsoleIter<'a> {
-    type Item = &'a String;
-
-    fn next(&mut self) -> Option<&'a String> {
-        self.idx = if self.idx == 0 {
-            self.lines.len() - 1
-        } else {
-            self.idx - 1
-        };
-        self.lines[self.idx].as_ref()
-    }
-}
-
 #[derive(Eq, Hash, PartialEq, PartialOrd, Ord, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum EquipType {
     Melee,
@@ -813,6 +1224,63 @@ fn rect_contains(left: f64, top: f64, width: f64, height: f64, x: f64, y: f64) -
     (x >= left) && (x <= (left + width)) && (y >= top) && (y <= (top + height))
 }
 
+/// The bytes of a save, in its envelope
+fn encode_save(save: &save::AldonGame) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut channel = TBufferChannel::with_capacity(
+        0,      // read_capacity
+        500000, // write_capacity
+    );
+    let transport = TBufferedWriteTransport::new(&mut channel);
+    let mut protocol = TCompactOutputProtocol::new(transport);
+    save.write_to_out_protocol(&mut protocol)?;
+    protocol.flush().unwrap();
+    Ok(save_file::wrap(&channel.write_bytes()))
+}
+
+/// Whether `body` can buy an item with `effect`, checked before any of the item's effects apply
+fn can_buy(body: &Body, effect: &EffectRes) -> bool {
+    match effect {
+        EffectRes::JoinGuild {
+            class,
+            journeyman_only,
+            barred_races,
+        } => {
+            let class: ClassType = (*class).into();
+            let race = body.race.get();
+            body.class.get() != class
+                && (!journeyman_only || body.class.get() == ClassType::JOURNEYMAN)
+                && !barred_races
+                    .iter()
+                    .any(|barred| race == Some((*barred).into()))
+        }
+        EffectRes::Train => body.level() < stats::max_level(body.exp()),
+        EffectRes::GrantPet => body.pet.borrow().is_none(),
+        _ => true,
+    }
+}
+
+/// Reads a save or a save code and brings it up to the current version
+fn decode_save(save_bytes: &[u8]) -> Result<save::AldonGame, Box<dyn Error>> {
+    let decoded;
+    let save_bytes = if save_code::is_code(save_bytes) {
+        decoded = save_code::decode(std::str::from_utf8(save_bytes)?)?;
+        &decoded
+    } else {
+        save_bytes
+    };
+
+    let mut channel = TBufferChannel::with_capacity(
+        500000, // read_capacity
+        0,      // write_capacity
+    );
+    channel.set_readable_bytes(save_file::unwrap(save_bytes)?);
+    let transport = TBufferedReadTransport::new(channel);
+    let mut protocol = TCompactInputProtocol::new(transport);
+    let mut save = save::AldonGame::read_from_in_protocol(&mut protocol)?;
+    migrate::migrate(&mut save)?;
+    Ok(save)
+}
+
 /// TODO: should be in static resource
 fn is_portrait_male(portrait: u16) -> bool {
     match portrait {
@@ -1,25 +1,59 @@
 //! Bindings to js
 //! TODO: this should be abstracted. Core game should not know anything about js
 use once_cell::sync::Lazy;
-use std::sync::Mutex;
-use std::vec::Vec;
+use std::{collections::VecDeque, sync::Mutex};
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 use wasm_bindgen::prelude::*;
 
-pub static DEBUG_LOGS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+/// Where debug logs end up, after a game records them in its `Console` if they came from one. In
+/// the browser this is the js console, natively logs are dropped unless a sink is set with
+/// `set_log_sink`.
+static LOG_SINK: Lazy<Mutex<fn(&str)>> = Lazy::new(|| Mutex::new(default_sink));
 
+/// How many debug logs `recent_logs` keeps
+const RECENT_LOGS: usize = 500;
+
+/// The last debug logs of any game, outside of it so they can still be read after a panic
+static RECENT: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console, js_name = log)]
     fn log_impl(s: &str);
 }
 
-pub fn log(s: &str) {
-    let mut logs = DEBUG_LOGS.lock().unwrap();
-    logs.push(s.to_string());
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+fn default_sink(s: &str) {
     log_impl(s);
 }
 
-pub fn clear_logs() {
-    let mut logs = DEBUG_LOGS.lock().unwrap();
-    logs.clear();
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+fn default_sink(_s: &str) {}
+
+/// Replace where debug logs are sent, e.g. `set_log_sink(|s| eprintln!("{}", s))`
+pub fn set_log_sink(sink: fn(&str)) {
+    *LOG_SINK.lock().unwrap() = sink;
+}
+
+pub fn log(s: &str) {
+    let mut recent = RECENT.lock().unwrap();
+    if recent.len() == RECENT_LOGS {
+        recent.pop_front();
+    }
+    recent.push_back(s.to_string());
+    drop(recent);
+    let sink = *LOG_SINK.lock().unwrap();
+    sink(s);
+}
+
+/// The last `RECENT_LOGS` debug logs, oldest first
+#[cfg(feature = "web")]
+pub fn recent_logs() -> String {
+    let recent = RECENT.lock().unwrap();
+    recent
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n")
 }
@@ -0,0 +1,102 @@
+//! Upgrades saves written by older versions of the game. Every change to what a save means gets
+//! a named migration that takes a save from one version to the next, and loading runs each
+//! migration newer than the save in order. Saves from before versioning are version 0.
+use crate::{data::World, fog, game::InvalidDataError, js, thrift::save};
+use std::collections::BTreeMap;
+
+/// The version written into new saves
+pub const SAVE_VERSION: i32 = 2;
+
+struct Migration {
+    /// Upgrades saves of this version to the next
+    from: i32,
+    name: &'static str,
+    apply: fn(&mut save::AldonGame) -> Result<(), InvalidDataError>,
+}
+
+const MIGRATIONS: [Migration; 3] = [
+    Migration {
+        from: 0,
+        name: "revive_elemental_after_bye",
+        apply: revive_elemental_after_bye,
+    },
+    Migration {
+        from: 0,
+        name: "default_world",
+        apply: default_world,
+    },
+    Migration {
+        from: 1,
+        name: "pack_fog",
+        apply: pack_fog,
+    },
+];
+
+/// Brings `save` up to `SAVE_VERSION`. Errors for saves from a newer version of the game.
+pub fn migrate(save: &mut save::AldonGame) -> Result<(), InvalidDataError> {
+    let version = save.version.unwrap_or(0);
+    if version > SAVE_VERSION {
+        return Err(InvalidDataError::new(&format!(
+            "save is version {}, this game only loads up to version {}",
+            version, SAVE_VERSION
+        )));
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+        js::log(&format!(
+            "migrating save from version {}: {}",
+            migration.from, migration.name
+        ));
+        (migration.apply)(save)
+            .map_err(|err| InvalidDataError::new(&format!("{}: {}", migration.name, err)))?;
+    }
+    save.version = Some(SAVE_VERSION);
+    Ok(())
+}
+
+/// Older games could save after the elemental (actor 171) died but before saying bye (actor
+/// 173) brought it back, leaving it dead with no body and the quest stuck. Put it back in its
+/// first state so it respawns.
+fn revive_elemental_after_bye(save: &mut save::AldonGame) -> Result<(), InvalidDataError> {
+    let has_body = save
+        .stage
+        .as_ref()
+        .and_then(|stage| stage.bodies.as_ref())
+        .is_some_and(|bodies| bodies.iter().any(|body| body.actor_id == Some(171)));
+    let Some(actor_save_by_id) = save
+        .cast
+        .as_mut()
+        .and_then(|cast| cast.actor_save_by_id.as_mut())
+    else {
+        return Ok(());
+    };
+    let bye_state = actor_save_by_id.get(&173).and_then(|bye| bye.state);
+    if let Some(elemental) = actor_save_by_id.get_mut(&171) {
+        if !has_body && bye_state == Some(1) {
+            elemental.state = Some(0);
+            elemental.dead = Some(false);
+        }
+    }
+    Ok(())
+}
+
+/// Saves from before worlds could be picked have no world id and are all from the original
+fn default_world(save: &mut save::AldonGame) -> Result<(), InvalidDataError> {
+    if save.world_id.is_none() {
+        save.world_id = Some(World::BUILT_IN[0].to_string());
+    }
+    Ok(())
+}
+
+/// Fog was a bool per tile, now it's a bit
+fn pack_fog(save: &mut save::AldonGame) -> Result<(), InvalidDataError> {
+    let Some(save_fog) = save.fog.as_mut() else {
+        return Ok(());
+    };
+    if let Some(fog_by_map) = save_fog.fog_by_map.take() {
+        let bits = save_fog.fog_bits_by_map.get_or_insert_with(BTreeMap::new);
+        for (map_id, fogged) in fog_by_map {
+            bits.insert(map_id, fog::pack(&fogged));
+        }
+    }
+    Ok(())
+}
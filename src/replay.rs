@@ -0,0 +1,315 @@
+//! Recording and replaying the inputs to a game. All randomness comes from the game's seed, so
+//! a fresh game given the same inputs at the same times ends up in exactly the same state. QA
+//! can attach a replay to a bug report instead of describing what they tapped.
+//!
+//! ```text
+//! game.start_recording()?;
+//! game.new_game(...);
+//! ... play ...
+//! let bytes = game.stop_recording().unwrap().to_bytes();
+//!
+//! let replay = Replay::from_bytes(&bytes)?;
+//! let game = replay::play(dialog, &replay)?; // errors if the game ends up somewhere else
+//! ```
+//! `aldon-sim --replay FILE` does the same from the command line.
+use crate::{
+    body::Body,
+    buttons::Button,
+    data::World,
+    game::{AldonGame, Dialog},
+    thrift::save::RaceType,
+};
+use bincode::Options;
+use serde_derive::{Deserialize, Serialize};
+use std::{error::Error, fmt, rc::Rc};
+
+/// Bumped whenever `Replay` or `Input` change in a way old replays can't be read
+const REPLAY_VERSION: u32 = 3;
+
+/// Replays are at most a few mb, anything that inflates past this is garbage
+const MAX_REPLAY_LEN: usize = 64 * 1024 * 1024;
+
+/// A call into `AldonGame` that changes the game. Bodies are referred to by their index in the
+/// stage's bodies.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Input {
+    Update,
+    NewGame {
+        name: String,
+        race: i32,
+        portrait: u16,
+        strength: i32,
+        dexterity: i32,
+        vitality: i32,
+        intelligence: i32,
+        wisdom: i32,
+        luck: i32,
+    },
+    LoadMap {
+        map_id: u16,
+        x: f64,
+        y: f64,
+    },
+    Save,
+    LoadSave(Vec<u8>),
+    InputStage {
+        x: f64,
+        y: f64,
+    },
+    InputButtons {
+        x: f64,
+        y: f64,
+        touch_up: bool,
+    },
+    SendResponse {
+        actor_id: u16,
+        response: u8,
+    },
+    Equip {
+        body: usize,
+        index: usize,
+    },
+    Unequip {
+        body: usize,
+        index: usize,
+    },
+    Sell {
+        body: usize,
+        index: usize,
+    },
+    Buy {
+        body: usize,
+        index: usize,
+    },
+    Relinquish {
+        body: usize,
+        index: usize,
+    },
+    Pickup {
+        body: usize,
+        index: usize,
+    },
+    UseTransactionItem {
+        body: usize,
+        index: usize,
+    },
+    UseItem {
+        body: usize,
+        prop_id: u16,
+    },
+    SetButton {
+        button_idx: usize,
+        button: Button,
+    },
+    SetSpellbookSpell {
+        spell_id: u16,
+    },
+    RecoverSave(Vec<u8>),
+    NewGameWithHero(Vec<u8>),
+    SetPaused(bool),
+    SetSpeed(f64),
+}
+
+/// Collects inputs while a game is being recorded
+pub(crate) struct Recorder {
+    seed: u64,
+    world_id: String,
+    inputs: Vec<(f64, Input)>,
+}
+
+impl Recorder {
+    pub(crate) fn new(seed: u64, world_id: &str) -> Self {
+        Self {
+            seed,
+            world_id: world_id.to_string(),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, now: f64, input: Input) {
+        self.inputs.push((now, input));
+    }
+
+    pub(crate) fn finish(self, end_state: Vec<u8>) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            seed: self.seed,
+            world_id: self.world_id,
+            inputs: self.inputs,
+            end_state,
+        }
+    }
+}
+
+/// A recorded game: the seed and world it started from, every input along with the time of the
+/// last update when it happened, and a snapshot of the state it ended in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    version: u32,
+    pub seed: u64,
+    pub world_id: String,
+    pub inputs: Vec<(f64, Input)>,
+    pub end_state: Vec<u8>,
+}
+
+impl Replay {
+    /// The version followed by the rest deflated. Every frame records an update, so most of a
+    /// replay is the same few bytes over and over.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let options = bincode::DefaultOptions::new();
+        let mut bytes = options.serialize(&self.version).unwrap();
+        let replay = options.serialize(self).unwrap();
+        bytes.extend(miniz_oxide::deflate::compress_to_vec(&replay, 10));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let options = bincode::DefaultOptions::new();
+        // The version comes first, check it before the rest is read in the wrong format
+        let version: u32 = options.allow_trailing_bytes().deserialize(bytes)?;
+        if version != REPLAY_VERSION {
+            return Err(Box::new(ReplayError::new(&format!(
+                "replay version {} is not supported, expected {}",
+                version, REPLAY_VERSION
+            ))));
+        }
+        let deflated = &bytes[options.serialized_size(&version)? as usize..];
+        let replay = miniz_oxide::inflate::decompress_to_vec_with_limit(deflated, MAX_REPLAY_LEN)
+            .map_err(|_| ReplayError::new("replay is damaged or incomplete"))?;
+        Ok(options.deserialize(&replay)?)
+    }
+}
+
+/// Plays `replay` against a fresh game and checks it ends in the recorded state. Only replays
+/// of built in worlds can be played.
+pub fn play(dialog: Rc<dyn Dialog>, replay: &Replay) -> Result<AldonGame, Box<dyn Error>> {
+    let world = World::built_in(&replay.world_id).ok_or(ReplayError::new(&format!(
+        "replay is from world '{}' which isn't built in",
+        replay.world_id
+    )))?;
+    let mut game = AldonGame::with_world(dialog, replay.seed, world);
+    for (now, input) in replay.inputs.iter() {
+        apply(&mut game, *now, input)?;
+    }
+    check_end_state(&game, replay)?;
+    Ok(game)
+}
+
+/// Errors if `game` isn't in the state `replay` ended in
+pub fn check_end_state(game: &AldonGame, replay: &Replay) -> Result<(), ReplayError> {
+    if game.snapshot() != replay.end_state {
+        return Err(ReplayError::new(
+            "game ended in a different state than was recorded",
+        ));
+    }
+    Ok(())
+}
+
+/// Makes the call to `game` that was recorded as `input`
+pub fn apply(game: &mut AldonGame, now: f64, input: &Input) -> Result<(), Box<dyn Error>> {
+    if let Input::Update = input {
+        game.update(now);
+        return Ok(());
+    }
+    if game.last_update() != now {
+        return Err(Box::new(ReplayError::new(&format!(
+            "{:?} was recorded at {} but the game is at {}",
+            input,
+            now,
+            game.last_update()
+        ))));
+    }
+    match input {
+        Input::Update => {}
+        Input::NewGame {
+            name,
+            race,
+            portrait,
+            strength,
+            dexterity,
+            vitality,
+            intelligence,
+            wisdom,
+            luck,
+        } => game.new_game(
+            name.clone(),
+            RaceType(*race),
+            *portrait,
+            *strength,
+            *dexterity,
+            *vitality,
+            *intelligence,
+            *wisdom,
+            *luck,
+        ),
+        Input::LoadMap { map_id, x, y } => game.load_map(*map_id, *x, *y),
+        Input::Save => {
+            game.save()?;
+        }
+        Input::LoadSave(bytes) => game.load_save(bytes.clone())?,
+        Input::RecoverSave(bytes) => {
+            game.recover_save(bytes.clone())?;
+        }
+        Input::NewGameWithHero(bytes) => game.new_game_with_hero(bytes.clone())?,
+        Input::SetPaused(true) => game.pause(),
+        Input::SetPaused(false) => game.resume(),
+        Input::SetSpeed(speed) => game.set_speed(*speed),
+        Input::InputStage { x, y } => game.input_stage(*x, *y),
+        Input::InputButtons { x, y, touch_up } => game.input_buttons(*x, *y, *touch_up),
+        Input::SendResponse { actor_id, response } => game.send_response(*actor_id, *response),
+        Input::Equip { body, index } => {
+            game.equip(body_at(game, *body)?.as_ref(), *index);
+        }
+        Input::Unequip { body, index } => {
+            game.unequip(body_at(game, *body)?.as_ref(), *index);
+        }
+        Input::Sell { body, index } => {
+            game.sell(body_at(game, *body)?.as_ref(), *index);
+        }
+        Input::Buy { body, index } => {
+            game.buy(body_at(game, *body)?, *index);
+        }
+        Input::Relinquish { body, index } => {
+            game.relinquish(body_at(game, *body)?.as_ref(), *index);
+        }
+        Input::Pickup { body, index } => {
+            game.pickup(body_at(game, *body)?.as_ref(), *index);
+        }
+        Input::UseTransactionItem { body, index } => {
+            game.use_transaction_item(body_at(game, *body)?.as_ref(), *index)
+        }
+        Input::UseItem { body, prop_id } => game.use_item(body_at(game, *body)?.as_ref(), *prop_id),
+        Input::SetButton { button_idx, button } => game.set_button(*button_idx, *button),
+        Input::SetSpellbookSpell { spell_id } => game.set_spellbook_spell(*spell_id),
+    }
+    Ok(())
+}
+
+fn body_at(game: &AldonGame, index: usize) -> Result<Rc<Body>, ReplayError> {
+    game.stage
+        .bodies()
+        .get(index)
+        .cloned()
+        .ok_or_else(|| ReplayError::new(&format!("no body at index {}", index)))
+}
+
+#[derive(Debug)]
+pub struct ReplayError {
+    message: String,
+}
+
+impl ReplayError {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: String::from(message),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
@@ -0,0 +1,121 @@
+//! Saves as text, for moving a character between devices through a clipboard, a file or a few
+//! QR codes.
+//!
+//! ```text
+//! aldn1:<base64url of the deflated save>
+//! aldn1:2/3:<the second of three chunks of the same>
+//! ```
+//! The save inside keeps its envelope, so a code carries the save's version and checksum and a
+//! mistyped or cut off code is caught when it's decoded.
+//!
+//! `AldonGame::export_save` gives a code that `load_save` takes on the other device.
+//! `AldonGame::export_hero` keeps just the player, their pet and inventories, for
+//! `new_game_with_hero` to start a new game around in the same world or another one.
+use crate::{game::InvalidDataError, save_file};
+use base64::{engine::general_purpose, Engine as _};
+
+const PREFIX: &str = "aldn1:";
+
+/// Saves are a few kb, anything that inflates past this is garbage
+const MAX_SAVE_LEN: usize = 500000;
+
+/// The text code for `save`, which must be from `AldonGame::save`
+pub fn encode(save: &[u8]) -> String {
+    let deflated = miniz_oxide::deflate::compress_to_vec(save, 10);
+    format!(
+        "{}{}",
+        PREFIX,
+        general_purpose::URL_SAFE_NO_PAD.encode(deflated)
+    )
+}
+
+/// Splits a code into chunks of at most `len` characters, e.g. to fit each in a QR code.
+/// The chunks can be decoded in any order.
+pub fn chunks(code: &str, len: usize) -> Vec<String> {
+    let data = code.trim().strip_prefix(PREFIX).unwrap_or(code).as_bytes();
+    // Room for the prefix and "99/99:"
+    let len = len.saturating_sub(PREFIX.len() + 6).max(1);
+    let count = data.len().div_ceil(len).max(1);
+    (0..count)
+        .map(|i| {
+            let part = &data[(i * len).min(data.len())..((i + 1) * len).min(data.len())];
+            let part = std::str::from_utf8(part).unwrap();
+            format!("{}{}/{}:{}", PREFIX, i + 1, count, part)
+        })
+        .collect()
+}
+
+/// Whether `bytes` look like a code rather than a save
+pub fn is_code(bytes: &[u8]) -> bool {
+    bytes.trim_ascii_start().starts_with(PREFIX.as_bytes())
+}
+
+/// The save in a code, or in its chunks separated by whitespace. Errors unless the whole save
+/// is there and undamaged.
+pub fn decode(text: &str) -> Result<Vec<u8>, InvalidDataError> {
+    let data = join(text)?;
+    let deflated = general_purpose::URL_SAFE_NO_PAD
+        .decode(data.as_bytes())
+        .map_err(|_| InvalidDataError::new("save code has characters that don't belong in it"))?;
+    let save = miniz_oxide::inflate::decompress_to_vec_with_limit(&deflated, MAX_SAVE_LEN)
+        .map_err(|_| InvalidDataError::new("save code is damaged or incomplete"))?;
+    if !save_file::is_wrapped(&save) {
+        return Err(InvalidDataError::new("save code doesn't hold a save"));
+    }
+    save_file::unwrap(&save)?;
+    Ok(save)
+}
+
+/// The base64 of a code, put back together from its chunks
+fn join(text: &str) -> Result<String, InvalidDataError> {
+    let mut whole = None;
+    let mut parts: Vec<Option<&str>> = Vec::new();
+    for token in text.split_whitespace() {
+        let rest = token
+            .strip_prefix(PREFIX)
+            .ok_or(InvalidDataError::new("not a save code"))?;
+        // ':' and '/' aren't in base64url so only chunks have them
+        let Some((position, part)) = rest.split_once(':') else {
+            whole = Some(rest);
+            continue;
+        };
+        let (i, count) = position
+            .split_once('/')
+            .and_then(|(i, count)| Some((i.parse::<usize>().ok()?, count.parse::<usize>().ok()?)))
+            .filter(|(i, count)| *i >= 1 && i <= count)
+            .ok_or(InvalidDataError::new(&format!(
+                "save code chunk '{}' isn't numbered like 1/3",
+                position
+            )))?;
+        if parts.is_empty() {
+            parts.resize(count, None);
+        } else if parts.len() != count {
+            return Err(InvalidDataError::new(
+                "save code chunks are from different codes",
+            ));
+        }
+        parts[i - 1] = Some(part);
+    }
+
+    match (whole, parts.is_empty()) {
+        (Some(whole), true) => Ok(whole.to_string()),
+        (None, false) => {
+            let missing: Vec<String> = (1..=parts.len())
+                .filter(|i| parts[i - 1].is_none())
+                .map(|i| i.to_string())
+                .collect();
+            if !missing.is_empty() {
+                return Err(InvalidDataError::new(&format!(
+                    "save code is missing chunk {} of {}",
+                    missing.join(", "),
+                    parts.len()
+                )));
+            }
+            Ok(parts.into_iter().flatten().collect())
+        }
+        (None, true) => Err(InvalidDataError::new("save code is empty")),
+        (Some(_), false) => Err(InvalidDataError::new(
+            "save code is mixed up with chunks of a code",
+        )),
+    }
+}
@@ -0,0 +1,131 @@
+//! The bytes of a save: the thrift `save::AldonGame` in an envelope with its length and a
+//! checksum, so a save that was cut off or damaged is caught before it's loaded.
+//!
+//! ```text
+//! "ALDN" | envelope version (u8) | length (u32) | crc32 (u32) | thrift AldonGame
+//! ```
+//! Numbers are big endian. Saves from before the envelope are bare thrift and load as they are.
+use crate::{game::InvalidDataError, thrift::save};
+use thrift::{
+    protocol::{TCompactInputProtocol, TInputProtocol, TSerializable, TType},
+    transport::{TBufferChannel, TBufferedReadTransport},
+    OrderedFloat,
+};
+
+const MAGIC: &[u8; 4] = b"ALDN";
+const ENVELOPE_VERSION: u8 = 1;
+const HEADER_LEN: usize = 13;
+
+/// Saves in an envelope are at least this version. Used when a damaged save lost its version.
+pub(crate) const FIRST_ENVELOPED_VERSION: i32 = 1;
+
+/// Wraps thrift save bytes in an envelope
+pub fn wrap(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(ENVELOPE_VERSION);
+    bytes.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&crc32(payload).to_be_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// The thrift bytes of a save, checking they're all there and undamaged
+pub fn unwrap(bytes: &[u8]) -> Result<&[u8], InvalidDataError> {
+    if !is_wrapped(bytes) {
+        return Ok(bytes);
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(InvalidDataError::new("save is truncated: header cut off"));
+    }
+    if bytes[4] != ENVELOPE_VERSION {
+        return Err(InvalidDataError::new(&format!(
+            "save envelope version {} isn't supported",
+            bytes[4]
+        )));
+    }
+    let len = u32::from_be_bytes(bytes[5..9].try_into().unwrap()) as usize;
+    let checksum = u32::from_be_bytes(bytes[9..13].try_into().unwrap());
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() < len {
+        return Err(InvalidDataError::new(&format!(
+            "save is truncated: {} of {} bytes",
+            payload.len(),
+            len
+        )));
+    }
+    if payload.len() > len {
+        return Err(InvalidDataError::new(&format!(
+            "save is corrupted: {} bytes after the end",
+            payload.len() - len
+        )));
+    }
+    if crc32(payload) != checksum {
+        return Err(InvalidDataError::new(
+            "save is corrupted: checksum doesn't match",
+        ));
+    }
+    Ok(payload)
+}
+
+pub fn is_wrapped(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// The thrift bytes of a save without any checks, for recovering what's left of a damaged one
+pub(crate) fn payload(bytes: &[u8]) -> &[u8] {
+    if is_wrapped(bytes) {
+        bytes.get(HEADER_LEN..).unwrap_or(&[])
+    } else {
+        bytes
+    }
+}
+
+/// Reads as much of a damaged save as it can. Each section is either read whole or left out,
+/// and reading stops at the first section that doesn't decode since everything after it is
+/// probably garbage too.
+pub(crate) fn read_partial(payload: &[u8]) -> save::AldonGame {
+    let mut channel = TBufferChannel::with_capacity(payload.len(), 0);
+    channel.set_readable_bytes(payload);
+    let mut protocol = TCompactInputProtocol::new(TBufferedReadTransport::new(channel));
+    let mut game = save::AldonGame::default();
+    let _ = read_sections(&mut protocol, &mut game);
+    game
+}
+
+fn read_sections(p: &mut dyn TInputProtocol, game: &mut save::AldonGame) -> thrift::Result<()> {
+    p.read_struct_begin()?;
+    loop {
+        let field = p.read_field_begin()?;
+        if field.field_type == TType::Stop {
+            return Ok(());
+        }
+        match field.id {
+            Some(1) => game.stage = Some(save::Stage::read_from_in_protocol(p)?),
+            Some(2) => game.cast = Some(save::Cast::read_from_in_protocol(p)?),
+            Some(3) => game.fog = Some(save::Fog::read_from_in_protocol(p)?),
+            Some(4) => game.buttons = Some(save::Buttons::read_from_in_protocol(p)?),
+            Some(5) => game.world_id = Some(p.read_string()?),
+            Some(6) => game.version = Some(p.read_i32()?),
+            Some(7) => game.play_time = Some(OrderedFloat::from(p.read_double()?)),
+            _ => p.skip(field.field_type)?,
+        }
+        p.read_field_end()?;
+    }
+}
+
+/// CRC-32 as used by zip and png
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
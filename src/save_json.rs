@@ -0,0 +1,636 @@
+//! Converts saves to and from JSON so they can be read and edited by hand. Field names and types
+//! come from save.thrift itself, so the JSON always matches the save format without any
+//! per-struct code. Enums are written by name.
+//!
+//! `to_json` also adds a "summary" next to the save with things that take cross referencing to
+//! read: the player, inventories, the quest log as text, vars that are set and how much of each
+//! map has been explored and the state of each actor. `from_json` ignores the summary, only the
+//! "save" is encoded.
+use crate::{
+    body::Body,
+    buttons::Button,
+    console::Console,
+    data::{World, PROPS},
+    game::{Dialog, InvalidDataError, TransactionType},
+    migrate, save_file,
+    stage::Stage,
+    stats::PlayerStats,
+    thrift::save,
+};
+use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, rc::Rc};
+use thrift::{
+    protocol::{
+        TCompactInputProtocol, TCompactOutputProtocol, TFieldIdentifier, TInputProtocol,
+        TListIdentifier, TMapIdentifier, TOutputProtocol, TSerializable, TSetIdentifier,
+        TStructIdentifier, TType,
+    },
+    transport::{TBufferChannel, TBufferedReadTransport, TBufferedWriteTransport},
+};
+
+static SCHEMA: Lazy<Schema> =
+    Lazy::new(|| Schema::parse(include_str!("thrift/save.thrift")).expect("save.thrift to parse"));
+
+/// Decodes a save into `{"summary": ..., "save": ...}`
+pub fn to_json(save_bytes: &[u8]) -> Result<Value, InvalidDataError> {
+    let save_bytes = save_file::unwrap(save_bytes)?;
+    // The generated code checks the save is valid and is easier to summarize
+    let game = read_save(save_bytes)?;
+
+    let mut channel = TBufferChannel::with_capacity(save_bytes.len(), 0);
+    channel.set_readable_bytes(save_bytes);
+    let mut protocol = TCompactInputProtocol::new(TBufferedReadTransport::new(channel));
+    let tree = read_value(&mut protocol, &Type::Named("AldonGame".to_string())).map_err(err)?;
+
+    Ok(json!({ "summary": summary(&game), "save": tree }))
+}
+
+/// Encodes JSON from `to_json` back into a save. Takes either the whole output of `to_json` or
+/// just its "save". Errors if the edited stage wouldn't load, except in saves from custom worlds
+/// which are only checked when the game loads them.
+pub fn from_json(value: &Value) -> Result<Vec<u8>, InvalidDataError> {
+    let tree = value.get("save").unwrap_or(value);
+    let mut channel = TBufferChannel::with_capacity(0, 500000);
+    let mut protocol = TCompactOutputProtocol::new(TBufferedWriteTransport::new(&mut channel));
+    write_value(
+        &mut protocol,
+        &Type::Named("AldonGame".to_string()),
+        tree,
+        "save",
+    )?;
+    protocol.flush().map_err(err)?;
+    let bytes = channel.write_bytes();
+
+    check_stage(read_save(&bytes)?)?;
+    Ok(save_file::wrap(&bytes))
+}
+
+/// Loads the stage the way `AldonGame::load_save` would, to catch edits that break it
+fn check_stage(mut game: save::AldonGame) -> Result<(), InvalidDataError> {
+    migrate::migrate(&mut game)?;
+    let stage = game
+        .stage
+        .as_ref()
+        .ok_or(InvalidDataError::new("stage field missing"))?;
+    let Some(world) = game.world_id.as_deref().and_then(World::built_in) else {
+        return Ok(());
+    };
+    Stage::from_save(0.0, stage, Rc::new(NoDialog), world, Console::default())
+        .map_err(|err| InvalidDataError::new(&format!("stage: {}", err)))?;
+    Ok(())
+}
+
+/// Loading a stage never opens a dialog
+struct NoDialog;
+
+impl Dialog for NoDialog {
+    fn tell_message(&self, _title: &str, _portrait_id: u16, _msg_id: u16, _from_actor: u16) {}
+
+    fn execute_trade(&self, _kind: TransactionType, _body: Rc<Body>, _items: Vec<Rc<Body>>) {}
+
+    fn pickup(&self, _body: Rc<Body>, _items: Vec<Rc<Body>>) {}
+
+    fn buy_sell(&self, _body: Rc<Body>, _items: Vec<Rc<Body>>, _kind: TransactionType) {}
+
+    fn pick_button(&self, _button_idx: usize, _buttons: Vec<Button>) {}
+
+    fn stats(&self, _stats: &PlayerStats) {}
+
+    fn spellbook(&self, _spells: &[u16]) {}
+
+    fn get_transaction(&self) -> Vec<Rc<Body>> {
+        Vec::new()
+    }
+
+    fn remove_item(&self, _index: usize) -> Rc<Body> {
+        unreachable!("no dialog is open while checking a save")
+    }
+}
+
+fn read_save(save_bytes: &[u8]) -> Result<save::AldonGame, InvalidDataError> {
+    let mut channel = TBufferChannel::with_capacity(save_bytes.len(), 0);
+    channel.set_readable_bytes(save_bytes);
+    let mut protocol = TCompactInputProtocol::new(TBufferedReadTransport::new(channel));
+    save::AldonGame::read_from_in_protocol(&mut protocol).map_err(err)
+}
+
+fn err(e: thrift::Error) -> InvalidDataError {
+    InvalidDataError::new(&e.to_string())
+}
+
+fn summary(game: &save::AldonGame) -> Value {
+    let world = game
+        .world_id
+        .as_deref()
+        .map_or(Some(World::default()), World::built_in);
+    let stage = game.stage.clone().unwrap_or_default();
+    let cast = game.cast.clone().unwrap_or_default();
+
+    let player = stage.player.as_ref().map(|player| {
+        json!({
+            "name": player.name,
+            "race": player.race.map(|race| enum_name("RaceType", race.0)),
+            "class": player.klass.map(|class| enum_name("ClassType", class.0)),
+            "level": player.level,
+            "exp": player.exp,
+            "health": player.health,
+            "magic": player.magic,
+            "gold": player.gold,
+            "str": player.base_str,
+            "int": player.base_int,
+            "dex": player.base_dex,
+            "wis": player.base_wis,
+            "vit": player.base_vit,
+            "luck": player.base_luck,
+        })
+    });
+
+    // Inventories are saved by who carries them, see `Stage::save`
+    let owners = ["player", "pet", "quest_pet", "summoned_pet"];
+    let mut inventory = Map::new();
+    for (owner_id, items) in stage.inventory_by_id.iter().flatten() {
+        let owner = owners
+            .get(*owner_id as usize)
+            .map_or(owner_id.to_string(), |owner| owner.to_string());
+        let items = items.iter().map(|item| item_name(item)).collect();
+        inventory.insert(owner, Value::Array(items));
+    }
+
+    let quest_log: Vec<Value> = cast
+        .quest_log
+        .iter()
+        .flatten()
+        .map(|msg_id| {
+            let text = world
+                .and_then(|world| world.res.messages.get(&msg_id.to_string()))
+                .map(|msg| msg.value.clone());
+            json!({ "id": msg_id, "text": text })
+        })
+        .collect();
+
+    let vars: Map<String, Value> = cast
+        .vars
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter(|(_, value)| **value != 0)
+        .map(|(var, value)| (var.to_string(), json!(value)))
+        .collect();
+
+    let actors: Map<String, Value> = cast
+        .actor_save_by_id
+        .iter()
+        .flatten()
+        .map(|(actor_id, actor)| {
+            let state = if actor.dead == Some(true) {
+                json!("dead")
+            } else {
+                json!(actor.state)
+            };
+            (actor_id.to_string(), state)
+        })
+        .collect();
+
+    // Saves before version 2 have a bool per tile, later ones a bit
+    let fog = game.fog.clone().unwrap_or_default();
+    let bools = fog.fog_by_map.iter().flatten().map(|(map_id, fogged)| {
+        let clear = fogged.iter().filter(|fogged| !**fogged).count();
+        (map_id, clear, fogged.len())
+    });
+    let bits = fog.fog_bits_by_map.iter().flatten().map(|(map_id, bits)| {
+        let fogged: u32 = bits.iter().map(|byte| byte.count_ones()).sum();
+        (map_id, bits.len() * 8 - fogged as usize, bits.len() * 8)
+    });
+    let explored: Map<String, Value> = bools
+        .chain(bits)
+        .map(|(map_id, clear, tiles)| {
+            let percent = 100 * clear / tiles.max(1);
+            (map_id.to_string(), json!(format!("{}%", percent)))
+        })
+        .collect();
+
+    json!({
+        "version": game.version,
+        "world": game.world_id,
+        "map_id": stage.map_id,
+        "player": player,
+        "inventory": inventory,
+        "quest_log": quest_log,
+        "vars": vars,
+        "actors": actors,
+        "explored": explored,
+    })
+}
+
+fn item_name(item: &save::Body) -> Value {
+    let prop_id = item.prop_id.unwrap_or(0);
+    let mut name = PROPS
+        .get(&prop_id.to_string())
+        .map_or(format!("prop {}", prop_id), |prop| prop.name.clone());
+    if let Some(quantity) = item.quantity.filter(|quantity| *quantity > 1) {
+        name += &format!(" x{}", quantity);
+    }
+    if item.equiped == Some(true) {
+        name += " (equipped)";
+    }
+    Value::String(name)
+}
+
+fn enum_name(enum_name: &str, value: i32) -> Value {
+    match SCHEMA.enum_value_name(enum_name, value) {
+        Some(name) => json!(name),
+        None => json!(value),
+    }
+}
+
+fn read_value(p: &mut dyn TInputProtocol, ty: &Type) -> thrift::Result<Value> {
+    let value = match ty {
+        Type::Bool => json!(p.read_bool()?),
+        Type::I32 => json!(p.read_i32()?),
+        Type::I64 => json!(p.read_i64()?),
+        Type::Double => json!(p.read_double()?),
+        Type::String => json!(p.read_string()?),
+        Type::Binary => json!(general_purpose::STANDARD.encode(p.read_bytes()?)),
+        Type::List(element) => {
+            let ident = p.read_list_begin()?;
+            let items = (0..ident.size)
+                .map(|_| read_value(p, element))
+                .collect::<thrift::Result<_>>()?;
+            p.read_list_end()?;
+            Value::Array(items)
+        }
+        Type::Set(element) => {
+            let ident = p.read_set_begin()?;
+            let items = (0..ident.size)
+                .map(|_| read_value(p, element))
+                .collect::<thrift::Result<_>>()?;
+            p.read_set_end()?;
+            Value::Array(items)
+        }
+        Type::Map(key, value) => {
+            let ident = p.read_map_begin()?;
+            let mut map = Map::new();
+            for _ in 0..ident.size {
+                let key = match read_value(p, key)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                map.insert(key, read_value(p, value)?);
+            }
+            p.read_map_end()?;
+            Value::Object(map)
+        }
+        Type::Named(name) if SCHEMA.enums.contains_key(name) => enum_name(name, p.read_i32()?),
+        Type::Named(name) => {
+            let fields = SCHEMA.fields(name);
+            let mut map = Map::new();
+            p.read_struct_begin()?;
+            loop {
+                let ident = p.read_field_begin()?;
+                if ident.field_type == TType::Stop {
+                    break;
+                }
+                let field = fields.iter().find(|field| Some(field.id) == ident.id);
+                match field {
+                    // Fields that have since been removed from save.thrift
+                    None => p.skip(ident.field_type)?,
+                    Some(field) => {
+                        map.insert(field.name.clone(), read_value(p, &field.ty)?);
+                    }
+                }
+                p.read_field_end()?;
+            }
+            p.read_struct_end()?;
+            Value::Object(map)
+        }
+    };
+    Ok(value)
+}
+
+/// `path` names where `value` is in the save for errors
+fn write_value(
+    p: &mut dyn TOutputProtocol,
+    ty: &Type,
+    value: &Value,
+    path: &str,
+) -> Result<(), InvalidDataError> {
+    let expected =
+        |what: &str| InvalidDataError::new(&format!("{}: expected {}, got {}", path, what, value));
+    match ty {
+        Type::Bool => p
+            .write_bool(value.as_bool().ok_or_else(|| expected("a bool"))?)
+            .map_err(err),
+        Type::I32 => {
+            let number = value.as_i64().and_then(|number| i32::try_from(number).ok());
+            p.write_i32(number.ok_or_else(|| expected("an i32"))?)
+                .map_err(err)
+        }
+        Type::I64 => p
+            .write_i64(value.as_i64().ok_or_else(|| expected("an i64"))?)
+            .map_err(err),
+        Type::Double => p
+            .write_double(value.as_f64().ok_or_else(|| expected("a number"))?)
+            .map_err(err),
+        Type::String => p
+            .write_string(value.as_str().ok_or_else(|| expected("a string"))?)
+            .map_err(err),
+        Type::Binary => {
+            let bytes = value
+                .as_str()
+                .and_then(|text| general_purpose::STANDARD.decode(text).ok());
+            p.write_bytes(&bytes.ok_or_else(|| expected("base64"))?)
+                .map_err(err)
+        }
+        Type::List(element) | Type::Set(element) => {
+            let items = value.as_array().ok_or_else(|| expected("a list"))?;
+            let size = items.len() as i32;
+            match ty {
+                Type::List(_) => p.write_list_begin(&TListIdentifier::new(element.ttype(), size)),
+                _ => p.write_set_begin(&TSetIdentifier::new(element.ttype(), size)),
+            }
+            .map_err(err)?;
+            for (i, item) in items.iter().enumerate() {
+                write_value(p, element, item, &format!("{}[{}]", path, i))?;
+            }
+            match ty {
+                Type::List(_) => p.write_list_end(),
+                _ => p.write_set_end(),
+            }
+            .map_err(err)
+        }
+        Type::Map(key_ty, value_ty) => {
+            let map = value.as_object().ok_or_else(|| expected("an object"))?;
+            let ident = TMapIdentifier::new(key_ty.ttype(), value_ty.ttype(), map.len() as i32);
+            // JSON keys are strings, parse them back and write in the order the game would
+            let mut entries = Vec::new();
+            for (key, value) in map {
+                let path = format!("{}.{}", path, key);
+                let key = match **key_ty {
+                    Type::String => json!(key),
+                    _ => serde_json::from_str(key).map_err(|_| {
+                        InvalidDataError::new(&format!("{}: key isn't a number", path))
+                    })?,
+                };
+                entries.push((key, value, path));
+            }
+            entries.sort_by_key(|(key, _, _)| key.as_i64());
+            p.write_map_begin(&ident).map_err(err)?;
+            for (key, value, path) in entries {
+                write_value(p, key_ty, &key, &path)?;
+                write_value(p, value_ty, value, &path)?;
+            }
+            p.write_map_end().map_err(err)
+        }
+        Type::Named(name) if SCHEMA.enums.contains_key(name) => {
+            let number = match value {
+                Value::String(value_name) => SCHEMA.enum_value(name, value_name),
+                value => value.as_i64().and_then(|number| i32::try_from(number).ok()),
+            };
+            let number = number.ok_or_else(|| expected(&format!("a {}", name)))?;
+            p.write_i32(number).map_err(err)
+        }
+        Type::Named(name) => {
+            let object = value.as_object().ok_or_else(|| expected("an object"))?;
+            let fields = SCHEMA.fields(name);
+            if let Some(key) = object
+                .keys()
+                .find(|key| !fields.iter().any(|field| field.name == **key))
+            {
+                return Err(InvalidDataError::new(&format!(
+                    "{}: {} has no field '{}'",
+                    path, name, key
+                )));
+            }
+            p.write_struct_begin(&TStructIdentifier::new(name.as_str()))
+                .map_err(err)?;
+            for field in fields {
+                let Some(value) = object.get(&field.name).filter(|value| !value.is_null()) else {
+                    continue;
+                };
+                let ident = TFieldIdentifier::new(field.name.as_str(), field.ty.ttype(), field.id);
+                p.write_field_begin(&ident).map_err(err)?;
+                write_value(p, &field.ty, value, &format!("{}.{}", path, field.name))?;
+                p.write_field_end().map_err(err)?;
+            }
+            p.write_field_stop().map_err(err)?;
+            p.write_struct_end().map_err(err)
+        }
+    }
+}
+
+/// The structs, unions and enums declared in a .thrift file
+struct Schema {
+    /// Unions are written the same as structs so they're kept here too
+    structs: HashMap<String, Vec<Field>>,
+    enums: HashMap<String, Vec<(String, i32)>>,
+}
+
+struct Field {
+    id: i16,
+    name: String,
+    ty: Type,
+}
+
+enum Type {
+    Bool,
+    I32,
+    I64,
+    Double,
+    String,
+    /// Written as base64
+    Binary,
+    List(Box<Type>),
+    Set(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    /// A struct, union or enum
+    Named(String),
+}
+
+impl Type {
+    fn ttype(&self) -> TType {
+        match self {
+            Type::Bool => TType::Bool,
+            Type::I32 => TType::I32,
+            Type::I64 => TType::I64,
+            Type::Double => TType::Double,
+            Type::String | Type::Binary => TType::String,
+            Type::List(_) => TType::List,
+            Type::Set(_) => TType::Set,
+            Type::Map(_, _) => TType::Map,
+            Type::Named(name) if SCHEMA.enums.contains_key(name) => TType::I32,
+            Type::Named(_) => TType::Struct,
+        }
+    }
+}
+
+impl Schema {
+    /// Understands just the parts of the thrift language save.thrift uses
+    fn parse(text: &str) -> Result<Self, InvalidDataError> {
+        let mut tokens = Tokens::new(text);
+        let mut schema = Schema {
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+        };
+        while let Some(keyword) = tokens.next() {
+            let name = tokens.ident()?;
+            tokens.expect("{")?;
+            match keyword {
+                "struct" | "union" => {
+                    let mut fields = Vec::new();
+                    while !tokens.eat("}") {
+                        let id = tokens.number()? as i16;
+                        tokens.expect(":")?;
+                        tokens.eat("optional");
+                        tokens.eat("required");
+                        let ty = tokens.ty()?;
+                        let name = tokens.ident()?.to_string();
+                        tokens.eat(";");
+                        tokens.eat(",");
+                        fields.push(Field { id, name, ty });
+                    }
+                    schema.structs.insert(name.to_string(), fields);
+                }
+                "enum" => {
+                    let mut values = Vec::new();
+                    let mut next = 0;
+                    while !tokens.eat("}") {
+                        let name = tokens.ident()?.to_string();
+                        if tokens.eat("=") {
+                            next = tokens.number()?;
+                        }
+                        tokens.eat(";");
+                        tokens.eat(",");
+                        values.push((name, next));
+                        next += 1;
+                    }
+                    schema.enums.insert(name.to_string(), values);
+                }
+                other => {
+                    return Err(InvalidDataError::new(&format!(
+                        "unexpected '{}' in save.thrift",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(schema)
+    }
+
+    fn fields(&self, name: &str) -> &[Field] {
+        self.structs
+            .get(name)
+            .unwrap_or_else(|| panic!("save.thrift to declare {}", name))
+    }
+
+    fn enum_value_name(&self, enum_name: &str, value: i32) -> Option<&str> {
+        self.enums[enum_name]
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(name, _)| name.as_str())
+    }
+
+    fn enum_value(&self, enum_name: &str, value_name: &str) -> Option<i32> {
+        self.enums[enum_name]
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value_name))
+            .map(|(_, v)| *v)
+    }
+}
+
+struct Tokens<'a> {
+    tokens: std::iter::Peekable<std::vec::IntoIter<&'a str>>,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut tokens = Vec::new();
+        for line in text.lines() {
+            let line = line.split("//").next().unwrap();
+            let mut start = None;
+            for (i, c) in line.char_indices() {
+                let word_char = c.is_alphanumeric() || c == '_' || c == '-';
+                match (word_char, start) {
+                    (true, None) => start = Some(i),
+                    (false, Some(s)) => {
+                        tokens.push(&line[s..i]);
+                        start = None;
+                    }
+                    _ => {}
+                }
+                if !word_char && !c.is_whitespace() {
+                    tokens.push(&line[i..i + c.len_utf8()]);
+                }
+            }
+            if let Some(s) = start {
+                tokens.push(&line[s..]);
+            }
+        }
+        Self {
+            tokens: tokens.into_iter().peekable(),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.tokens.next()
+    }
+
+    /// Consumes `token` if it's next
+    fn eat(&mut self, token: &str) -> bool {
+        self.tokens.next_if_eq(&token).is_some()
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), InvalidDataError> {
+        match self.next() {
+            Some(next) if next == token => Ok(()),
+            next => Err(InvalidDataError::new(&format!(
+                "expected '{}' in save.thrift, got {:?}",
+                token, next
+            ))),
+        }
+    }
+
+    fn ident(&mut self) -> Result<&'a str, InvalidDataError> {
+        self.next()
+            .ok_or_else(|| InvalidDataError::new("save.thrift ends early"))
+    }
+
+    fn number(&mut self) -> Result<i32, InvalidDataError> {
+        let token = self.ident()?;
+        token
+            .parse()
+            .map_err(|_| InvalidDataError::new(&format!("expected a number, got '{}'", token)))
+    }
+
+    fn ty(&mut self) -> Result<Type, InvalidDataError> {
+        let ty = match self.ident()? {
+            "bool" => Type::Bool,
+            "i32" => Type::I32,
+            "i64" => Type::I64,
+            "double" => Type::Double,
+            "string" => Type::String,
+            "binary" => Type::Binary,
+            "list" => Type::List(self.element()?),
+            "set" => Type::Set(self.element()?),
+            "map" => {
+                self.expect("<")?;
+                let key = Box::new(self.ty()?);
+                self.expect(",")?;
+                let value = Box::new(self.ty()?);
+                self.expect(">")?;
+                Type::Map(key, value)
+            }
+            name => Type::Named(name.to_string()),
+        };
+        Ok(ty)
+    }
+
+    /// The `<T>` of a list or set
+    fn element(&mut self) -> Result<Box<Type>, InvalidDataError> {
+        self.expect("<")?;
+        let element = self.ty()?;
+        self.expect(">")?;
+        Ok(Box::new(element))
+    }
+}
@@ -0,0 +1,249 @@
+//! Named save slots kept in whatever storage the host has: local storage in the browser, a
+//! directory or memory natively. Hosts implement the few `SaveStore` methods that read and write
+//! records and get slots, metadata and backups from the provided methods. A game uses the store
+//! set with `AldonGame::set_save_store` for `save_slot`, `load_slot` and `save_slots`, and writes
+//! the "autosave" slot on every map change.
+//!
+//! Every save to a slot is written as a new record next to the old ones, and only after it's
+//! written are records older than `BACKUPS` removed. A crash while writing can lose the new save
+//! but never the ones before it.
+use crate::game::InvalidDataError;
+use bincode::Options;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The slot written whenever the player changes maps
+pub const AUTOSAVE_SLOT: &str = "autosave";
+
+/// How many older saves are kept for each slot
+pub const BACKUPS: usize = 2;
+
+/// What's shown for a save without loading it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlotInfo {
+    pub slot: String,
+    pub player_name: String,
+    pub class: String,
+    pub level: i32,
+    pub map_name: String,
+    /// ms spent playing over the life of the game
+    pub play_time: f64,
+    /// ms since the unix epoch when the save was written
+    pub timestamp: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    info: SlotInfo,
+    save: Vec<u8>,
+}
+
+pub trait SaveStore {
+    /// The record under `key`, if there is one
+    fn read(&self, key: &str) -> Option<Vec<u8>>;
+
+    fn write(&self, key: &str, record: &[u8]) -> Result<(), Box<dyn Error>>;
+
+    fn remove(&self, key: &str);
+
+    /// Every key with a record
+    fn keys(&self) -> Vec<String>;
+
+    /// ms since the unix epoch, to timestamp saves
+    fn now(&self) -> f64;
+
+    /// Writes `save` to the slot named in `info`, keeping the slot's last `BACKUPS` saves
+    fn save_slot(&self, mut info: SlotInfo, save: &[u8]) -> Result<(), Box<dyn Error>> {
+        check_slot_name(&info.slot)?;
+        info.timestamp = self.now();
+        let generations = generations(self, &info.slot);
+        let next = generations.last().map_or(0, |generation| generation + 1);
+        let slot = info.slot.clone();
+        let record = Record {
+            info,
+            save: save.to_vec(),
+        };
+        self.write(&key(&slot, next), &bincode_options().serialize(&record)?)?;
+
+        for generation in generations.iter().rev().skip(BACKUPS) {
+            self.remove(&key(&slot, *generation));
+        }
+        Ok(())
+    }
+
+    /// Saves in `slot`, newest first. Backups follow the newest save so a save that won't load
+    /// can fall back to the one before it.
+    fn load_slot(&self, slot: &str) -> Vec<(SlotInfo, Vec<u8>)> {
+        generations(self, slot)
+            .iter()
+            .rev()
+            .filter_map(|generation| read_record(self, &key(slot, *generation)))
+            .map(|record| (record.info, record.save))
+            .collect()
+    }
+
+    /// The newest save in each slot, most recently saved first
+    fn slots(&self) -> Vec<SlotInfo> {
+        let mut slot_names: Vec<String> = self
+            .keys()
+            .iter()
+            .filter_map(|key| parse_key(key))
+            .map(|(slot, _)| slot.to_string())
+            .collect();
+        slot_names.sort();
+        slot_names.dedup();
+
+        let mut slots: Vec<SlotInfo> = slot_names
+            .iter()
+            .filter_map(|slot| self.load_slot(slot).into_iter().next())
+            .map(|(info, _)| info)
+            .collect();
+        slots.sort_by(|a, b| b.timestamp.total_cmp(&a.timestamp));
+        slots
+    }
+
+    /// Removes a slot and its backups
+    fn delete_slot(&self, slot: &str) {
+        for generation in generations(self, slot) {
+            self.remove(&key(slot, generation));
+        }
+    }
+}
+
+/// Slot names end up in keys and file names so they're kept simple
+fn check_slot_name(slot: &str) -> Result<(), InvalidDataError> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_';
+    if slot.is_empty() || !slot.chars().all(allowed) {
+        return Err(InvalidDataError::new(&format!(
+            "slot name '{}' can only have letters, numbers, spaces, '-' and '_'",
+            slot
+        )));
+    }
+    Ok(())
+}
+
+fn key(slot: &str, generation: u64) -> String {
+    format!("{}.{}.sav", slot, generation)
+}
+
+fn parse_key(key: &str) -> Option<(&str, u64)> {
+    let (slot, generation) = key.strip_suffix(".sav")?.rsplit_once('.')?;
+    Some((slot, generation.parse().ok()?))
+}
+
+/// The generations written to `slot`, oldest first
+fn generations<S: SaveStore + ?Sized>(store: &S, slot: &str) -> Vec<u64> {
+    let mut generations: Vec<u64> = store
+        .keys()
+        .iter()
+        .filter_map(|key| parse_key(key))
+        .filter(|(key_slot, _)| *key_slot == slot)
+        .map(|(_, generation)| generation)
+        .collect();
+    generations.sort();
+    generations
+}
+
+/// Records that were cut off while writing don't decode and are skipped
+fn read_record<S: SaveStore + ?Sized>(store: &S, key: &str) -> Option<Record> {
+    let bytes = store.read(key)?;
+    bincode_options().deserialize(&bytes).ok()
+}
+
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |time| time.as_millis() as f64)
+}
+
+/// Keeps saves for as long as the process runs. For tests and the simulator.
+#[derive(Default)]
+pub struct MemorySaveStore {
+    records: RefCell<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemorySaveStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SaveStore for MemorySaveStore {
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        self.records.borrow().get(key).cloned()
+    }
+
+    fn write(&self, key: &str, record: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.records
+            .borrow_mut()
+            .insert(key.to_string(), record.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) {
+        self.records.borrow_mut().remove(key);
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.records.borrow().keys().cloned().collect()
+    }
+
+    fn now(&self) -> f64 {
+        unix_now()
+    }
+}
+
+/// Keeps each record in its own file in a directory
+pub struct FileSaveStore {
+    dir: PathBuf,
+}
+
+impl FileSaveStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+}
+
+impl SaveStore for FileSaveStore {
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.dir.join(key)).ok()
+    }
+
+    fn write(&self, key: &str, record: &[u8]) -> Result<(), Box<dyn Error>> {
+        // Renaming is atomic, a crash leaves at worst a stray .tmp file
+        let tmp = self.dir.join(format!("{}.tmp", key));
+        fs::write(&tmp, record)?;
+        fs::rename(&tmp, self.dir.join(key))?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.dir.join(key));
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .collect()
+    }
+
+    fn now(&self) -> f64 {
+        unix_now()
+    }
+}
@@ -3,12 +3,16 @@ use crate::{
     aldon_log,
     body::{ActionState, Body},
     combat::{
-        self, make_attack, Attack, BattleEventType, Missile, MissileEffect, MissileInfo,
-        MissileType, Motion, Strike,
+        self, make_attack, Attack, BattleEventType, Missile, MissileEffect, MissileType, Motion,
+        Strike,
     },
     condition::{self},
-    data::{self, PropTypeRes, SpawnerRes, SpellTarget, PROPS, SPELLS, WORLD},
-    game::{Dialog, InvalidDataError, CONSOLE},
+    console::Console,
+    data::{
+        self, DamageType, EffectRes, PropTypeRes, SpawnerRes, SpellTarget, World, PROPS, SPELLS,
+    },
+    events::{EventQueue, GameEvent},
+    game::{Dialog, InvalidDataError},
     js,
     search::search_path,
     thrift::{
@@ -16,11 +20,10 @@ use crate::{
         util::{box_vec, unbox_vec},
     },
 };
-use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use std::{
-    cell::{Cell, RefCell},
+    cell::{Cell, RefCell, RefMut},
     collections::{BTreeMap, HashSet},
-    fmt::Write,
     rc::Rc,
     vec::Vec,
 };
@@ -30,6 +33,7 @@ const IMPASSIBLE_TILES: [u8; 7] = [3, 0, 4, 1, 5, 2, 14];
 const SIGHT_BLOCKER_TILES: [u8; 5] = [3, 0, 1, 2, 14];
 
 pub(crate) struct Stage {
+    world: World,
     map_id: Cell<u16>,
     pub(crate) map: Cell<&'static data::MapRes>,
 
@@ -37,6 +41,8 @@ pub(crate) struct Stage {
     spawners: RefCell<Vec<Spawner>>,
     missiles: RefCell<Vec<Missile>>,
     traps: RefCell<Vec<Trap>>,
+    /// Items dropped by bodies killed this update, placed once the bodies aren't borrowed
+    loot: RefCell<Vec<Rc<Body>>>,
     occupancy: RefCell<Occupancy>,
     dialog: Rc<dyn Dialog>,
     player_has_moved: Cell<bool>,
@@ -46,24 +52,45 @@ pub(crate) struct Stage {
     // Actors that die in the current session are added here.  Does not
     // contain all actors to die ever.
     dead_actors: RefCell<HashSet<u16>>,
+
+    // All randomness in the game comes from here so a run can be reproduced from its seed
+    rng: RefCell<StdRng>,
+    seed: Cell<u64>,
+
+    /// Shared with the bodies on the stage, see `drain_events`
+    events: EventQueue,
+    /// The game's console, passed on to bodies as they're placed
+    console: Console,
 }
 
 impl Stage {
-    pub fn new(map_id: u16, dialog: Rc<dyn Dialog>) -> Self {
-        let map = &data::WORLD.maps[&map_id.to_string()];
+    pub fn new(
+        map_id: u16,
+        dialog: Rc<dyn Dialog>,
+        seed: u64,
+        world: World,
+        console: Console,
+    ) -> Self {
+        let map = &world.res.maps[&map_id.to_string()];
         Self {
+            world,
             map_id: Cell::new(map_id),
             map: Cell::new(map),
             bodies: RefCell::new(Vec::new()),
             spawners: RefCell::new(Vec::new()),
             occupancy: RefCell::new(Occupancy::new()),
             missiles: RefCell::new(Vec::new()),
+            loot: RefCell::new(Vec::new()),
             traps: RefCell::new(Vec::new()),
             dialog,
             dead_actors: RefCell::new(HashSet::new()),
             player_has_moved: Cell::new(false),
             player_start_position: Cell::new((0.0, 0.0)),
             now: Cell::new(0.0),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            seed: Cell::new(seed),
+            events: EventQueue::default(),
+            console,
         }
     }
 
@@ -71,10 +98,33 @@ impl Stage {
         self.now.get()
     }
 
+    /// The world the maps and actors come from
+    pub fn world(&self) -> World {
+        self.world
+    }
+
+    pub(crate) fn rng(&self) -> RefMut<'_, StdRng> {
+        self.rng.borrow_mut()
+    }
+
+    /// The seed the rng was last seeded with
+    pub fn seed(&self) -> u64 {
+        self.seed.get()
+    }
+
+    /// Draws a new seed from the rng and reseeds with it. Returns the new seed.
+    pub(crate) fn reseed(&self) -> u64 {
+        let seed = self.rng().gen();
+        *self.rng() = StdRng::seed_from_u64(seed);
+        self.seed.set(seed);
+        seed
+    }
+
     pub fn bodies(&self) -> Vec<Rc<Body>> {
         self.bodies.borrow().clone()
     }
 
+    #[cfg(feature = "web")]
     pub fn traps(&self) -> Vec<Trap> {
         self.traps.borrow().clone()
     }
@@ -83,6 +133,8 @@ impl Stage {
         now: f64,
         stage_save: &save::Stage,
         dialog: Rc<dyn Dialog>,
+        world: World,
+        console: Console,
     ) -> Result<Self, InvalidDataError> {
         let map_id: u16 = stage_save
             .map_id
@@ -90,16 +142,6 @@ impl Stage {
             .try_into()
             .map_err(|_| InvalidDataError::new("map_id not valid u16"))?;
 
-        let player_save = stage_save
-            .player
-            .as_ref()
-            .ok_or(InvalidDataError::new("player field missing"))?;
-
-        let inventory_by_id = stage_save
-            .inventory_by_id
-            .as_ref()
-            .ok_or(InvalidDataError::new("inventory_by_id field missing"))?;
-
         let traps: Vec<Trap> = stage_save
             .traps
             .iter()
@@ -108,19 +150,94 @@ impl Stage {
             .filter_map(|trap| match trap {
                 Ok(t) => Some(t),
                 Err(e) => {
-                    js::log(&format!("failed to load trap, ignoring: {}", e));
+                    console.debug(&format!("failed to load trap, ignoring: {}", e));
                     None
                 }
             })
             .collect();
 
-        let map = &data::WORLD.maps[&map_id.to_string()];
+        // Saves from before the rng was seeded get a fresh seed
+        let seed = match stage_save.rng_seed {
+            Some(seed) => seed as u64,
+            None => rand::random(),
+        };
+
+        let map = world
+            .res
+            .maps
+            .get(&map_id.to_string())
+            .ok_or(InvalidDataError::new("map_id not in world"))?;
+        let bodies = Self::load_party(now, stage_save)?;
+        let player = bodies[0].clone();
+        let stage = Self {
+            world,
+            map_id: Cell::new(map_id),
+            map: Cell::new(map),
+            bodies: RefCell::new(bodies),
+            spawners: RefCell::new(Vec::new()),
+            occupancy: RefCell::new(Occupancy::new()),
+            missiles: RefCell::new(Vec::new()),
+            loot: RefCell::new(Vec::new()),
+            dialog,
+            dead_actors: RefCell::new(HashSet::new()),
+            player_has_moved: Cell::new(false),
+            player_start_position: Cell::new((player.x(), player.y())),
+            now: Cell::new(now),
+            traps: RefCell::new(traps),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            seed: Cell::new(seed),
+            events: EventQueue::default(),
+            console,
+        };
+        for body in stage.bodies.borrow().iter() {
+            stage.adopt(body);
+        }
+
+        stage.load_map(map_id, true /*from_save*/);
+        if let Some(save_bodies) = &stage_save.bodies {
+            for save in save_bodies {
+                let body = Body::from_save(now, &save)?;
+                body.equip_default(now);
+                let body = Rc::new(body);
+                stage.place_body(body.clone());
+                stage
+                    .console
+                    .debug(&format!("loaded body: {} {:?}", body.name, body.actor_id));
+
+                if !save.from_spawner.unwrap_or(false) {
+                    continue;
+                }
+                for spawner in stage.spawners.borrow_mut().iter_mut() {
+                    if !spawner.is_match(body.clone()) {
+                        continue;
+                    }
+                    spawner.spawned.push(body.clone());
+                }
+            }
+        } else {
+            stage.console.debug("There are no bodies!!!");
+        }
+        Ok(stage)
+    }
+
+    /// The player and their pets from a save, player first
+    fn load_party(now: f64, stage_save: &save::Stage) -> Result<Vec<Rc<Body>>, InvalidDataError> {
+        let player_save = stage_save
+            .player
+            .as_ref()
+            .ok_or(InvalidDataError::new("player field missing"))?;
+
+        let inventory_by_id = stage_save
+            .inventory_by_id
+            .as_ref()
+            .ok_or(InvalidDataError::new("inventory_by_id field missing"))?;
+
         let mut bodies = Vec::new();
         let player = Rc::new(Body::from_save(now, &player_save)?);
         bodies.push(player.clone());
         let inventory = inventory_by_id
             .get(&0) // player inventory saved with id 0
-            .expect("Player has no inventory");
+            .ok_or(InvalidDataError::new("player has no inventory"))?;
 
         let inventory = unbox_vec(inventory);
         player.give_inventory(now, &inventory)?;
@@ -129,7 +246,7 @@ impl Stage {
             let pet = Body::from_save(now, pet_save)?;
             let inventory = inventory_by_id
                 .get(&1) // pet inventory saved with id 1
-                .expect("Pet has no inventory");
+                .ok_or(InvalidDataError::new("pet has no inventory"))?;
 
             let inventory = unbox_vec(inventory);
             pet.give_inventory(now, &inventory)?;
@@ -141,7 +258,7 @@ impl Stage {
         if let Some(quest_save) = &stage_save.quest_pet {
             let inventory = inventory_by_id
                 .get(&2) // quest pet saved with id 2
-                .expect("Quest pet has no inventory");
+                .ok_or(InvalidDataError::new("quest pet has no inventory"))?;
             let pet = Body::from_save(now, quest_save)?;
 
             let inventory = unbox_vec(inventory);
@@ -155,7 +272,7 @@ impl Stage {
             let pet = Body::from_save(now, save)?;
             let inventory = inventory_by_id
                 .get(&3) // summoned pet saved with id 3
-                .expect("Summoned pet has no inventory");
+                .ok_or(InvalidDataError::new("summoned pet has no inventory"))?;
 
             let inventory = unbox_vec(inventory);
             pet.give_inventory(now, &inventory)?;
@@ -164,44 +281,24 @@ impl Stage {
             player.give_summoned_pet(pet.clone());
             bodies.push(pet);
         }
-        let stage = Self {
-            map_id: Cell::new(map_id),
-            map: Cell::new(map),
-            bodies: RefCell::new(bodies),
-            spawners: RefCell::new(Vec::new()),
-            occupancy: RefCell::new(Occupancy::new()),
-            missiles: RefCell::new(Vec::new()),
-            dialog,
-            dead_actors: RefCell::new(HashSet::new()),
-            player_has_moved: Cell::new(false),
-            player_start_position: Cell::new((player.x(), player.y())),
-            now: Cell::new(now),
-            traps: RefCell::new(traps),
-        };
-
-        stage.load_map(map_id, true /*from_save*/);
-        if let Some(save_bodies) = &stage_save.bodies {
-            for save in save_bodies {
-                let body = Body::from_save(now, &save)?;
-                body.equip_default(now);
-                let body = Rc::new(body);
-                stage.place_body(body.clone());
-                js::log(&format!("loaded body: {} {:?}", body.name, body.actor_id));
+        Ok(bodies)
+    }
 
-                if !save.from_spawner.unwrap_or(false) {
-                    continue;
-                }
-                for spawner in stage.spawners.borrow_mut().iter_mut() {
-                    if !spawner.is_match(body.clone()) {
-                        continue;
-                    }
-                    spawner.spawned.push(body.clone());
-                }
-            }
-        } else {
-            js::log("There are no bodies!!!");
+    /// Puts a player from another game on the stage, with their pet but not their quest or
+    /// summoned pet which belong to the other game. The stage must not have a player yet.
+    pub(crate) fn place_hero(&self, hero: &save::Stage) -> Result<Rc<Body>, InvalidDataError> {
+        let hero = save::Stage {
+            quest_pet: None,
+            summoned_pet: None,
+            ..hero.clone()
+        };
+        let bodies = Self::load_party(self.now.get(), &hero)?;
+        let player = bodies[0].clone();
+        for body in &bodies {
+            self.adopt(body);
         }
-        Ok(stage)
+        self.bodies.borrow_mut().extend(bodies);
+        Ok(player)
     }
 
     pub fn sight(&self) -> [bool; 576] {
@@ -236,6 +333,40 @@ impl Stage {
     }
 
     pub fn save(&self, now: f64) -> save::Stage {
+        // Reseed so the game that keeps playing and a game loaded from this save draw the same
+        // random numbers
+        let rng_seed = self.reseed();
+        self.save_with_seed(now, rng_seed)
+    }
+
+    /// Like `save` but leaves the rng alone, storing the next number it will draw instead of a
+    /// new seed. Two stages in the same state have the same snapshot.
+    pub(crate) fn snapshot(&self, now: f64) -> save::Stage {
+        let next = self.rng().clone().gen();
+        self.save_with_seed(now, next)
+    }
+
+    /// Just the player, their pet and their inventories, for starting a new game with them
+    pub(crate) fn save_hero(&self, now: f64) -> save::Stage {
+        let player = self.get_player();
+        let pet = player.pet();
+        let mut inventory_by_id = BTreeMap::new();
+        inventory_by_id.insert(0, player.save_inventory(now));
+        if let Some(p) = &pet {
+            inventory_by_id.insert(1, p.save_inventory(now));
+        }
+        save::Stage {
+            map_id: Some(self.map_id.get() as i32),
+            player: Some(Box::new(player.save(now))),
+            inventory_by_id: Some(inventory_by_id),
+            pet: pet.map(|p| Box::new(p.save(now))),
+            bodies: Some(Vec::new()),
+            traps: Some(Vec::new()),
+            ..Default::default()
+        }
+    }
+
+    fn save_with_seed(&self, now: f64, rng_seed: u64) -> save::Stage {
         let player = self.get_player();
         let pet = player.pet();
         let quest_pet = player.quest_pet();
@@ -275,10 +406,12 @@ impl Stage {
             summoned_pet.map(|p| Box::new(p.save(now))),
             bodies,
             box_vec(&traps),
+            rng_seed as i64,
         )
     }
 
-    pub fn missiles(&self) -> Vec<MissileInfo> {
+    #[cfg(feature = "web")]
+    pub fn missiles(&self) -> Vec<combat::MissileInfo> {
         self.missiles.borrow().iter().map(|m| m.info()).collect()
     }
 
@@ -343,7 +476,8 @@ impl Stage {
     pub fn friend_at(&self, x: f64, y: f64) -> Option<Rc<Body>> {
         let mut friends = self.get_player().henchmen();
         friends.push(self.get_player());
-        js::log(&format!("finding friend at {}, {}", x, y));
+        self.console
+            .debug(&format!("finding friend at {}, {}", x, y));
 
         for body in self.bodies.borrow().iter() {
             let (body_x, body_y) = body.moving_to();
@@ -352,7 +486,8 @@ impl Stage {
             }
             let friend = friends.iter().any(|b| Rc::ptr_eq(body, b));
             if friend {
-                js::log(&format!("found {} at {}, {}", body.name, x, y));
+                self.console
+                    .debug(&format!("found {} at {}, {}", body.name, x, y));
                 return Some(body.clone());
             }
         }
@@ -392,7 +527,7 @@ impl Stage {
 
         match (intel.kind.get(), &body2.team()) {
             (_, Some(save::Team::ENEMY)) => {
-                js::log("attack!");
+                self.console.debug("attack!");
                 player.attack(body2.clone());
                 true
             }
@@ -402,7 +537,7 @@ impl Stage {
                 | save::IntelType::MESSAGE_BEARER,
                 _,
             ) => {
-                js::log("talk!");
+                self.console.debug("talk!");
                 if intel.has_message() {
                     player.talk_to(body2.clone());
                     true
@@ -492,19 +627,36 @@ impl Stage {
     }
 
     fn on_death(&self, attacker: &Body, attackee: &Body) {
-        aldon_log!("-{} dies...-", attackee.name);
+        aldon_log!(self.console, "-{} dies...-", attackee.name);
         attacker.set_action_state(ActionState::Idle);
         attacker.clear_attack();
 
-        if attacker.is_player() {
-            attacker.monster_reward(attackee.prop_id, attackee.level());
+        let (exp, gold) = if attacker.is_player() {
+            attacker.monster_reward(attackee.prop_id, attackee.level(), &mut self.rng())
         } else if attacker.is_pet() {
             let attackee_prop_id = attackee.prop_id;
             self.get_player()
-                .monster_reward(attackee_prop_id, attackee.level());
+                .monster_reward(attackee_prop_id, attackee.level(), &mut self.rng())
+        } else {
+            (0, 0)
+        };
+        if attacker.is_player() || attacker.is_pet() {
+            let (x, y) = attackee.moving_to();
+            for prop_id in combat::monster_loot(attackee.prop_id, &mut self.rng()) {
+                let prop = &PROPS[&prop_id.to_string()];
+                let item = Rc::new(Body::new(prop.name.clone(), None, prop_id, x, y));
+                item.persist();
+                self.loot.borrow_mut().push(item);
+            }
         }
+        self.emit(GameEvent::Kill {
+            target: attackee.into(),
+            killer: attacker.into(),
+            exp,
+            gold,
+        });
         if attackee.is_player() {
-            aldon_log!("*Game Over! press menu to continue*");
+            aldon_log!(self.console, "*Game Over! press menu to continue*");
         }
     }
 
@@ -532,7 +684,8 @@ impl Stage {
             }
         }
         for spawner in self.spawners.borrow_mut().iter_mut() {
-            if let Some(body) = spawner.update(now, &occupancy) {
+            if let Some(body) = spawner.update(now, &occupancy, &mut self.rng()) {
+                self.adopt(&body);
                 bodies.push(body);
             }
         }
@@ -555,16 +708,26 @@ impl Stage {
                 match effect {
                     MissileEffect::Strike(Strike {
                         damage,
+                        damage_type,
                         event,
                         target,
                     }) => {
-                        target.take_attack(now, *event, *damage, Some(attacker.clone()));
+                        target.take_attack(
+                            now,
+                            *event,
+                            *damage,
+                            *damage_type,
+                            Some(attacker.clone()),
+                        );
                         // TODO: check this automatically for all targets
                         if target.get_health() == 0 {
                             self.on_death(&attacker, target);
                         }
                     }
-                    MissileEffect::SplashDamage { amount } => {
+                    MissileEffect::SplashDamage {
+                        amount,
+                        damage_type,
+                    } => {
                         let Some(target) = self.enemy_at(missile.x().floor(), missile.y().floor())
                         else {
                             continue;
@@ -573,6 +736,7 @@ impl Stage {
                             now,
                             BattleEventType::Hit,
                             *amount,
+                            *damage_type,
                             Some(attacker.clone()),
                         );
                         // TODO: check this automatically for all targets
@@ -675,18 +839,19 @@ impl Stage {
                     // This logic is not sound and will sometimes loop forever. Just exit if we
                     // detect that is happening. This should be fixed when moving most of the
                     // implementation to Body::update
-                    js::log("HACKY BREAK!");
+                    self.console.debug("HACKY BREAK!");
                     break; // super hacky hack but better than crashing
                 }
                 if body.health.get() == 0 {
                     if let Some(actor_id) = body.actor_id {
                         if !self.dead_actors.borrow().contains(&actor_id) {
-                            js::log(&format!("actor {} has died by health", actor_id));
+                            self.console
+                                .debug(&format!("actor {} has died by health", actor_id));
                         }
                         self.dead_actors.borrow_mut().insert(actor_id);
                     }
                 }
-                body.update(now);
+                body.update(now, &mut self.rng());
 
                 if body.team() == Some(save::Team::ENEMY) {
                     self.maybe_trigger_trap(player.clone(), body);
@@ -862,11 +1027,18 @@ impl Stage {
                     }
                     if body.last_attack_time() + delay < now {
                         body.set_last_attack_time(now);
-                        let attacks = make_attack(now, body.clone(), attackee.clone());
+                        let attacks = make_attack(
+                            now,
+                            body.clone(),
+                            attackee.clone(),
+                            &mut self.rng(),
+                            &self.console,
+                        );
                         for attack in attacks {
                             match attack {
                                 Attack::Melee(Strike {
                                     mut damage,
+                                    damage_type,
                                     event,
                                     target,
                                 }) => {
@@ -876,10 +1048,16 @@ impl Stage {
                                         && (event == BattleEventType::Hit
                                             || event == BattleEventType::Crit)
                                     {
-                                        aldon_log!("*Sneak attack! x4 damage!*");
+                                        aldon_log!(self.console, "*Sneak attack! x4 damage!*");
                                         damage = damage * 4;
                                     }
-                                    target.take_attack(now, event, damage, Some(body.clone()));
+                                    target.take_attack(
+                                        now,
+                                        event,
+                                        damage,
+                                        damage_type,
+                                        Some(body.clone()),
+                                    );
                                 }
                                 Attack::Range(missile) => self.missiles.borrow_mut().push(missile),
                             }
@@ -892,6 +1070,9 @@ impl Stage {
                 break;
             }
         }
+        for item in self.loot.take() {
+            self.place_body(item);
+        }
     }
 
     pub fn load_map(&self, map_id: u16, from_save: bool) {
@@ -916,6 +1097,10 @@ impl Stage {
         self.player_has_moved.set(false);
         self.player_start_position.set(player_start_position);
 
+        if !from_save {
+            self.emit(GameEvent::MapChanged { map_id });
+        }
+
         self.bodies.borrow_mut().retain(|body| {
             let is_henchmen = henchmen.iter().any(|b| Rc::ptr_eq(body, b));
             let is_player = body.actor_id == Some(0);
@@ -924,7 +1109,7 @@ impl Stage {
         player.clear_talk();
 
         self.map_id.set(map_id);
-        let map = &WORLD.maps[&map_id.to_string()];
+        let map = &self.world.res.maps[&map_id.to_string()];
         self.map.set(map);
         for y in 0..24 {
             for x in 0..24 {
@@ -955,7 +1140,10 @@ impl Stage {
             // been loaded from a save file
 
             for spawner in self.spawners.borrow_mut().iter_mut() {
-                let mut bodies = spawner.spawn_all(now, &occupancy);
+                let mut bodies = spawner.spawn_all(now, &occupancy, &mut self.rng());
+                for body in &bodies {
+                    self.adopt(body);
+                }
                 self.bodies.borrow_mut().append(&mut bodies);
             }
         }
@@ -970,6 +1158,7 @@ impl Stage {
         Some(map.tiles[i as usize])
     }
 
+    #[cfg(feature = "web")]
     pub fn prop_at(&self, x: i64, y: i64) -> Option<u16> {
         if x < 0 || y < 0 || x > 24 || y > 24 {
             return None;
@@ -993,6 +1182,7 @@ impl Stage {
     ) -> Rc<Body> {
         let body = Body::new(name, actor_id, prop_id, x, y);
         body.set_health(i32::MAX);
+        self.adopt(&body);
         let wrapper = Rc::new(body);
         let mut bodies = self.bodies.borrow_mut();
         bodies.push(wrapper);
@@ -1033,14 +1223,36 @@ impl Stage {
             }
         };
         let body_kind = &PROPS[&prop_id.to_string()].name;
-        aldon_log!("(you now have a {} follower)", body_kind);
+        aldon_log!(self.console, "(you now have a {} follower)", body_kind);
         henchmen
     }
 
     pub fn place_body(&self, body: Rc<Body>) {
+        self.adopt(&body);
         self.bodies.borrow_mut().push(body);
     }
 
+    /// The game's console
+    pub(crate) fn console(&self) -> &Console {
+        &self.console
+    }
+
+    /// Queues an event for the host
+    pub(crate) fn emit(&self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    /// Everything that happened since the last call, oldest first
+    pub(crate) fn drain_events(&self) -> Vec<GameEvent> {
+        self.events.drain()
+    }
+
+    /// Has `body` log to the game's console and queue events with the stage's
+    fn adopt(&self, body: &Body) {
+        body.set_console(&self.console);
+        body.set_events(&self.events);
+    }
+
     pub fn get_body(&self, actor_id: u16) -> Option<Rc<Body>> {
         for body in self.bodies.borrow().iter().rev() {
             if body.actor_id == Some(actor_id) {
@@ -1067,7 +1279,8 @@ impl Stage {
             return body;
         }
         // This should never happen
-        js::log("get_player called but no player found! Creating a player...");
+        self.console
+            .debug("get_player called but no player found! Creating a player...");
         let player = self.create_body("Enter Name".to_string(), Some(0), 55, 12.0, 3.0);
         player.set_health(0);
         player
@@ -1075,10 +1288,10 @@ impl Stage {
 
     pub fn remove_body(&self, actor_id: u16) {
         let Some(body) = self.get_body(actor_id) else {
-            js::log("remove body got no body");
+            self.console.debug("remove body got no body");
             return;
         };
-        js::log("remove body is working");
+        self.console.debug("remove body is working");
         let foo: Vec<Option<u16>> = self
             .bodies
             .borrow()
@@ -1087,7 +1300,7 @@ impl Stage {
             .filter(|x| x.is_some())
             .collect();
 
-        js::log(&format!("bodies before: {:?}", foo));
+        self.console.debug(&format!("bodies before: {:?}", foo));
 
         self.remove_body_ref(body);
 
@@ -1099,7 +1312,7 @@ impl Stage {
             .filter(|x| x.is_some())
             .collect();
 
-        js::log(&format!("bodies before: {:?}", foo));
+        self.console.debug(&format!("bodies before: {:?}", foo));
     }
 
     pub fn remove_body_ref(&self, body: Rc<Body>) {
@@ -1108,7 +1321,8 @@ impl Stage {
         if let Some(i) = idx {
             if let Some(actor_id) = bodies[i].actor_id {
                 if !self.dead_actors.borrow().contains(&actor_id) {
-                    js::log(&format!("actor {} has died by remove", actor_id));
+                    self.console
+                        .debug(&format!("actor {} has died by remove", actor_id));
                 }
                 self.dead_actors.borrow_mut().insert(actor_id);
             }
@@ -1136,22 +1350,46 @@ impl Stage {
             match trap.kind {
                 save::TrapKind::SPARK1 => {
                     let attacker = self.get_player();
-                    body.take_attack(now, BattleEventType::Hit, 15, Some(attacker));
+                    body.take_attack(
+                        now,
+                        BattleEventType::Hit,
+                        15,
+                        DamageType::Physical,
+                        Some(attacker),
+                    );
                     body.battle_event(now, BattleEventType::Condition1);
                 }
                 save::TrapKind::SPARK2 => {
                     let attacker = self.get_player();
-                    body.take_attack(now, BattleEventType::Hit, 30, Some(attacker));
+                    body.take_attack(
+                        now,
+                        BattleEventType::Hit,
+                        30,
+                        DamageType::Physical,
+                        Some(attacker),
+                    );
                     body.battle_event(now, BattleEventType::Condition1);
                 }
                 save::TrapKind::SPARK3 => {
                     let attacker = self.get_player();
-                    body.take_attack(now, BattleEventType::Hit, 45, Some(attacker));
+                    body.take_attack(
+                        now,
+                        BattleEventType::Hit,
+                        45,
+                        DamageType::Physical,
+                        Some(attacker),
+                    );
                     body.battle_event(now, BattleEventType::Condition1);
                 }
                 save::TrapKind::FLAME1 => {
                     let attacker = self.get_player();
-                    body.take_attack(now, BattleEventType::Hit, 30, Some(attacker));
+                    body.take_attack(
+                        now,
+                        BattleEventType::Hit,
+                        30,
+                        DamageType::Fire,
+                        Some(attacker),
+                    );
                     let missiles = explosion(
                         now,
                         x,
@@ -1165,7 +1403,13 @@ impl Stage {
                 }
                 save::TrapKind::FLAME2 => {
                     let attacker = self.get_player();
-                    body.take_attack(now, BattleEventType::Hit, 30, Some(attacker));
+                    body.take_attack(
+                        now,
+                        BattleEventType::Hit,
+                        30,
+                        DamageType::Fire,
+                        Some(attacker),
+                    );
                     let missiles = explosion(
                         now,
                         x,
@@ -1193,158 +1437,68 @@ impl Stage {
                     body.battle_event(now, BattleEventType::Condition2);
                 }
                 save::TrapKind(i) => {
-                    js::log(&format!("Warning, unknown trap kind {}", i));
+                    self.console
+                        .debug(&format!("Warning, unknown trap kind {}", i));
                 }
             }
             false
         });
     }
 
+    /// Uses up one of the body's `prop_id` for its `effects` in props.json
     pub fn use_item(&self, body: &Body, prop_id: u16) {
         let prop = &PROPS[&prop_id.to_string()];
         if !prop.can_use(body.class(), body.level()) {
             return;
         }
         let now = self.now.get();
-        match prop_id {
-            // Trap, Spark I
-            101 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::SPARK1);
-            }
-
-            // Trap, Slowness I
-            180 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::SLOWNESS);
-            }
-
-            // potion, minor heal
-            19 => {
-                body.heal(10);
-                body.battle_event(now, BattleEventType::Condition1);
-            }
-
-            // potion, cure poison
-            210 => {
-                body.cure_poison();
-                body.battle_event(now, BattleEventType::Condition1);
-            }
-
-            // potion, purify
-            211 => {
-                body.cure_poison();
-                body.battle_event(now, BattleEventType::Condition1);
-            }
-
-            // Trap, Weakness I
-            213 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::WEAKNESS);
-            }
-
-            // Trap, Poison
-            214 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::POISON);
-            }
-
-            // bag, medicine
-            223 => {
-                body.heal(15);
-                body.battle_event(now, BattleEventType::Condition1);
-            }
-
-            // potion, quicksilver
-            315 => {
-                let c = condition::potion(now, save::ConditionType::DEXTERITY);
-                body.add_condition(c);
-                body.battle_event(now, BattleEventType::Condition1);
-                aldon_log!("*{} recieved positive Dex.*", body.name);
-            }
-
-            // potion, stone skin
-            316 => {
-                let c = condition::potion(now, save::ConditionType::ARMOR);
-                body.add_condition(c);
-                body.battle_event(now, BattleEventType::Condition1);
-                aldon_log!("*{} recieved Armor.*", body.name);
-            }
-
-            // potion, iron skin
-            317 => {
-                let c = condition::potion(now, save::ConditionType::ARMOR);
-                body.add_condition(c);
-                body.battle_event(now, BattleEventType::Condition1);
-                aldon_log!("*{} recieved Armor.*", body.name);
-            }
-
-            // potion, troll's blood
-            321 => {
-                let c = condition::potion_regen(now);
-                body.add_condition(c);
-                body.battle_event(now, BattleEventType::Condition1);
-                aldon_log!("*{} recieved Regen*", body.name);
-            }
-
-            // Trap, Spark II
-            365 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::SPARK2);
-            }
-
-            // Trap, Spark III
-            366 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::SPARK3);
-            }
-
-            // Trap, Flame I
-            367 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::FLAME1);
-            }
-
-            // Trap, Flame II
-            368 => {
-                let (x, y) = body.moving_from();
-                self.place_trap(x, y, save::TrapKind::FLAME2);
-            }
-
-            // potion, mana renewal
-            405 => {}
-
-            // potion, wood skin
-            89 => {
-                let c = condition::potion(now, save::ConditionType::ARMOR);
-                body.add_condition(c);
-                body.battle_event(now, BattleEventType::Condition1);
-                aldon_log!("*{} recieved Armor.*", body.name);
-            }
-
-            // potion, heal
-            91 => {
-                body.heal(25);
-                body.battle_event(now, BattleEventType::Condition1);
-            }
-
-            // potion, major heal
-            92 => {
-                body.heal(45);
-                body.battle_event(now, BattleEventType::Condition1);
-            }
-
-            // potion, iron arm
-            93 => {
-                let c = condition::potion(now, save::ConditionType::STRENGTH);
-                body.add_condition(c);
-                body.battle_event(now, BattleEventType::Condition1);
-                aldon_log!("*{} recieved positive Str.*", body.name);
+        let mut used = false;
+        for effect in &prop.effects {
+            match effect {
+                EffectRes::PlaceTrap { trap } => {
+                    let (x, y) = body.moving_from();
+                    self.place_trap(x, y, (*trap).into());
+                }
+                EffectRes::Heal { amount } => {
+                    body.heal(*amount);
+                    body.battle_event(now, BattleEventType::Condition1);
+                }
+                EffectRes::CurePoison => {
+                    body.cure_poison();
+                    body.battle_event(now, BattleEventType::Condition1);
+                }
+                EffectRes::Condition { stat } => {
+                    let kind = (*stat).into();
+                    let c = match kind {
+                        save::ConditionType::HEALTH | save::ConditionType::MANA => {
+                            condition::potion_regen(now, kind)
+                        }
+                        _ => condition::potion(now, kind),
+                    };
+                    body.add_condition(c);
+                    body.battle_event(now, BattleEventType::Condition1);
+                    match kind {
+                        save::ConditionType::ARMOR => {
+                            aldon_log!(self.console, "*{} recieved Armor.*", body.name)
+                        }
+                        save::ConditionType::HEALTH | save::ConditionType::MANA => {
+                            let name: &str = kind.into();
+                            aldon_log!(self.console, "*{} recieved {}*", body.name, name)
+                        }
+                        _ => {
+                            let name: &str = kind.into();
+                            aldon_log!(self.console, "*{} recieved positive {}.*", body.name, name)
+                        }
+                    }
+                }
+                EffectRes::UsedUp => {}
+                _ => continue,
             }
-
-            _ => return,
+            used = true;
+        }
+        if used {
+            body.take_item(prop_id)
         }
-        body.take_item(prop_id)
     }
 
     // Effecting a spell means it was already cast and now it is time to have some effect
@@ -1352,7 +1506,7 @@ impl Stage {
         match spell_id {
             // First, Second, Third, Fourth, Fifth Summon
             8 | 9 | 10 | 11 | 12 => {
-                let prop_id = combat::summon(spell_id);
+                let prop_id = combat::summon(spell_id, &mut self.rng());
                 let name = &PROPS[&prop_id.to_string()].name;
                 let pet = self.create_pet(
                     name,
@@ -1372,7 +1526,7 @@ impl Stage {
     // Casting a spell may fail (e.g. not enough mana, fizzles, no enemy). If successful a missile
     // will be created. Returns true if a spell was cast
     pub fn cast_spell(&self, spell_id: u16, caster: Rc<Body>, x: f64, y: f64) -> bool {
-        js::log(&format!(
+        self.console.debug(&format!(
             "casting spell: {}, {}, {}, {}",
             spell_id, caster.name, x, y
         ));
@@ -1380,7 +1534,7 @@ impl Stage {
 
         match spell.target {
             SpellTarget::None | SpellTarget::Corpse => {
-                if !caster.try_spell(self.now(), spell_id) {
+                if !caster.try_spell(self.now(), spell_id, &mut self.rng()) {
                     return true;
                 }
                 let missile = combat::spell(self.now.get(), spell_id, caster.clone(), x, y);
@@ -1397,7 +1551,7 @@ impl Stage {
                 let Some(target) = maybe_target else {
                     return false;
                 };
-                if !caster.try_spell(self.now(), spell_id) {
+                if !caster.try_spell(self.now(), spell_id, &mut self.rng()) {
                     return true;
                 }
                 let missile = combat::targeted_spell(
@@ -1405,6 +1559,7 @@ impl Stage {
                     spell_id,
                     caster.clone(),
                     target.clone(),
+                    &mut self.rng(),
                 );
                 self.missiles.borrow_mut().push(missile);
                 return true;
@@ -1417,7 +1572,6 @@ impl Stage {
 struct Spawner {
     res: &'static SpawnerRes,
     tick_deadline: Option<f64>,
-    rng: ThreadRng,
     spawned: Vec<Rc<Body>>,
 }
 
@@ -1427,7 +1581,6 @@ impl Spawner {
             res,
             spawned: Vec::new(),
             tick_deadline: None,
-            rng: rand::thread_rng(),
         }
     }
 
@@ -1452,11 +1605,11 @@ impl Spawner {
             && body.level() == self.res.level
     }
 
-    fn spawn_all(&mut self, now: f64, occupancy: &Occupancy) -> Vec<Rc<Body>> {
+    fn spawn_all(&mut self, now: f64, occupancy: &Occupancy, rng: &mut StdRng) -> Vec<Rc<Body>> {
         let mut bodies = Vec::new();
 
         while self.spawned.len() < self.res.max_creatures {
-            let Some(body) = self.spawn(now, occupancy) else {
+            let Some(body) = self.spawn(now, occupancy, rng) else {
                 continue;
             };
             self.spawned.push(body.clone());
@@ -1465,7 +1618,7 @@ impl Spawner {
         bodies
     }
 
-    fn spawn(&mut self, now: f64, occupancy: &Occupancy) -> Option<Rc<Body>> {
+    fn spawn(&mut self, now: f64, occupancy: &Occupancy, rng: &mut StdRng) -> Option<Rc<Body>> {
         let x_min = self.res.x;
         let x_max = self.res.x + self.res.width;
         let y_min = self.res.y;
@@ -1479,13 +1632,13 @@ impl Spawner {
                 }
             }
         }
-        let Some(&(x, y)) = spaces.choose(&mut self.rng) else {
+        let Some(&(x, y)) = spaces.choose(rng) else {
             return None;
         };
         let prop_id = *self
             .res
             .creatures
-            .choose(&mut self.rng)
+            .choose(rng)
             .expect("Spawner should always have at least one creature type");
 
         let prop = &PROPS[&prop_id.to_string()];
@@ -1508,7 +1661,7 @@ impl Spawner {
         return Some(Rc::new(body));
     }
 
-    fn update(&mut self, now: f64, occupancy: &Occupancy) -> Option<Rc<Body>> {
+    fn update(&mut self, now: f64, occupancy: &Occupancy, rng: &mut StdRng) -> Option<Rc<Body>> {
         self.spawned.retain(|body| body.get_health() > 0);
 
         if self.spawned.len() == self.res.max_creatures {
@@ -1518,10 +1671,10 @@ impl Spawner {
         match self.tick_deadline {
             Some(deadline) if now > deadline => {
                 self.tick_deadline = Some(now + self.res.delay * 50.0);
-                let rand: f64 = self.rng.gen();
+                let rand: f64 = rng.gen();
 
                 if rand < self.prob_spawn(self.spawned.len()) {
-                    let maybe_body = self.spawn(now, occupancy);
+                    let maybe_body = self.spawn(now, occupancy, rng);
 
                     if let Some(body) = &maybe_body {
                         self.spawned.push(body.clone());
@@ -1653,7 +1806,10 @@ fn explosion(
                 y0 + j as f64,
                 attacker.clone(),
                 kind.clone(),
-                vec![MissileEffect::SplashDamage { amount: damage }],
+                vec![MissileEffect::SplashDamage {
+                    amount: damage,
+                    damage_type: kind.damage_type(),
+                }],
             );
             missiles.push(missile);
         }
@@ -469,13 +469,15 @@ impl From<&TrapKind> for i32 {
 pub struct Fog {
   pub current_map: Option<i32>,
   pub fog_by_map: Option<BTreeMap<i32, Vec<bool>>>,
+  pub fog_bits_by_map: Option<BTreeMap<i32, Vec<u8>>>,
 }
 
 impl Fog {
-  pub fn new<F1, F2>(current_map: F1, fog_by_map: F2) -> Fog where F1: Into<Option<i32>>, F2: Into<Option<BTreeMap<i32, Vec<bool>>>> {
+  pub fn new<F1, F2, F3>(current_map: F1, fog_by_map: F2, fog_bits_by_map: F3) -> Fog where F1: Into<Option<i32>>, F2: Into<Option<BTreeMap<i32, Vec<bool>>>>, F3: Into<Option<BTreeMap<i32, Vec<u8>>>> {
     Fog {
       current_map: current_map.into(),
       fog_by_map: fog_by_map.into(),
+      fog_bits_by_map: fog_bits_by_map.into(),
     }
   }
 }
@@ -484,7 +486,8 @@ impl TSerializable for Fog {
   fn read_from_in_protocol(i_prot: &mut dyn TInputProtocol) -> thrift::Result<Fog> {
     i_prot.read_struct_begin()?;
     let mut f_1: Option<i32> = Some(0);
-    let mut f_2: Option<BTreeMap<i32, Vec<bool>>> = Some(BTreeMap::new());
+    let mut f_2: Option<BTreeMap<i32, Vec<bool>>> = None;
+    let mut f_3: Option<BTreeMap<i32, Vec<u8>>> = None;
     loop {
       let field_ident = i_prot.read_field_begin()?;
       if field_ident.field_type == TType::Stop {
@@ -513,6 +516,17 @@ impl TSerializable for Fog {
           i_prot.read_map_end()?;
           f_2 = Some(val);
         },
+        3 => {
+          let map_ident = i_prot.read_map_begin()?;
+          let mut val: BTreeMap<i32, Vec<u8>> = BTreeMap::new();
+          for _ in 0..map_ident.size {
+            let map_key_3 = i_prot.read_i32()?;
+            let map_val_4 = i_prot.read_bytes()?;
+            val.insert(map_key_3, map_val_4);
+          }
+          i_prot.read_map_end()?;
+          f_3 = Some(val);
+        },
         _ => {
           i_prot.skip(field_ident.field_type)?;
         },
@@ -523,6 +537,7 @@ impl TSerializable for Fog {
     let ret = Fog {
       current_map: f_1,
       fog_by_map: f_2,
+      fog_bits_by_map: f_3,
     };
     Ok(ret)
   }
@@ -548,6 +563,16 @@ impl TSerializable for Fog {
       o_prot.write_map_end()?;
       o_prot.write_field_end()?
     }
+    if let Some(ref fld_var) = self.fog_bits_by_map {
+      o_prot.write_field_begin(&TFieldIdentifier::new("fog_bits_by_map", TType::Map, 3))?;
+      o_prot.write_map_begin(&TMapIdentifier::new(TType::I32, TType::String, fld_var.len() as i32))?;
+      for (k, v) in fld_var {
+        o_prot.write_i32(*k)?;
+        o_prot.write_bytes(v)?;
+      }
+      o_prot.write_map_end()?;
+      o_prot.write_field_end()?
+    }
     o_prot.write_field_stop()?;
     o_prot.write_struct_end()
   }
@@ -1594,10 +1619,11 @@ pub struct Stage {
   pub summoned_pet: Option<Box<Body>>,
   pub bodies: Option<Vec<Box<Body>>>,
   pub traps: Option<Vec<Box<Trap>>>,
+  pub rng_seed: Option<i64>,
 }
 
 impl Stage {
-  pub fn new<F1, F2, F3, F4, F5, F6, F7, F8>(map_id: F1, player: F2, inventory_by_id: F3, pet: F4, quest_pet: F5, summoned_pet: F6, bodies: F7, traps: F8) -> Stage where F1: Into<Option<i32>>, F2: Into<Option<Box<Body>>>, F3: Into<Option<BTreeMap<i32, Vec<Box<Body>>>>>, F4: Into<Option<Box<Body>>>, F5: Into<Option<Box<Body>>>, F6: Into<Option<Box<Body>>>, F7: Into<Option<Vec<Box<Body>>>>, F8: Into<Option<Vec<Box<Trap>>>> {
+  pub fn new<F1, F2, F3, F4, F5, F6, F7, F8, F9>(map_id: F1, player: F2, inventory_by_id: F3, pet: F4, quest_pet: F5, summoned_pet: F6, bodies: F7, traps: F8, rng_seed: F9) -> Stage where F1: Into<Option<i32>>, F2: Into<Option<Box<Body>>>, F3: Into<Option<BTreeMap<i32, Vec<Box<Body>>>>>, F4: Into<Option<Box<Body>>>, F5: Into<Option<Box<Body>>>, F6: Into<Option<Box<Body>>>, F7: Into<Option<Vec<Box<Body>>>>, F8: Into<Option<Vec<Box<Trap>>>>, F9: Into<Option<i64>> {
     Stage {
       map_id: map_id.into(),
       player: player.into(),
@@ -1607,6 +1633,7 @@ impl Stage {
       summoned_pet: summoned_pet.into(),
       bodies: bodies.into(),
       traps: traps.into(),
+      rng_seed: rng_seed.into(),
     }
   }
 }
@@ -1622,6 +1649,7 @@ impl TSerializable for Stage {
     let mut f_6: Option<Box<Body>> = None;
     let mut f_7: Option<Vec<Box<Body>>> = Some(Vec::new());
     let mut f_8: Option<Vec<Box<Trap>>> = Some(Vec::new());
+    let mut f_9: Option<i64> = None;
     loop {
       let field_ident = i_prot.read_field_begin()?;
       if field_ident.field_type == TType::Stop {
@@ -1686,6 +1714,10 @@ impl TSerializable for Stage {
           i_prot.read_list_end()?;
           f_8 = Some(val);
         },
+        9 => {
+          let val = i_prot.read_i64()?;
+          f_9 = Some(val);
+        },
         _ => {
           i_prot.skip(field_ident.field_type)?;
         },
@@ -1702,6 +1734,7 @@ impl TSerializable for Stage {
       summoned_pet: f_6,
       bodies: f_7,
       traps: f_8,
+      rng_seed: f_9,
     };
     Ok(ret)
   }
@@ -1765,6 +1798,11 @@ impl TSerializable for Stage {
       o_prot.write_list_end()?;
       o_prot.write_field_end()?
     }
+    if let Some(fld_var) = self.rng_seed {
+      o_prot.write_field_begin(&TFieldIdentifier::new("rng_seed", TType::I64, 9))?;
+      o_prot.write_i64(fld_var)?;
+      o_prot.write_field_end()?
+    }
     o_prot.write_field_stop()?;
     o_prot.write_struct_end()
   }
@@ -3067,15 +3105,21 @@ pub struct AldonGame {
   pub cast: Option<Cast>,
   pub fog: Option<Fog>,
   pub buttons: Option<Buttons>,
+  pub world_id: Option<String>,
+  pub version: Option<i32>,
+  pub play_time: Option<OrderedFloat<f64>>,
 }
 
 impl AldonGame {
-  pub fn new<F1, F2, F3, F4>(stage: F1, cast: F2, fog: F3, buttons: F4) -> AldonGame where F1: Into<Option<Stage>>, F2: Into<Option<Cast>>, F3: Into<Option<Fog>>, F4: Into<Option<Buttons>> {
+  pub fn new<F1, F2, F3, F4, F5, F6, F7>(stage: F1, cast: F2, fog: F3, buttons: F4, world_id: F5, version: F6, play_time: F7) -> AldonGame where F1: Into<Option<Stage>>, F2: Into<Option<Cast>>, F3: Into<Option<Fog>>, F4: Into<Option<Buttons>>, F5: Into<Option<String>>, F6: Into<Option<i32>>, F7: Into<Option<OrderedFloat<f64>>> {
     AldonGame {
       stage: stage.into(),
       cast: cast.into(),
       fog: fog.into(),
       buttons: buttons.into(),
+      world_id: world_id.into(),
+      version: version.into(),
+      play_time: play_time.into(),
     }
   }
 }
@@ -3087,6 +3131,9 @@ impl TSerializable for AldonGame {
     let mut f_2: Option<Cast> = None;
     let mut f_3: Option<Fog> = None;
     let mut f_4: Option<Buttons> = None;
+    let mut f_5: Option<String> = None;
+    let mut f_6: Option<i32> = None;
+    let mut f_7: Option<OrderedFloat<f64>> = None;
     loop {
       let field_ident = i_prot.read_field_begin()?;
       if field_ident.field_type == TType::Stop {
@@ -3110,6 +3157,18 @@ impl TSerializable for AldonGame {
           let val = Buttons::read_from_in_protocol(i_prot)?;
           f_4 = Some(val);
         },
+        5 => {
+          let val = i_prot.read_string()?;
+          f_5 = Some(val);
+        },
+        6 => {
+          let val = i_prot.read_i32()?;
+          f_6 = Some(val);
+        },
+        7 => {
+          let val = OrderedFloat::from(i_prot.read_double()?);
+          f_7 = Some(val);
+        },
         _ => {
           i_prot.skip(field_ident.field_type)?;
         },
@@ -3122,6 +3181,9 @@ impl TSerializable for AldonGame {
       cast: f_2,
       fog: f_3,
       buttons: f_4,
+      world_id: f_5,
+      version: f_6,
+      play_time: f_7,
     };
     Ok(ret)
   }
@@ -3148,6 +3210,21 @@ impl TSerializable for AldonGame {
       fld_var.write_to_out_protocol(o_prot)?;
       o_prot.write_field_end()?
     }
+    if let Some(ref fld_var) = self.world_id {
+      o_prot.write_field_begin(&TFieldIdentifier::new("world_id", TType::String, 5))?;
+      o_prot.write_string(fld_var)?;
+      o_prot.write_field_end()?
+    }
+    if let Some(fld_var) = self.version {
+      o_prot.write_field_begin(&TFieldIdentifier::new("version", TType::I32, 6))?;
+      o_prot.write_i32(fld_var)?;
+      o_prot.write_field_end()?
+    }
+    if let Some(fld_var) = self.play_time {
+      o_prot.write_field_begin(&TFieldIdentifier::new("play_time", TType::Double, 7))?;
+      o_prot.write_double(fld_var.into())?;
+      o_prot.write_field_end()?
+    }
     o_prot.write_field_stop()?;
     o_prot.write_struct_end()
   }
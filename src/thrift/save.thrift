@@ -1,7 +1,10 @@
 // Describe how game data is to be serialized/deserialized in a backwards compatible way
 struct Fog {
     1: i32 current_map;
-    2: map<i32, list<bool>> fog_by_map;
+    // A bool per tile in saves before version 2, see migrate.rs
+    2: optional map<i32, list<bool>> fog_by_map;
+    // A bit per tile, set while the tile is fogged
+    3: optional map<i32, binary> fog_bits_by_map;
 }
 
 struct Actor {
@@ -65,6 +68,8 @@ struct Stage {
     6: optional Body summoned_pet;
     7: list<Body> bodies;
     8: list<Trap> traps;
+    // seeds the game's random number generator when loading
+    9: optional i64 rng_seed;
 }
 
 enum ClassType {
@@ -212,6 +217,11 @@ struct AldonGame {
     2: Cast cast;
     3: Fog fog;
     4: Buttons buttons;
+    5: optional string world_id;
+    // Missing in saves from before saves were versioned, which are version 0. See migrate.rs
+    6: optional i32 version;
+    // ms spent playing, for save slots
+    7: optional double play_time;
 }
 
 enum TrapKind {
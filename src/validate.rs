@@ -0,0 +1,287 @@
+//! Checks the world data for mistakes that would otherwise only show up as a panic or a stuck
+//! actor while playing: actor byte code that doesn't decode, jumps to states that don't exist,
+//! and references to messages, props, vars and maps that don't exist.
+use crate::{
+    bytecode::{self, Action},
+    cast::VAR_COUNT,
+    data::{ActorRes, MapRes, WorldRes, PROPS},
+    thrift::save::{ClassType, IntelType, Team},
+};
+use std::{collections::HashMap, fmt};
+
+/// Something wrong in the world data and where it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub map_id: u16,
+    pub actor_id: Option<u16>,
+    pub state: Option<u16>,
+    pub message: String,
+    /// Playing into it would panic, e.g. a map, prop or message that doesn't exist, rather than
+    /// just leave an actor doing the wrong thing
+    pub fatal: bool,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "map {}", self.map_id)?;
+        if let Some(actor_id) = self.actor_id {
+            write!(f, " actor {}", actor_id)?;
+        }
+        if let Some(state) = self.state {
+            write!(f, " state {:03}", state)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Finds every problem in `world`, ordered by map
+pub fn validate(world: &WorldRes) -> Vec<Problem> {
+    let mut map_ids: Vec<u16> = world.maps.keys().filter_map(|id| id.parse().ok()).collect();
+    map_ids.sort();
+
+    let mut problems = Vec::new();
+    for id in world.maps.keys() {
+        if id.parse::<u16>().is_err() {
+            problems.push(Problem {
+                map_id: 0,
+                actor_id: None,
+                state: None,
+                message: format!("map id '{}' is not a number", id),
+                fatal: true,
+            });
+        }
+    }
+    for map_id in map_ids {
+        let map = &world.maps[&map_id.to_string()];
+        let mut checker = Checker {
+            world,
+            map,
+            map_id,
+            actor_id: None,
+            state: None,
+            problems: &mut problems,
+        };
+        checker.check_map();
+    }
+    problems
+}
+
+struct Checker<'a> {
+    world: &'a WorldRes,
+    map: &'a MapRes,
+    map_id: u16,
+    actor_id: Option<u16>,
+    state: Option<u16>,
+    problems: &'a mut Vec<Problem>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, message: String) {
+        self.push(message, false);
+    }
+
+    fn report_fatal(&mut self, message: String) {
+        self.push(message, true);
+    }
+
+    fn push(&mut self, message: String, fatal: bool) {
+        self.problems.push(Problem {
+            map_id: self.map_id,
+            actor_id: self.actor_id,
+            state: self.state,
+            message,
+            fatal,
+        });
+    }
+
+    fn check_map(&mut self) {
+        let neighbours = [
+            ("north", self.map.north),
+            ("south", self.map.south),
+            ("east", self.map.east),
+            ("west", self.map.west),
+        ];
+        for (direction, map_id) in neighbours {
+            if let Some(map_id) = map_id {
+                self.check_map_id(map_id, &format!("{} neighbour", direction));
+            }
+        }
+        for teleport in self.map.teleports.iter() {
+            let what = format!("teleport at ({}, {})", teleport.from_x, teleport.from_y);
+            self.check_map_id(teleport.id, &what);
+        }
+        for placement in self.map.props.iter() {
+            let what = format!("prop placed at ({}, {})", placement.x, placement.y);
+            self.check_prop(placement.id, &what);
+        }
+        for spawner in self.map.spawners.iter() {
+            for prop_id in spawner.creatures.iter() {
+                let what = format!("spawner at ({}, {})", spawner.x, spawner.y);
+                self.check_prop(*prop_id, &what);
+            }
+        }
+
+        // Decode everything first so jumps between actors can be checked
+        let mut programs: HashMap<u16, Vec<Action>> = HashMap::new();
+        for actor in self.map.actors.iter() {
+            let Some(actions) = &actor.actions else {
+                continue;
+            };
+            match bytecode::decode(actions) {
+                Ok(actions) => {
+                    programs.insert(actor.id, actions);
+                }
+                Err(err) => {
+                    self.actor_id = Some(actor.id);
+                    self.report(format!("byte code doesn't decode: {}", err));
+                }
+            }
+        }
+        for actor in self.map.actors.iter() {
+            if let Some(actions) = programs.get(&actor.id) {
+                self.check_actor(actor, actions, &programs);
+            }
+        }
+        self.actor_id = None;
+        self.state = None;
+    }
+
+    fn check_actor(
+        &mut self,
+        actor: &ActorRes,
+        actions: &[Action],
+        programs: &HashMap<u16, Vec<Action>>,
+    ) {
+        self.actor_id = Some(actor.id);
+        for action in actions {
+            self.state = Some(action.state);
+            self.check_action(actor, action, programs);
+        }
+        self.state = None;
+    }
+
+    fn check_action(
+        &mut self,
+        actor: &ActorRes,
+        action: &Action,
+        programs: &HashMap<u16, Vec<Action>>,
+    ) {
+        let p = &action.params;
+        let own_states = &programs[&actor.id];
+        match action.name() {
+            "SETMESSAGE" | "TELLMESSAGE" | "ADDQUESTLOG" | "REMOVEQUESTLOG" => {
+                self.check_message(p[0], action.name())
+            }
+            "CREATESELF" | "ADDSELLITEM" => self.check_prop(p[0], action.name()),
+            "GIVEITEM" | "DROPITEM" | "TAKEITEM" | "GIVEHENCHMEN" | "GIVEQUESTPET" => {
+                self.check_prop(p[1], action.name())
+            }
+            "SPAWN" => self.check_prop(p[0], action.name()),
+            "SETVAR" | "ADDVAR" | "SUBVAR" => self.check_var(p[0] >> 8, action.name()),
+            "MAPSETLOC" => self.check_map_id(p[0], action.name()),
+            "SETSTATE" => {
+                // Jumping other actors only works if they're on the same map
+                if let Some(states) = programs.get(&p[0]) {
+                    self.check_state(states, p[1], &format!("SETSTATE of actor {}", p[0]));
+                }
+            }
+            "WAIT" => {
+                let cond_type = p[3] >> 8;
+                if cond_type == 3 {
+                    for (target, choice) in p[0..3].iter().zip(["A", "B", "C"]) {
+                        let what = format!("WAIT RESPONSE choice {}", choice);
+                        self.check_state(own_states, *target, &what);
+                    }
+                } else {
+                    self.check_cond(cond_type, p[0], p[1], "WAIT");
+                }
+            }
+            "IF" => {
+                self.check_cond(p[3] >> 8, p[0], p[1], "IF");
+                self.check_state(own_states, p[4], "IF");
+            }
+            "SETINTEL" => {
+                if IntelType::try_from((p[1] >> 8) as u8).is_err() {
+                    self.report(format!("SETINTEL with unknown intel type {}", p[1] >> 8));
+                }
+                let hostile_to = (p[1] & 0xff) as u8;
+                if hostile_to > 0 && Team::try_from(hostile_to).is_err() {
+                    self.report(format!("SETINTEL hostile to unknown team {}", hostile_to));
+                }
+            }
+            "SETTEAM" if Team::try_from((p[1] >> 8) as u8).is_err() => {
+                self.report(format!("SETTEAM with unknown team {}", p[1] >> 8));
+            }
+            "EXECUTETRADE" if p[0] >> 8 > 1 => {
+                self.report(format!(
+                    "EXECUTETRADE with unknown trade type {}",
+                    p[0] >> 8
+                ));
+            }
+            "TAKEPET" if p[1] >> 8 > 2 => {
+                self.report(format!("TAKEPET with unknown pet type {}", p[1] >> 8));
+            }
+            _ => {}
+        }
+    }
+
+    /// Mirrors the conditions `Cond::new` understands
+    fn check_cond(&mut self, cond_type: u16, param_a: u16, param_b: u16, what: &str) {
+        match cond_type {
+            0 | 1 | 2 | 8 | 11 | 16 => {}
+            4 | 12 | 13 | 15 => self.check_prop(param_a, &format!("{} condition", what)),
+            5..=7 => self.check_var(param_a >> 8, &format!("{} condition", what)),
+            9 => {
+                if !ClassType::ENUM_VALUES.contains(&ClassType((param_b >> 8) as i32)) {
+                    self.report(format!("{} checks unknown class {}", what, param_b >> 8));
+                }
+            }
+            14 => {
+                if param_a >> 8 > 2 {
+                    self.report(format!("{} checks unknown race {}", what, param_a >> 8));
+                }
+            }
+            _ => self.report(format!(
+                "{} with unknown condition type {}",
+                what, cond_type
+            )),
+        }
+    }
+
+    /// Jumping to a state that doesn't exist silently runs the next state after it instead.
+    /// Jumping past the last state is fine, it's how actors stop.
+    fn check_state(&mut self, states: &[Action], state: u16, what: &str) {
+        let last = states.iter().map(|action| action.state).max().unwrap_or(0);
+        // Every actor starts in state 0, even if it has no actions there
+        if state != 0 && state <= last && !states.iter().any(|action| action.state == state) {
+            self.report(format!("{} jumps to missing state {:03}", what, state));
+        }
+    }
+
+    fn check_message(&mut self, msg_id: u16, what: &str) {
+        if !self.world.messages.contains_key(&msg_id.to_string()) {
+            self.report_fatal(format!("{} uses unknown message {}", what, msg_id));
+        }
+    }
+
+    fn check_prop(&mut self, prop_id: u16, what: &str) {
+        if !PROPS.contains_key(&prop_id.to_string()) {
+            self.report_fatal(format!("{} uses unknown prop {}", what, prop_id));
+        }
+    }
+
+    fn check_var(&mut self, var: u16, what: &str) {
+        if var as usize >= VAR_COUNT {
+            self.report(format!(
+                "{} uses var {}, there are only {}",
+                what, var, VAR_COUNT
+            ));
+        }
+    }
+
+    fn check_map_id(&mut self, map_id: u16, what: &str) {
+        if !self.world.maps.contains_key(&map_id.to_string()) {
+            self.report_fatal(format!("{} goes to unknown map {}", what, map_id));
+        }
+    }
+}
@@ -4,14 +4,14 @@ use crate::{
     buttons::Button,
     combat::{BattleEvent, BattleEventType, MissileType},
     data::*,
-    game::CONSOLE,
     js,
     stage::Stage,
     thrift::save::{ClassType, RaceType},
-    AldonHtmlCanvasGame,
 };
 use wasm_bindgen::prelude::*;
 
+use super::AldonHtmlCanvasGame;
+
 // Maps a tile id to a frame id. For some reason in the original game
 // these numbers are almost the same but not quite.
 const FRAME_BY_TILE: [u16; 14] = [0, 1, 2, 3, 4, 7, 6, 5, 10, 11, 12, 13, 14, 15];
@@ -95,8 +95,10 @@ impl AldonHtmlCanvasGame {
 
     #[wasm_bindgen]
     pub fn render(&mut self, now_js: js_sys::BigInt) {
-        let now = now_js.as_f64().unwrap();
-        let animation_idx = ((now / 200.0).floor() % 3.0) as u16;
+        let wall = now_js.as_f64().unwrap();
+        let animation_idx = ((wall / 200.0).floor() % 3.0) as u16;
+        // Bodies animate on the game clock, which their battle events are timed by
+        let now = self.game.now();
         let (viewport_width, viewport_height) = self.viewport_size();
         let ctx = self.ctx();
         let stage_ctx = self.stage_ctx();
@@ -321,9 +323,8 @@ impl AldonHtmlCanvasGame {
         ctx.set_font("18px PalmOS");
         ctx.set_fill_style(&JsValue::from("white"));
         let text_height = 11.0;
-        let console = CONSOLE.lock().unwrap();
         let mut y = viewport_height - 2.0;
-        for line in console.iter() {
+        for line in self.game.console().lines().iter().rev() {
             if y - text_height < stage_height {
                 break;
             }
@@ -425,7 +426,7 @@ impl AldonHtmlCanvasGame {
         }
         // props
         let map_id = self.game.stage.map_id();
-        let map = &WORLD.maps[&map_id.to_string()];
+        let map = &self.game.world().res.maps[&map_id.to_string()];
         for prop in &map.props {
             let Some(frame) = minimap_prop_frame(prop.id) else {
                 continue;
@@ -693,6 +694,8 @@ impl BattleEvent {
             BattleEventType::Fizzle => None,
             BattleEventType::Condition1 => Some(1670),
             BattleEventType::Condition2 => Some(1680),
+            // The bonfire's flames, for a weapon that flares
+            BattleEventType::Proc => Some(1640),
         }
     }
 }
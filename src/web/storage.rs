@@ -0,0 +1,63 @@
+//! Keeps save slots in the browser's local storage
+use crate::save_store::SaveStore;
+use base64::{engine::general_purpose, Engine as _};
+use std::error::Error;
+use web_sys::Storage;
+
+/// Keeps records apart from the rest of local storage, e.g. the js's own "aldon-games"
+const PREFIX: &str = "aldon-save:";
+
+pub(crate) struct LocalStorageSaveStore {
+    storage: Option<Storage>,
+}
+
+impl LocalStorageSaveStore {
+    pub fn new() -> Self {
+        let storage = web_sys::window().and_then(|window| window.local_storage().ok().flatten());
+        Self { storage }
+    }
+}
+
+impl SaveStore for LocalStorageSaveStore {
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        let text = self
+            .storage
+            .as_ref()?
+            .get_item(&format!("{}{}", PREFIX, key))
+            .ok()??;
+        general_purpose::STANDARD.decode(text).ok()
+    }
+
+    fn write(&self, key: &str, record: &[u8]) -> Result<(), Box<dyn Error>> {
+        let storage = self.storage.as_ref().ok_or("no local storage")?;
+        storage
+            .set_item(
+                &format!("{}{}", PREFIX, key),
+                &general_purpose::STANDARD.encode(record),
+            )
+            // Usually the storage quota
+            .map_err(|err| format!("couldn't write {}: {:?}", key, err))?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) {
+        if let Some(storage) = &self.storage {
+            let _ = storage.remove_item(&format!("{}{}", PREFIX, key));
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let Some(storage) = &self.storage else {
+            return Vec::new();
+        };
+        let len = storage.length().unwrap_or(0);
+        (0..len)
+            .filter_map(|i| storage.key(i).ok()?)
+            .filter_map(|key| Some(key.strip_prefix(PREFIX)?.to_string()))
+            .collect()
+    }
+
+    fn now(&self) -> f64 {
+        js_sys::Date::now()
+    }
+}
@@ -0,0 +1,98 @@
+//! Pausing and stepping actors with the debugger
+mod common;
+
+use aldonlib::{game::AldonGame, thrift::save::RaceType, ActorInfo};
+use common::{Call, MockDialog};
+
+const NARRATOR: u16 = 1;
+
+fn narrator(game: &AldonGame) -> ActorInfo {
+    game.debug_actors()
+        .into_iter()
+        .find(|actor| actor.id == NARRATOR)
+        .unwrap()
+}
+
+fn messages_told(dialog: &MockDialog) -> usize {
+    dialog
+        .calls()
+        .iter()
+        .filter(|call| matches!(call, Call::TellMessage { .. }))
+        .count()
+}
+
+/// Runs a few ticks starting at `tick`, sending any responses
+fn run(game: &mut AldonGame, dialog: &MockDialog, tick: &mut u32) {
+    for _ in 0..5 {
+        *tick += 1;
+        game.update(*tick as f64 * 50.0);
+        dialog.send_responses(game);
+    }
+}
+
+#[test]
+fn lists_actors() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let mut tick = 0;
+    run(&mut game, &dialog, &mut tick);
+
+    // The narrator welcomes the player and waits for them to answer
+    let narrator = narrator(&game);
+    assert_eq!(narrator.name, "Narrator");
+    assert_eq!(narrator.state, 10);
+    assert_eq!(narrator.waiting_on.as_deref(), Some("response"));
+    assert!(!narrator.paused);
+    assert_eq!(game.debug_vars().len(), 255);
+}
+
+#[test]
+fn breakpoint_and_step() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    game.set_breakpoint(NARRATOR, 20);
+    assert_eq!(game.breakpoints(), [(NARRATOR, 20)]);
+    dialog.respond_with(0);
+    let mut tick = 0;
+    run(&mut game, &dialog, &mut tick);
+
+    // Paused before 020=SETPORTRAIT 1 703
+    let paused = narrator(&game);
+    assert!(paused.paused);
+    assert_eq!(paused.state, 20);
+    assert_eq!(paused.waiting_on.as_deref(), Some("debugger"));
+    assert_eq!(messages_told(&dialog), 1);
+
+    // SETPORTRAIT runs, then the actor pauses before TELLMESSAGE
+    game.step_actor(NARRATOR);
+    run(&mut game, &dialog, &mut tick);
+    assert!(narrator(&game).paused);
+    assert_eq!(messages_told(&dialog), 1);
+
+    game.step_actor(NARRATOR);
+    run(&mut game, &dialog, &mut tick);
+    assert_eq!(messages_told(&dialog), 2);
+
+    game.continue_actor(NARRATOR);
+    game.clear_breakpoint(NARRATOR, 20);
+    run(&mut game, &dialog, &mut tick);
+    let narrator = narrator(&game);
+    assert!(!narrator.paused);
+    assert_eq!(narrator.waiting_on.as_deref(), Some("response"));
+    assert!(game.breakpoints().is_empty());
+}
+
+#[test]
+fn step_pauses_a_running_actor() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    game.step_actor(NARRATOR);
+    let mut tick = 0;
+    run(&mut game, &dialog, &mut tick);
+
+    // Stopped before 010=FREEZE 0, so the player hasn't been welcomed
+    let narrator = narrator(&game);
+    assert!(narrator.paused);
+    assert_eq!(narrator.state, 10);
+    assert_eq!(messages_told(&dialog), 0);
+}
@@ -0,0 +1,99 @@
+//! Simulated fights between a player build and a creature
+use aldonlib::{
+    balance::{self, Build},
+    thrift::save::ClassType,
+};
+
+const LONG_SWORD: u16 = 1;
+const CHAIN_TUNIC: u16 = 25;
+const KITE_SHIELD: u16 = 23;
+const SPIDER_FANG: u16 = 198;
+const RAT: u16 = 30;
+const GOBLIN: u16 = 29;
+const SPIDER: u16 = 200;
+
+fn fighter(level: i32) -> Build {
+    Build {
+        class: ClassType::FIGHTER,
+        level,
+        weapon: Some(LONG_SWORD),
+        armor: vec![CHAIN_TUNIC, KITE_SHIELD],
+        ..Build::default()
+    }
+}
+
+#[test]
+fn same_seed_same_fights() {
+    let first = balance::simulate(&fighter(1), GOBLIN, 1, 50, 7).unwrap();
+    let second = balance::simulate(&fighter(1), GOBLIN, 1, 50, 7).unwrap();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn every_fight_and_attack_is_counted() {
+    let report = balance::simulate(&fighter(1), GOBLIN, 1, 200, 1).unwrap();
+    assert_eq!(report.fights, 200);
+    assert_eq!(report.wins + report.losses + report.draws, 200);
+    for side in [&report.player, &report.creature] {
+        assert!(side.attacks > 0);
+        assert_eq!(
+            side.hits + side.crits + side.misses + side.fizzles,
+            side.attacks
+        );
+        let percents =
+            side.hit_percent() + side.crit_percent() + side.miss_percent() + side.fizzle_percent();
+        assert!((percents - 100.0).abs() < 0.001);
+    }
+    assert!(report.player.crits > 0);
+    assert!(report.player_dps() > 0.0);
+    assert!(report.creature_dps() > 0.0);
+    assert!(report.time_to_kill().unwrap() > 0.0);
+}
+
+#[test]
+fn levels_win_fights() {
+    let weak = balance::simulate(&fighter(1), GOBLIN, 3, 200, 1).unwrap();
+    let strong = balance::simulate(&fighter(8), RAT, 1, 200, 1).unwrap();
+    assert!(weak.win_percent() < strong.win_percent());
+    assert_eq!(strong.wins, 200);
+}
+
+#[test]
+fn unarmed_players_never_win() {
+    let build = Build {
+        weapon: None,
+        ..fighter(1)
+    };
+    let report = balance::simulate(&build, RAT, 1, 20, 1).unwrap();
+    assert_eq!(report.wins, 0);
+    assert_eq!(report.player.attacks, 0);
+}
+
+#[test]
+fn resistances_count() {
+    // Spiders shrug off poison, including the poison their own fangs leave
+    let build = Build {
+        weapon: Some(SPIDER_FANG),
+        ..fighter(10)
+    };
+    let report = balance::simulate(&build, SPIDER, 1, 20, 1).unwrap();
+    assert!(report.player.hits > 0);
+    assert_eq!(report.player.damage, 0);
+    assert_eq!(report.wins, 0);
+}
+
+#[test]
+fn bad_builds_are_rejected() {
+    let not_a_weapon = Build {
+        weapon: Some(CHAIN_TUNIC),
+        ..fighter(1)
+    };
+    assert!(balance::simulate(&not_a_weapon, RAT, 1, 1, 1).is_err());
+    let not_armor = Build {
+        armor: vec![LONG_SWORD],
+        ..fighter(1)
+    };
+    assert!(balance::simulate(&not_armor, RAT, 1, 1, 1).is_err());
+    assert!(balance::simulate(&fighter(1), LONG_SWORD, 1, 1, 1).is_err());
+    assert!(balance::simulate(&fighter(1), RAT, 0, 1, 1).is_err());
+}
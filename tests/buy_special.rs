@@ -0,0 +1,186 @@
+//! Buying the shop items that do something other than adding to the inventory
+mod common;
+
+use aldonlib::{
+    stats,
+    thrift::save::{ClassType, RaceType},
+};
+use common::MockDialog;
+
+const TRAIN: u16 = 13;
+const FIGHTERS_GUILD: u16 = 14;
+const THIEVES_GUILD: u16 = 15;
+const PRIESTHOOD: u16 = 16;
+const MAGES_GUILD: u16 = 17;
+const QUIT_GUILD: u16 = 18;
+const DOG: u16 = 61;
+const CAT: u16 = 63;
+const INN: u16 = 90;
+
+#[test]
+fn join_fighters_guild() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+
+    assert!(common::buy(&mut game, &dialog, FIGHTERS_GUILD));
+    assert_eq!(player.class.get(), ClassType::FIGHTER);
+    assert_eq!(player.gold.get(), 95);
+
+    // Already a member, so it's not sold twice
+    assert!(!common::buy(&mut game, &dialog, FIGHTERS_GUILD));
+    assert_eq!(player.gold.get(), 95);
+}
+
+#[test]
+fn join_thieves_guild() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+
+    assert!(common::buy(&mut game, &dialog, THIEVES_GUILD));
+    assert_eq!(player.class.get(), ClassType::THIEF);
+
+    // Only journeymen can join a guild
+    assert!(!common::buy(&mut game, &dialog, MAGES_GUILD));
+    assert_eq!(player.class.get(), ClassType::THIEF);
+}
+
+#[test]
+fn join_priesthood() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::DWARF);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+
+    assert!(common::buy(&mut game, &dialog, PRIESTHOOD));
+    assert_eq!(player.class.get(), ClassType::PRIEST);
+}
+
+#[test]
+fn elves_cant_join_priesthood() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::ELF);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+
+    assert!(!common::buy(&mut game, &dialog, PRIESTHOOD));
+    assert_eq!(player.class.get(), ClassType::JOURNEYMAN);
+    assert_eq!(player.gold.get(), 100);
+}
+
+#[test]
+fn join_mages_guild() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::ELF);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+
+    assert!(common::buy(&mut game, &dialog, MAGES_GUILD));
+    assert_eq!(player.class.get(), ClassType::SPELLCASTER);
+}
+
+#[test]
+fn dwarves_cant_join_mages_guild() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::DWARF);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+
+    assert!(!common::buy(&mut game, &dialog, MAGES_GUILD));
+    assert_eq!(player.class.get(), ClassType::JOURNEYMAN);
+}
+
+#[test]
+fn quit_guild() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+    assert!(common::buy(&mut game, &dialog, FIGHTERS_GUILD));
+    player.set_level(3);
+
+    assert!(common::buy(&mut game, &dialog, QUIT_GUILD));
+    assert_eq!(player.class.get(), ClassType::JOURNEYMAN);
+    assert_eq!(player.level(), 1);
+
+    // Free to join another guild now
+    assert!(common::buy(&mut game, &dialog, THIEVES_GUILD));
+    assert_eq!(player.class.get(), ClassType::THIEF);
+}
+
+#[test]
+fn train_when_ready() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(1000);
+
+    // Not enough experience yet
+    assert_eq!(stats::max_level(player.exp()), 1);
+    assert!(!common::buy(&mut game, &dialog, TRAIN));
+    assert_eq!(player.level(), 1);
+    assert_eq!(player.gold.get(), 1000);
+
+    player.give_exp(3000);
+    assert_eq!(stats::max_level(player.exp()), 3);
+
+    assert!(common::buy(&mut game, &dialog, TRAIN));
+    assert_eq!(player.level(), 2);
+    assert_eq!(dialog.last_stats().unwrap().level, 2);
+
+    // Training goes one level at a time
+    assert!(common::buy(&mut game, &dialog, TRAIN));
+    assert_eq!(player.level(), 3);
+    assert!(!common::buy(&mut game, &dialog, TRAIN));
+    assert_eq!(player.level(), 3);
+    assert_eq!(player.gold.get(), 800);
+}
+
+#[test]
+fn buy_pet() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(1000);
+
+    assert!(common::buy(&mut game, &dialog, DOG));
+    let pet = player.pet().unwrap();
+    assert_eq!(pet.prop_id, DOG);
+    assert!(player.henchmen().iter().any(|body| body.prop_id == DOG));
+
+    // Only one pet at a time
+    let gold = player.gold.get();
+    assert!(!common::buy(&mut game, &dialog, CAT));
+    assert_eq!(player.pet().unwrap().prop_id, DOG);
+    assert_eq!(player.gold.get(), gold);
+}
+
+#[test]
+fn rest_at_inn() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(1000);
+    assert!(common::buy(&mut game, &dialog, DOG));
+    let pet = player.pet().unwrap();
+
+    player.set_health(1);
+    pet.set_health(1);
+    assert!(common::buy(&mut game, &dialog, INN));
+    assert_eq!(player.get_health(), player.max_health());
+    assert_eq!(pet.get_health(), pet.max_health());
+}
+
+#[test]
+fn cant_afford() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.take_gold(player.gold.get());
+
+    assert!(!common::buy(&mut game, &dialog, FIGHTERS_GUILD));
+    assert_eq!(player.class.get(), ClassType::JOURNEYMAN);
+}
@@ -0,0 +1,48 @@
+//! Disassembling and assembling actor byte code
+use aldonlib::{bytecode, data::WORLD};
+
+#[test]
+fn every_actor_round_trips() {
+    for (map_id, map) in WORLD.maps.iter() {
+        for actor in map.actors.iter() {
+            let Some(actions) = &actor.actions else {
+                continue;
+            };
+            let text = bytecode::disassemble(actions)
+                .unwrap_or_else(|err| panic!("map {} actor {}: {}", map_id, actor.id, err));
+            let assembled = bytecode::assemble(&text).unwrap();
+            assert_eq!(&assembled, actions, "map {} actor {}", map_id, actor.id);
+        }
+    }
+}
+
+#[test]
+fn assemble_text() {
+    let text = "
+        // a chest
+        000=CREATESELF 64
+        001=setmessage 67 // lower case names are fine
+        040=GIVEEXP 0 50
+    ";
+    let actions = bytecode::decode(&bytecode::assemble(text).unwrap()).unwrap();
+    assert_eq!(actions.len(), 3);
+    assert_eq!(actions[1].state, 1);
+    assert_eq!(actions[1].name(), "SETMESSAGE");
+    assert_eq!(actions[1].params, vec![67]);
+    assert_eq!(actions[2].params, vec![0, 50]);
+}
+
+#[test]
+fn assemble_errors() {
+    let err = |text: &str| bytecode::assemble(text).unwrap_err().to_string();
+    assert_eq!(
+        err("000=CREATESELF 64\n000=JUMP 1"),
+        "line 2: unknown action 'JUMP'"
+    );
+    assert_eq!(
+        err("000=GIVEEXP 50"),
+        "line 1: GIVEEXP takes 2 parameters, got 1"
+    );
+    assert_eq!(err("000=REMOVE -1"), "line 1: bad parameter '-1'");
+    assert_eq!(err("CREATESELF 64"), "line 1: expected <state>=<ACTION>");
+}
@@ -0,0 +1,148 @@
+//! The game clock: pausing, speed and long frames
+mod common;
+
+use aldonlib::{
+    clock::{MAX_SPEED, MIN_SPEED},
+    events::GameEvent,
+    game::AldonGame,
+    replay,
+    thrift::save::{ConditionType, RaceType},
+};
+use common::MockDialog;
+
+const QUICKSILVER: u16 = 315;
+
+/// A new game with the player walking across a map
+fn walking_game() -> AldonGame {
+    let mut game = common::new_game(MockDialog::new(), RaceType::HUMAN);
+    game.load_map(2, 10.0, 10.0);
+    game.input_stage(14.5, 10.5);
+    game
+}
+
+fn dex_wore_off(game: &AldonGame) -> bool {
+    game.drain_events().iter().any(|event| {
+        matches!(
+            event,
+            GameEvent::ConditionWornOff {
+                condition: ConditionType::DEXTERITY,
+                ..
+            }
+        )
+    })
+}
+
+#[test]
+fn pause_and_resume() {
+    let mut game = walking_game();
+    game.update(100.0);
+    let position = game.player_position();
+
+    game.pause();
+    assert!(game.paused());
+    game.update(1000.0);
+    assert_eq!(game.now(), 100.0);
+    assert_eq!(game.last_update(), 1000.0);
+    assert_eq!(game.player_position(), position);
+
+    // Picks up where it left off rather than jumping ahead
+    game.resume();
+    game.update(1050.0);
+    assert_eq!(game.now(), 150.0);
+    game.update(3000.0);
+    assert_ne!(game.player_position(), position);
+}
+
+#[test]
+fn speed() {
+    let mut game = walking_game();
+    game.set_speed(2.0);
+    game.update(100.0);
+    assert_eq!(game.now(), 200.0);
+    game.set_speed(0.5);
+    game.update(200.0);
+    assert_eq!(game.now(), 250.0);
+
+    game.set_speed(10.0);
+    assert_eq!(game.speed(), MAX_SPEED);
+    game.set_speed(0.0);
+    assert_eq!(game.speed(), MIN_SPEED);
+}
+
+#[test]
+fn conditions_run_on_game_time() {
+    let mut game = common::new_game(MockDialog::new(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    game.use_item(&player, QUICKSILVER);
+
+    // Potions last 90 seconds, 45 at double speed
+    game.set_speed(2.0);
+    let mut wall = 0.0;
+    while wall < 44000.0 {
+        wall += 50.0;
+        game.update(wall);
+    }
+    assert!(!dex_wore_off(&game));
+    while wall < 46000.0 {
+        wall += 50.0;
+        game.update(wall);
+    }
+    assert!(dex_wore_off(&game));
+}
+
+#[test]
+fn long_frames_are_stepped() {
+    let mut stepped = walking_game();
+    let mut now = 0.0;
+    while now < 2000.0 {
+        now += 50.0;
+        stepped.update(now);
+    }
+    let mut jumped = walking_game();
+    jumped.update(2000.0);
+    assert_eq!(jumped.now(), 2000.0);
+    assert_ne!(jumped.player_position(), Some((10.0, 10.0)));
+    assert_eq!(jumped.player_position(), stepped.player_position());
+}
+
+#[test]
+fn backgrounded_tab_skips_time() {
+    let mut game = common::new_game(MockDialog::new(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    game.use_item(&player, QUICKSILVER);
+
+    // An hour in the background is only a few seconds of game time
+    game.update(3600000.0);
+    assert!(game.now() <= 5000.0);
+    assert!(!dex_wore_off(&game));
+    game.update(3600050.0);
+    assert_eq!(game.now(), 5050.0);
+}
+
+#[test]
+fn pauses_replay() {
+    let mut game = AldonGame::with_seed(MockDialog::new(), 5);
+    game.start_recording().unwrap();
+    game.new_game(
+        "Test".to_string(),
+        RaceType::HUMAN,
+        600,
+        10,
+        10,
+        10,
+        10,
+        10,
+        10,
+    );
+    game.update(0.0);
+    game.load_map(2, 10.0, 10.0);
+    game.input_stage(14.5, 10.5);
+    game.update(500.0);
+    game.pause();
+    game.update(2000.0);
+    game.resume();
+    game.set_speed(4.0);
+    game.update(2200.0);
+    let recorded = game.stop_recording().unwrap();
+    assert!(replay::play(MockDialog::new(), &recorded).is_ok());
+}
@@ -0,0 +1,183 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+use aldonlib::{
+    body::Body,
+    buttons::Button,
+    data::World,
+    game::{AldonGame, Dialog, TransactionType},
+    stats::PlayerStats,
+    thrift::save::RaceType,
+};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+/// A call made to the dialog by the game
+#[derive(Clone)]
+pub enum Call {
+    TellMessage {
+        title: String,
+        portrait_id: u16,
+        msg_id: u16,
+        from_actor: u16,
+    },
+    ExecuteTrade {
+        kind: TransactionType,
+        body: String,
+        items: Vec<u16>,
+    },
+    Pickup {
+        body: String,
+        items: Vec<u16>,
+    },
+    BuySell {
+        kind: TransactionType,
+        body: String,
+        items: Vec<u16>,
+    },
+    PickButton {
+        button_idx: usize,
+    },
+    Stats(PlayerStats),
+    Spellbook(Vec<u16>),
+}
+
+/// A dialog that records every call the game makes to it. Messages can be answered with
+/// scripted responses, which are sent back to the game by `send_responses`.
+pub struct MockDialog {
+    calls: RefCell<Vec<Call>>,
+    transaction: RefCell<Vec<Rc<Body>>>,
+    responses: RefCell<VecDeque<u8>>,
+    pending: RefCell<Vec<(u16, u8)>>,
+}
+
+impl MockDialog {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self {
+            calls: RefCell::new(Vec::new()),
+            transaction: RefCell::new(Vec::new()),
+            responses: RefCell::new(VecDeque::new()),
+            pending: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.borrow().clone()
+    }
+
+    /// The stats shown by the last call to `stats`
+    pub fn last_stats(&self) -> Option<PlayerStats> {
+        self.calls
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|call| match call {
+                Call::Stats(stats) => Some(stats.clone()),
+                _ => None,
+            })
+    }
+
+    /// Replace the transaction, like a shop dialog opening with these items
+    pub fn set_transaction(&self, items: Vec<Rc<Body>>) {
+        *self.transaction.borrow_mut() = items;
+    }
+
+    /// Answer the next message told with `response`, 0 for choice A, 1 for B and 2 for C
+    pub fn respond_with(&self, response: u8) {
+        self.responses.borrow_mut().push_back(response);
+    }
+
+    /// Send the responses to messages told so far to the game
+    pub fn send_responses(&self, game: &mut AldonGame) {
+        let pending: Vec<(u16, u8)> = self.pending.borrow_mut().drain(..).collect();
+        for (actor_id, response) in pending {
+            game.send_response(actor_id, response);
+        }
+    }
+
+    fn record(&self, call: Call) {
+        self.calls.borrow_mut().push(call);
+    }
+}
+
+impl Dialog for MockDialog {
+    fn tell_message(&self, title: &str, portrait_id: u16, msg_id: u16, from_actor: u16) {
+        self.record(Call::TellMessage {
+            title: title.to_string(),
+            portrait_id,
+            msg_id,
+            from_actor,
+        });
+        if let Some(response) = self.responses.borrow_mut().pop_front() {
+            self.pending.borrow_mut().push((from_actor, response));
+        }
+    }
+
+    fn execute_trade(&self, kind: TransactionType, body: Rc<Body>, items: Vec<Rc<Body>>) {
+        self.record(Call::ExecuteTrade {
+            kind,
+            body: body.name.clone(),
+            items: prop_ids(&items),
+        });
+        self.set_transaction(items);
+    }
+
+    fn pickup(&self, body: Rc<Body>, items: Vec<Rc<Body>>) {
+        self.record(Call::Pickup {
+            body: body.name.clone(),
+            items: prop_ids(&items),
+        });
+        self.set_transaction(items);
+    }
+
+    fn buy_sell(&self, body: Rc<Body>, items: Vec<Rc<Body>>, kind: TransactionType) {
+        self.record(Call::BuySell {
+            kind,
+            body: body.name.clone(),
+            items: prop_ids(&items),
+        });
+        self.set_transaction(items);
+    }
+
+    fn pick_button(&self, button_idx: usize, _buttons: Vec<Button>) {
+        self.record(Call::PickButton { button_idx });
+    }
+
+    fn stats(&self, stats: &PlayerStats) {
+        self.record(Call::Stats(stats.clone()));
+    }
+
+    fn spellbook(&self, spells: &[u16]) {
+        self.record(Call::Spellbook(spells.to_vec()));
+    }
+
+    fn get_transaction(&self) -> Vec<Rc<Body>> {
+        self.transaction.borrow().clone()
+    }
+
+    fn remove_item(&self, index: usize) -> Rc<Body> {
+        self.transaction.borrow_mut().remove(index)
+    }
+}
+
+fn prop_ids(items: &[Rc<Body>]) -> Vec<u16> {
+    items.iter().map(|item| item.prop_id).collect()
+}
+
+/// A new level 1 character with the same seed every time
+pub fn new_game(dialog: Rc<MockDialog>, race: RaceType) -> AldonGame {
+    new_game_in(dialog, race, World::default())
+}
+
+/// Starts a new game in `world`
+pub fn new_game_in(dialog: Rc<MockDialog>, race: RaceType, world: World) -> AldonGame {
+    let mut game = AldonGame::with_world(dialog, 1, world);
+    game.new_game("Test".to_string(), race, 600, 10, 10, 10, 10, 10, 10);
+    game.update(0.0);
+    game
+}
+
+/// Buys `prop_id` for the player as if it were the only item in a shop
+pub fn buy(game: &mut AldonGame, dialog: &MockDialog, prop_id: u16) -> bool {
+    let item = Rc::new(Body::new("item".to_string(), None, prop_id, 0.0, 0.0));
+    dialog.set_transaction(vec![item]);
+    game.buy(game.player().unwrap(), 0)
+}
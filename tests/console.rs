@@ -0,0 +1,87 @@
+//! Each game's console
+mod common;
+
+use aldonlib::{console::DEFAULT_CAPACITY, game::AldonGame, thrift::save::RaceType};
+use common::MockDialog;
+
+/// A new character fighting bandits that are too strong for them
+fn fighting_game() -> AldonGame {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    game.load_map(19, 6.0, 5.0);
+    let mut now = 0.0;
+    while now < 60000.0 && !game.game_over() {
+        now += 50.0;
+        game.update(now);
+    }
+    game
+}
+
+#[test]
+fn games_have_their_own_console() {
+    let fighting = fighting_game();
+    let quiet = common::new_game(MockDialog::new(), RaceType::ELF);
+
+    let lines = fighting.console().lines();
+    assert!(lines.iter().any(|line| line.contains("bandit")));
+    assert_eq!(lines.last().unwrap(), "*Game Over! press menu to continue*");
+    assert!(quiet.console().lines().is_empty());
+    assert!(!quiet.console().debug_logs().contains("bandit"));
+}
+
+#[test]
+fn console_keeps_scrollback() {
+    let game = fighting_game();
+    let console = game.console();
+    assert_eq!(console.capacity(), DEFAULT_CAPACITY);
+    let fight = console.lines();
+    assert_eq!(fight.len(), console.logged());
+    for i in 0..DEFAULT_CAPACITY {
+        console.log(&format!("-line {}-", i));
+    }
+    // The fight scrolled off
+    let lines = console.lines();
+    assert_eq!(lines.len(), DEFAULT_CAPACITY);
+    assert_eq!(lines[0], "-line 0-");
+    assert_eq!(console.logged(), fight.len() + DEFAULT_CAPACITY);
+
+    console.set_capacity(3);
+    assert_eq!(console.lines(), lines[lines.len() - 3..]);
+    console.log("-one more-");
+    assert_eq!(console.lines().len(), 3);
+    assert_eq!(console.lines()[2], "-one more-");
+}
+
+#[test]
+fn debug_logs_are_per_map() {
+    let mut game = fighting_game();
+    let logs = game.console().debug_logs();
+    // Console lines are debug logs too, along with everything else
+    assert!(logs.contains("*Game Over! press menu to continue*"));
+    assert!(logs.contains("loaded map: 19"));
+
+    game.load_map(2, 7.0, 19.0);
+    let logs = game.console().debug_logs();
+    assert!(!logs.contains("Game Over"));
+    assert!(logs.contains("loaded map: 2"));
+}
+
+#[test]
+fn console_carries_over_to_new_games() {
+    let mut game = fighting_game();
+    game.console().set_capacity(20);
+    let logged = game.console().logged();
+    game.new_game(
+        "Tess".to_string(),
+        RaceType::DWARF,
+        600,
+        10,
+        10,
+        10,
+        10,
+        10,
+        10,
+    );
+    assert_eq!(game.console().capacity(), 20);
+    assert_eq!(game.console().logged(), logged);
+}
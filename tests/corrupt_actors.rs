@@ -0,0 +1,149 @@
+//! Actors with broken byte code are stopped and logged without taking the game down
+mod common;
+
+use aldonlib::{
+    bytecode::{self, Action},
+    data::{ActorRes, World, WorldRes},
+    game::AldonGame,
+    thrift::save::RaceType,
+};
+use common::MockDialog;
+
+const NARRATOR: u16 = 1;
+const UNKNOWN_ACTION: u16 = 900;
+const TRUNCATED: u16 = 901;
+const BAD_CONDITION: u16 = 902;
+const VAR_OUT_OF_RANGE: u16 = 903;
+
+const SETVAR: u16 = 0x0e06;
+const IF: u16 = 0x060e;
+
+/// Sets `var` to `val`, packed the way the actors pack them
+fn set_var(state: u16, var: u16, val: u16) -> Action {
+    Action {
+        state,
+        id: SETVAR,
+        params: vec![var << 8 | val],
+    }
+}
+
+/// An actor that marks its var with 1, breaks in state 5, and would mark it 2 if it went on
+fn actor(id: u16, broken: Action, goes_on: bool) -> ActorRes {
+    let var = id - UNKNOWN_ACTION + 100;
+    let mut actions = vec![set_var(0, var, 1), broken];
+    if goes_on {
+        actions.push(set_var(10, var, 2));
+    }
+    ActorRes {
+        id,
+        x: 3.0,
+        y: 3.0,
+        health: 0.0,
+        name: format!("Broken {}", id),
+        bmp_offset: 0,
+        is_mapped: false,
+        actions: Some(bytecode::encode(&actions)),
+    }
+}
+
+fn corrupt_world() -> World {
+    let mut res: WorldRes = serde_json::from_str(include_str!("../src/data/maps.json")).unwrap();
+    let map = res.maps.get_mut("1").unwrap();
+    map.actors.extend([
+        actor(
+            UNKNOWN_ACTION,
+            Action {
+                state: 5,
+                id: 0x7777,
+                params: vec![],
+            },
+            false,
+        ),
+        // SETVAR without its parameter at the end of the byte code
+        actor(
+            TRUNCATED,
+            Action {
+                state: 5,
+                id: SETVAR,
+                params: vec![],
+            },
+            false,
+        ),
+        // Condition type 10 doesn't exist
+        actor(
+            BAD_CONDITION,
+            Action {
+                state: 5,
+                id: IF,
+                params: vec![0, 0, 0, 10 << 8, 10],
+            },
+            true,
+        ),
+        // Var 255 is one past the last
+        actor(VAR_OUT_OF_RANGE, set_var(5, 255, 1), true),
+    ]);
+    World {
+        id: "corrupt",
+        res: Box::leak(Box::new(res)),
+    }
+}
+
+fn errors_logged(game: &AldonGame, actor_id: u16) -> Vec<String> {
+    let needle = format!("Actor error in actor {} state", actor_id);
+    game.console()
+        .debug_logs()
+        .lines()
+        .filter(|line| line.contains(&needle))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[test]
+fn corrupt_actors_are_stopped_and_logged() {
+    let mut game = common::new_game_in(MockDialog::new(), RaceType::HUMAN, corrupt_world());
+    for tick in 1..=40 {
+        game.update(tick as f64 * 50.0);
+    }
+
+    let running: Vec<u16> = game.debug_actors().iter().map(|actor| actor.id).collect();
+    let vars = game.debug_vars();
+    for (i, actor_id) in [UNKNOWN_ACTION, TRUNCATED, BAD_CONDITION, VAR_OUT_OF_RANGE]
+        .into_iter()
+        .enumerate()
+    {
+        assert!(!running.contains(&actor_id), "{}", actor_id);
+        // Everything before the broken action ran and nothing after it
+        assert_eq!(vars[100 + i], 1, "{}", actor_id);
+        // Killed on the first error rather than failing every update
+        let errors = errors_logged(&game, actor_id);
+        assert_eq!(errors.len(), 1, "{}", actor_id);
+        assert!(errors[0].contains(&format!("actor {} state 5:", actor_id)));
+    }
+    let errors = [
+        errors_logged(&game, UNKNOWN_ACTION),
+        errors_logged(&game, TRUNCATED),
+        errors_logged(&game, BAD_CONDITION),
+        errors_logged(&game, VAR_OUT_OF_RANGE),
+    ];
+    assert!(errors[0][0].ends_with("Unknown action 0x7777"));
+    assert!(errors[1][0].ends_with("Stack underflow"));
+    assert!(errors[2][0].ends_with("Bad condition: unknown type 10"));
+    assert!(errors[3][0].ends_with("Var 255 out of range"));
+
+    // The rest of the map carries on
+    let narrator = game
+        .debug_actors()
+        .into_iter()
+        .find(|actor| actor.id == NARRATOR)
+        .unwrap();
+    assert_eq!(narrator.state, 10);
+    game.send_response(NARRATOR, 0);
+    for tick in 41..=80 {
+        game.update(tick as f64 * 50.0);
+    }
+    let narrator = game
+        .debug_actors()
+        .into_iter()
+        .find(|actor| actor.id == NARRATOR);
+    assert!(narrator.is_none_or(|narrator| narrator.state > 10));
+}
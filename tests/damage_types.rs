@@ -0,0 +1,154 @@
+//! Damage types and the resistances creatures and items have to them
+mod common;
+
+use aldonlib::{
+    body::Body,
+    data::{DamageType, World, WorldRes, SPELLS},
+    events::{BattleEventType, GameEvent},
+    thrift::save::RaceType,
+};
+use common::MockDialog;
+use std::rc::Rc;
+
+const DOG: u16 = 61;
+const FIRE_FIST: u16 = 233;
+const OILSKIN_CLOAK: u16 = 398;
+const LESSER_FIRE_ELEMENTAL: u16 = 236;
+const LESSER_ICE_ELEMENTAL: u16 = 228;
+const BLOOD_BLADE: u16 = 248;
+const DEMON: u16 = 361;
+
+fn creature(prop_id: u16) -> Body {
+    Body::new("creature".to_string(), None, prop_id, 0.0, 0.0)
+}
+
+#[test]
+fn elementals_resist_their_element() {
+    let fire = creature(LESSER_FIRE_ELEMENTAL);
+    assert_eq!(fire.resistance(DamageType::Fire), 100);
+    assert_eq!(fire.resistance(DamageType::Ice), -50);
+    assert_eq!(fire.resistance(DamageType::Physical), 0);
+
+    let ice = creature(LESSER_ICE_ELEMENTAL);
+    assert_eq!(ice.resistance(DamageType::Ice), 100);
+    assert_eq!(ice.resistance(DamageType::Fire), -50);
+}
+
+#[test]
+fn spells_have_damage_types() {
+    let damage_type = |spell_id: u16| SPELLS[&spell_id.to_string()].damage_type;
+    assert_eq!(damage_type(0), DamageType::Fire); // Fire Bolt
+    assert_eq!(damage_type(3), DamageType::Ice); // Ice Bolt
+    assert_eq!(damage_type(14), DamageType::Necrotic); // Life Drain
+    assert_eq!(damage_type(51), DamageType::Holy); // Holy Smite
+    assert_eq!(damage_type(38), DamageType::Physical); // Strength
+}
+
+#[test]
+fn equipment_grants_resistance() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog, RaceType::HUMAN);
+    let player = game.player().unwrap();
+    assert_eq!(player.resistance(DamageType::Ice), 0);
+
+    let cloak = player.give_item(OILSKIN_CLOAK);
+    assert!(player.equip(game.now(), cloak.clone()));
+    assert_eq!(player.resistance(DamageType::Ice), 25);
+
+    assert!(player.unequip(cloak));
+    game.update(1000.0);
+    assert_eq!(player.resistance(DamageType::Ice), 0);
+}
+
+#[test]
+fn weapons_deal_their_damage_type() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+    assert!(common::buy(&mut game, &dialog, DOG));
+    game.load_map(19, 6.0, 5.0);
+    let dog = player.pet().unwrap();
+    let fist = Rc::new(Body::new("fist".to_string(), None, FIRE_FIST, 0.0, 0.0));
+    dog.force_equip(game.now(), fist);
+
+    let mut now = 0.0;
+    let mut events = Vec::new();
+    while now < 120000.0 && events.len() < 10 {
+        now += 50.0;
+        game.update(now);
+        player.set_health_no_max(10000);
+        dog.set_health_no_max(10000);
+        events.extend(
+            game.drain_events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    GameEvent::Damage {
+                        attacker: Some(attacker),
+                        damage_type,
+                        ..
+                    } => Some((attacker.name, damage_type)),
+                    _ => None,
+                }),
+        );
+    }
+    assert!(events.contains(&(dog.name.clone(), DamageType::Fire)));
+    assert!(events.contains(&("bandit".to_string(), DamageType::Physical)));
+}
+
+#[test]
+fn resisted_hits_drain_nothing() {
+    // Demons in place of the bandits on map 19
+    let mut res: WorldRes = serde_json::from_str(include_str!("../src/data/maps.json")).unwrap();
+    let spawner = &mut res.maps.get_mut("19").unwrap().spawners[0];
+    spawner.creatures = vec![DEMON];
+    spawner.level = 1;
+    let world = World {
+        id: "demons",
+        res: Box::leak(Box::new(res)),
+    };
+
+    let dialog = MockDialog::new();
+    let mut game = common::new_game_in(dialog.clone(), RaceType::HUMAN, world);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+    assert!(common::buy(&mut game, &dialog, DOG));
+    game.load_map(19, 6.0, 5.0);
+    let dog = player.pet().unwrap();
+    let blade = Rc::new(Body::new("blade".to_string(), None, BLOOD_BLADE, 0.0, 0.0));
+    dog.force_equip(game.now(), blade);
+    // Demons are hard to hit
+    dog.set_level(20);
+
+    let mut now = 0.0;
+    let mut hits = 0;
+    while now < 120000.0 && hits < 10 {
+        now += 50.0;
+        game.update(now);
+        // Hurt, so a drain would show
+        player.set_health_no_max(10000);
+        dog.set_health(dog.max_health() / 2);
+        for event in game.drain_events() {
+            if let GameEvent::Damage {
+                attacker: Some(attacker),
+                kind: BattleEventType::Hit | BattleEventType::Crit,
+                amount,
+                damage_type,
+                ..
+            } = event
+            {
+                if attacker.name == dog.name {
+                    assert_eq!((amount, damage_type), (0, DamageType::Necrotic));
+                    hits += 1;
+                }
+            }
+        }
+    }
+    assert_eq!(hits, 10);
+    let gains = format!("*{} gains", dog.name);
+    assert!(!game
+        .console()
+        .lines()
+        .iter()
+        .any(|line| line.starts_with(&gains)));
+}
@@ -0,0 +1,43 @@
+//! Scripted responses to the dialogs actors open
+mod common;
+
+use aldonlib::thrift::save::RaceType;
+use common::{Call, MockDialog};
+
+/// Plays the start of a new game, answering the narrator's welcome with `response`. Returns
+/// the ids of the messages told.
+fn play_intro(response: u8) -> Vec<(u16, u16)> {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    dialog.respond_with(response);
+    for tick in 1..40 {
+        game.update(tick as f64 * 50.0);
+        dialog.send_responses(&mut game);
+    }
+    dialog
+        .calls()
+        .iter()
+        .filter_map(|call| match call {
+            Call::TellMessage {
+                msg_id, from_actor, ..
+            } => Some((*from_actor, *msg_id)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn begin_tutorial() {
+    let told = play_intro(0);
+    assert_eq!(told.len(), 2, "{:?}", told);
+    // The narrator welcomes the player then starts the tutorial
+    assert_eq!(told[0].0, 1);
+    assert_eq!(told[1].0, 1);
+}
+
+#[test]
+fn skip_tutorial() {
+    let told = play_intro(1);
+    assert_eq!(told.len(), 1, "{:?}", told);
+    assert_eq!(told[0].0, 1);
+}
@@ -0,0 +1,156 @@
+//! Item effects from the `effects` of props in props.json
+mod common;
+
+use aldonlib::{
+    data::{EffectRes, ProcAction, PropRes, PropTypeRes, StatRes, PROPS, SPELLS},
+    events::GameEvent,
+    thrift::save::{ConditionType, RaceType},
+};
+use common::MockDialog;
+
+const RING_OF_FORCE: u16 = 173;
+const MINOR_HEAL: u16 = 19;
+const IRON_ARM: u16 = 93;
+const MANA_RENEWAL: u16 = 405;
+const MAGES_GUILD: u16 = 17;
+
+#[test]
+fn ring_bonus_while_equipped() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog, RaceType::HUMAN);
+    let player = game.player().unwrap();
+    let strength = player.strength();
+
+    let ring = player.give_item(RING_OF_FORCE);
+    assert!(player.equip(game.now(), ring.clone()));
+    assert_eq!(player.strength(), strength + 1);
+
+    assert!(player.unequip(ring));
+    game.update(1000.0);
+    assert_eq!(player.strength(), strength);
+}
+
+#[test]
+fn heal_potion_is_used_up() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog, RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.set_health(1);
+    player.give_item(MINOR_HEAL);
+
+    game.use_item(&player, MINOR_HEAL);
+    assert_eq!(player.health.get(), 11.min(player.max_health()));
+    assert_eq!(player.item_quantity(MINOR_HEAL), 0);
+}
+
+#[test]
+fn potions_give_conditions() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+
+    // Mana renewal is for level 6 priests and mages
+    player.give_gold(100);
+    assert!(common::buy(&mut game, &dialog, MAGES_GUILD));
+    player.set_level(6);
+    player.give_item(IRON_ARM);
+    player.give_item(MANA_RENEWAL);
+    game.drain_events();
+
+    game.use_item(&player, IRON_ARM);
+    game.use_item(&player, MANA_RENEWAL);
+    let gained: Vec<ConditionType> = game
+        .drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            GameEvent::ConditionGained { condition, .. } => Some(condition),
+            _ => None,
+        })
+        .collect();
+    // Mana renewal is used up without doing anything
+    assert_eq!(gained, vec![ConditionType::STRENGTH]);
+    assert!(game
+        .console()
+        .lines()
+        .contains(&"*Test recieved positive Str.*".to_string()));
+    assert_eq!(player.item_quantity(MANA_RENEWAL), 0);
+}
+
+#[test]
+fn items_without_use_effects_are_kept() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog, RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_item(RING_OF_FORCE);
+
+    game.use_item(&player, RING_OF_FORCE);
+    assert_eq!(player.item_quantity(RING_OF_FORCE), 1);
+}
+
+#[test]
+fn effects_parse_from_json() {
+    let prop: PropRes = serde_json::from_str(
+        r#"{
+            "str_id": "brcr",
+            "name": "Silvery Bracers",
+            "blocker": false,
+            "sight_blocker": false,
+            "draw_depth": 2,
+            "effects": [
+                {"effect": "stat_bonus", "stat": "dexterity", "magnitude": 1},
+                {"effect": "regen", "stat": "mana"}
+            ],
+            "Item": {"frame": 1}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        prop.effects,
+        vec![
+            EffectRes::StatBonus {
+                stat: StatRes::Dexterity,
+                magnitude: 1
+            },
+            EffectRes::Regen {
+                stat: StatRes::Mana
+            },
+        ]
+    );
+}
+
+#[test]
+fn built_in_effects_fit_their_props() {
+    for (prop_id, prop) in PROPS.iter() {
+        for effect in &prop.effects {
+            match effect {
+                EffectRes::CastSpell { spell_id } => {
+                    assert!(matches!(prop.kind, PropTypeRes::Weapon { .. }), "{prop_id}");
+                    assert!(SPELLS.contains_key(&spell_id.to_string()), "{prop_id}");
+                }
+                EffectRes::Proc { action, .. } => {
+                    assert!(matches!(prop.kind, PropTypeRes::Weapon { .. }), "{prop_id}");
+                    if let ProcAction::CastSpell { spell_id } = action {
+                        assert!(SPELLS.contains_key(&spell_id.to_string()), "{prop_id}");
+                    }
+                }
+                EffectRes::Resistance { percent, .. } => {
+                    assert!(
+                        matches!(
+                            prop.kind,
+                            PropTypeRes::Creature { .. } | PropTypeRes::Armor { .. }
+                        ),
+                        "{prop_id}"
+                    );
+                    assert!((-100..=100).contains(percent), "{prop_id}");
+                }
+                EffectRes::GrantPet => {
+                    assert!(
+                        matches!(prop.kind, PropTypeRes::Creature { .. }),
+                        "{prop_id}"
+                    )
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! The events hosts drain from the game each frame
+mod common;
+
+use aldonlib::{
+    body::Body,
+    data::DamageType,
+    events::{BattleEventType, GameEvent},
+    game::AldonGame,
+    thrift::save::{ConditionType, RaceType},
+};
+use common::MockDialog;
+use std::rc::Rc;
+
+const TRAIN: u16 = 13;
+const QUICKSILVER: u16 = 315;
+const DOG: u16 = 61;
+
+/// Updates every 50ms up to `until`, answering dialogs as they open
+fn play(game: &mut AldonGame, dialog: &MockDialog, from: f64, until: f64) {
+    let mut now = from;
+    while now < until {
+        now += 50.0;
+        game.update(now);
+        dialog.send_responses(game);
+    }
+}
+
+#[test]
+fn map_changes_and_quest_log() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    assert_eq!(game.drain_events(), [GameEvent::MapChanged { map_id: 1 }]);
+    // Nothing twice
+    assert!(game.drain_events().is_empty());
+
+    // Briggins asks for help with his rats
+    game.load_map(2, 7.0, 19.0);
+    play(&mut game, &dialog, 0.0, 1000.0);
+    game.input_stage(6.5, 19.5);
+    for response in [1, 0, 0] {
+        dialog.respond_with(response);
+    }
+    play(&mut game, &dialog, 1000.0, 10000.0);
+    assert_eq!(
+        game.drain_events(),
+        [
+            GameEvent::MapChanged { map_id: 2 },
+            GameEvent::QuestLogAdded { msg_id: 113 },
+        ]
+    );
+}
+
+#[test]
+fn level_ups() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(1000);
+    player.give_exp(3000);
+    game.drain_events();
+
+    assert!(common::buy(&mut game, &dialog, TRAIN));
+    assert!(common::buy(&mut game, &dialog, TRAIN));
+    let levels: Vec<i32> = game
+        .drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            GameEvent::LevelUp { body, level } if body.is_player() => Some(level),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(levels, [2, 3]);
+}
+
+#[test]
+fn conditions_come_and_go() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    game.drain_events();
+
+    game.use_item(&player, QUICKSILVER);
+    assert_eq!(
+        game.drain_events(),
+        [GameEvent::ConditionGained {
+            body: player.as_ref().into(),
+            condition: ConditionType::DEXTERITY,
+        }]
+    );
+
+    // Potions last 90 seconds
+    play(&mut game, &dialog, 0.0, 91000.0);
+    assert!(game.drain_events().contains(&GameEvent::ConditionWornOff {
+        body: player.as_ref().into(),
+        condition: ConditionType::DEXTERITY,
+    }));
+}
+
+#[test]
+fn pickups() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    game.drain_events();
+
+    let item = Rc::new(Body::new("sling".to_string(), None, 20, 12.0, 4.0));
+    dialog.set_transaction(vec![item]);
+    assert!(game.pickup(&player, 0));
+    assert_eq!(
+        game.drain_events(),
+        [GameEvent::Pickup {
+            body: player.as_ref().into(),
+            prop_id: 20,
+            quantity: 1,
+        }]
+    );
+}
+
+#[test]
+fn damage_and_death() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    // Bandits far too strong for a new character
+    game.load_map(19, 6.0, 5.0);
+    play(&mut game, &dialog, 0.0, 60000.0);
+    assert!(game.game_over());
+
+    let events = game.drain_events();
+    let taken: i32 = events
+        .iter()
+        .filter_map(|event| match event {
+            GameEvent::Damage {
+                target,
+                attacker: Some(attacker),
+                kind: BattleEventType::Hit | BattleEventType::Crit,
+                amount,
+                damage_type: DamageType::Physical,
+            } if target.is_player() => {
+                assert_eq!(attacker.name, "bandit");
+                Some(*amount)
+            }
+            _ => None,
+        })
+        .sum();
+    assert!(taken >= game.player().unwrap().max_health());
+
+    let Some(GameEvent::Kill {
+        target,
+        killer,
+        exp,
+        gold,
+    }) = events.last()
+    else {
+        panic!("expected the player's death last: {:?}", events);
+    };
+    assert!(target.is_player());
+    assert_eq!(killer.name, "bandit");
+    assert_eq!((*exp, *gold), (0, 0));
+}
+
+#[test]
+fn events_as_json() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    game.drain_events();
+    game.use_item(&player, QUICKSILVER);
+
+    let json = serde_json::to_value(game.drain_events()).unwrap();
+    assert_eq!(json[0]["type"], "ConditionGained");
+    assert_eq!(json[0]["condition"], "Dex");
+    assert_eq!(json[0]["body"]["actor_id"], 0);
+}
+
+#[test]
+fn events_keep_their_order_across_bodies() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(1000);
+    assert!(common::buy(&mut game, &dialog, DOG));
+    let pet = player.pet().unwrap();
+    game.drain_events();
+
+    game.use_item(&pet, QUICKSILVER);
+    game.use_item(&player, QUICKSILVER);
+    // The dog's body is cleared away 10 seconds after it dies, its event stays
+    pet.set_health(0);
+    play(&mut game, &dialog, 0.0, 11000.0);
+    let gained: Vec<String> = game
+        .drain_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            GameEvent::ConditionGained { body, .. } => Some(body.name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(gained, [pet.name.clone(), player.name.clone()]);
+}
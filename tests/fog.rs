@@ -0,0 +1,117 @@
+//! Fog of war in saves
+mod common;
+
+use aldonlib::{
+    game::AldonGame,
+    save_file,
+    thrift::save::{self, RaceType},
+};
+use common::MockDialog;
+use std::collections::BTreeMap;
+use thrift::{
+    protocol::{TCompactInputProtocol, TCompactOutputProtocol, TOutputProtocol, TSerializable},
+    transport::{TBufferChannel, TBufferedReadTransport, TBufferedWriteTransport},
+};
+
+fn read(bytes: &[u8]) -> save::AldonGame {
+    let bytes = save_file::unwrap(bytes).unwrap();
+    let mut channel = TBufferChannel::with_capacity(bytes.len(), 0);
+    channel.set_readable_bytes(bytes);
+    let mut protocol = TCompactInputProtocol::new(TBufferedReadTransport::new(channel));
+    save::AldonGame::read_from_in_protocol(&mut protocol).unwrap()
+}
+
+fn write(game: &save::AldonGame) -> Vec<u8> {
+    let mut channel = TBufferChannel::with_capacity(0, 64 * 1024);
+    let mut protocol = TCompactOutputProtocol::new(TBufferedWriteTransport::new(&mut channel));
+    game.write_to_out_protocol(&mut protocol).unwrap();
+    protocol.flush().unwrap();
+    channel.write_bytes()
+}
+
+/// A game that has been to a few maps
+fn explored_game() -> AldonGame {
+    let mut game = common::new_game(MockDialog::new(), RaceType::ELF);
+    for (i, map_id) in [2, 3, 4, 5].into_iter().enumerate() {
+        game.update(i as f64 * 100.0);
+        game.load_map(map_id, 10.0, 10.0);
+        game.update(i as f64 * 100.0 + 50.0);
+    }
+    game
+}
+
+#[test]
+fn fog_is_a_bit_per_tile() {
+    let save = read(&explored_game().save().unwrap());
+    let fog = save.fog.as_ref().unwrap();
+    assert_eq!(fog.fog_by_map, None);
+    let bits_by_map = fog.fog_bits_by_map.as_ref().unwrap();
+    assert_eq!(bits_by_map.len(), 5);
+    assert!(bits_by_map.values().all(|bits| bits.len() == 72));
+}
+
+#[test]
+fn packed_fog_makes_saves_smaller() {
+    let save = read(&explored_game().save().unwrap());
+    let bits_by_map = save.fog.as_ref().unwrap().fog_bits_by_map.clone().unwrap();
+
+    // The same fog the way it used to be saved
+    let fog_by_map: BTreeMap<i32, Vec<bool>> = bits_by_map
+        .iter()
+        .map(|(map_id, bits)| {
+            let fogged = (0..576).map(|i| bits[i / 8] & (1 << (i % 8)) != 0);
+            (*map_id, fogged.collect())
+        })
+        .collect();
+    let old = save::AldonGame {
+        fog: Some(save::Fog::new(
+            save.fog.as_ref().unwrap().current_map,
+            fog_by_map,
+            None,
+        )),
+        ..save.clone()
+    };
+
+    let packed_len = write(&save).len();
+    let old_len = write(&old).len();
+    println!(
+        "{} maps of fog: {} bytes saved as bits, {} as bools ({:.0}% smaller)",
+        bits_by_map.len(),
+        packed_len,
+        old_len,
+        100.0 * (old_len - packed_len) as f64 / old_len as f64
+    );
+    // A byte per tile down to a bit, less some overhead
+    assert!(old_len - packed_len > bits_by_map.len() * 500);
+
+    // Loading either way ends up with the same fog
+    let load = |save: &save::AldonGame| {
+        let mut game = AldonGame::with_seed(MockDialog::new(), 2);
+        game.load_save(save_file::wrap(&write(save))).unwrap();
+        read(&game.save().unwrap()).fog
+    };
+    let old = save::AldonGame {
+        version: Some(1),
+        ..old
+    };
+    assert_eq!(load(&old), load(&save));
+}
+
+#[test]
+fn looking_past_the_top_and_left_edges() {
+    let mut game = common::new_game(MockDialog::new(), RaceType::ELF);
+    for (i, (x, y)) in [(0.0, 0.0), (0.0, 10.0), (10.0, 0.0)]
+        .into_iter()
+        .enumerate()
+    {
+        game.update(i as f64 * 100.0);
+        game.load_map(2, x, y);
+        game.update(i as f64 * 100.0 + 50.0);
+        assert_eq!(game.player_position(), Some((x, y)));
+    }
+
+    // The corner the player stood on is explored
+    let save = read(&game.save().unwrap());
+    let bits = &save.fog.as_ref().unwrap().fog_bits_by_map.as_ref().unwrap()[&2];
+    assert_eq!(bits[0] & 1, 0);
+}
@@ -0,0 +1,123 @@
+//! Starting new games with a hero from another game
+mod common;
+
+use aldonlib::{
+    data::World,
+    game::AldonGame,
+    replay,
+    thrift::save::{ClassType, RaceType},
+};
+use common::MockDialog;
+
+/// A fighter with experience, gold and a dog, out on another map
+fn seasoned_game() -> AldonGame {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::DWARF);
+    let player = game.player().unwrap();
+    player.give_gold(500);
+    player.give_exp(1234);
+    assert!(common::buy(&mut game, &dialog, 14)); // Fighters' Guild
+    assert!(common::buy(&mut game, &dialog, 61)); // dog
+    game.update(1000.0);
+    game.load_map(2, 10.0, 10.0);
+    game.update(2000.0);
+    game
+}
+
+#[test]
+fn hero_keeps_their_character() {
+    let game = seasoned_game();
+    let hero = game.export_hero().unwrap();
+    let original = game.player().unwrap();
+
+    let mut new_game = AldonGame::with_seed(MockDialog::new(), 5);
+    new_game.update(100.0);
+    new_game.new_game_with_hero(hero).unwrap();
+    assert!(new_game.loaded());
+
+    // Back at the start
+    assert_eq!(new_game.map_id(), 1);
+    assert_eq!(new_game.player_position(), Some((12.0, 3.0)));
+
+    let player = new_game.player().unwrap();
+    assert_eq!(player.name, original.name);
+    assert_eq!(player.race.get(), Some(RaceType::DWARF));
+    assert_eq!(player.class.get(), ClassType::FIGHTER);
+    assert_eq!(player.level(), original.level());
+    assert_eq!(player.exp(), original.exp());
+    assert_eq!(player.gold.get(), original.gold.get());
+    assert_eq!(player.pet().unwrap().name, original.pet().unwrap().name);
+
+    // And the game plays and saves like any other
+    new_game.update(200.0);
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    loaded.load_save(new_game.save().unwrap()).unwrap();
+    assert_eq!(loaded.player().unwrap().gold.get(), original.gold.get());
+}
+
+#[test]
+fn hero_leaves_the_world_behind() {
+    let game = seasoned_game();
+    let hero = game.export_hero().unwrap();
+    assert!(hero.len() < game.save().unwrap().len() / 2);
+
+    // Not a whole game
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    assert!(loaded.load_save(hero).is_err());
+    assert!(!loaded.loaded());
+}
+
+#[test]
+fn transfer_hero_to_another_world() {
+    let game = seasoned_game();
+    let fathers_day = World::built_in("fathers_day").unwrap();
+    let mut new_game = AldonGame::with_world(MockDialog::new(), 3, fathers_day);
+    new_game
+        .new_game_with_hero(game.export_hero().unwrap())
+        .unwrap();
+    assert_eq!(new_game.world().id, "fathers_day");
+    assert_eq!(
+        new_game.player().unwrap().level(),
+        game.player().unwrap().level()
+    );
+
+    new_game.update(100.0);
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    loaded.load_save(new_game.save().unwrap()).unwrap();
+    assert_eq!(loaded.world().id, "fathers_day");
+}
+
+#[test]
+fn new_game_plus_from_save_code() {
+    let game = seasoned_game();
+    let code = game.export_save().unwrap();
+    let mut new_game = AldonGame::with_seed(MockDialog::new(), 5);
+    new_game.new_game_with_hero(code.into_bytes()).unwrap();
+    assert_eq!(new_game.map_id(), 1);
+    assert_eq!(
+        new_game.player().unwrap().gold.get(),
+        game.player().unwrap().gold.get()
+    );
+}
+
+#[test]
+fn bad_hero_leaves_game_alone() {
+    let mut game = seasoned_game();
+    let mut hero = game.export_hero().unwrap();
+    hero.truncate(hero.len() - 5);
+    assert!(game.new_game_with_hero(hero).is_err());
+    assert!(game.loaded());
+    assert_eq!(game.map_id(), 2);
+}
+
+#[test]
+fn hero_games_replay() {
+    let hero = seasoned_game().export_hero().unwrap();
+    let mut game = AldonGame::with_seed(MockDialog::new(), 5);
+    game.start_recording().unwrap();
+    game.update(100.0);
+    game.new_game_with_hero(hero).unwrap();
+    game.update(500.0);
+    let recorded = game.stop_recording().unwrap();
+    assert!(replay::play(MockDialog::new(), &recorded).is_ok());
+}
@@ -0,0 +1,189 @@
+//! Weapons that do more than damage on a hit or kill
+mod common;
+
+use aldonlib::{
+    body::Body,
+    data::{DamageType, EffectRes, ProcAction, ProcTrigger, PROPS},
+    events::{BattleEventType, GameEvent},
+    game::AldonGame,
+    thrift::save::{ConditionType, RaceType},
+};
+use common::MockDialog;
+use std::rc::Rc;
+
+const DOG: u16 = 61;
+const ASSASSINS_DAGGER: u16 = 121;
+const BLOOD_BLADE: u16 = 248;
+const STORM_HAMMER: u16 = 249;
+const STORM_SWORD: u16 = 390;
+
+/// The player's dog, armed with `weapon`, fights the bandits on map 19 until `done`. Neither of
+/// them can die.
+fn dog_fights(weapon: u16, done: impl Fn(&GameEvent) -> bool) -> (AldonGame, Vec<GameEvent>) {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+    assert!(common::buy(&mut game, &dialog, DOG));
+    game.load_map(19, 6.0, 5.0);
+    let dog = player.pet().unwrap();
+    let item = Rc::new(Body::new("weapon".to_string(), None, weapon, 0.0, 0.0));
+    dog.force_equip(game.now(), item);
+
+    let mut events = Vec::new();
+    let mut now = 0.0;
+    while now < 120000.0 {
+        now += 50.0;
+        game.update(now);
+        player.set_health_no_max(10000);
+        dog.set_health_no_max(10000);
+        events.extend(game.drain_events());
+        if events.iter().any(&done) {
+            break;
+        }
+    }
+    (game, events)
+}
+
+#[test]
+fn dagger_poisons_on_hit() {
+    let (game, events) = dog_fights(ASSASSINS_DAGGER, |event| {
+        matches!(
+            event,
+            GameEvent::ConditionGained {
+                condition: ConditionType::POISON,
+                ..
+            }
+        )
+    });
+    let poisoned = events.iter().find_map(|event| match event {
+        GameEvent::ConditionGained { body, condition } if *condition == ConditionType::POISON => {
+            Some(body)
+        }
+        _ => None,
+    });
+    assert_eq!(poisoned.unwrap().name, "bandit");
+    assert!(events.iter().any(|event| matches!(
+        event,
+        GameEvent::Proc { body, prop_id: ASSASSINS_DAGGER } if !body.is_player()
+    )));
+    assert!(game
+        .console()
+        .lines()
+        .iter()
+        .any(|line| line.ends_with("Assasins' Dagger flares!*")));
+}
+
+#[test]
+fn always_procs_flare_on_every_hit() {
+    let (game, events) = dog_fights(
+        BLOOD_BLADE,
+        |event| matches!(event, GameEvent::Kill { target, .. } if target.name == "bandit"),
+    );
+    let dog = game.player().unwrap().pet().unwrap();
+    let procs = events
+        .iter()
+        .filter(|event| matches!(event, GameEvent::Proc { .. }))
+        .collect::<Vec<_>>();
+    let hits = events
+        .iter()
+        .filter(|event| {
+            matches!(
+                event,
+                GameEvent::Damage {
+                    attacker: Some(attacker),
+                    kind: BattleEventType::Hit | BattleEventType::Crit,
+                    ..
+                } if attacker.name == dog.name
+            )
+        })
+        .count();
+    assert!(hits > 0);
+    assert_eq!(procs.len(), hits);
+    assert!(procs.iter().all(|event| matches!(
+        event,
+        GameEvent::Proc { body, prop_id: BLOOD_BLADE } if body.name == dog.name
+    )));
+    assert!(game
+        .console()
+        .lines()
+        .iter()
+        .any(|line| line.ends_with("Blood Blade flares!*")));
+}
+
+/// The storm weapons' explosion on a kill, which ordinary hammer blows never reach
+fn is_explosion(event: &GameEvent) -> bool {
+    matches!(
+        event,
+        GameEvent::Damage {
+            kind: BattleEventType::Hit,
+            damage_type: DamageType::Fire,
+            amount: 21,
+            ..
+        }
+    )
+}
+
+#[test]
+fn storm_weapons_explode_on_kills() {
+    let (_, events) = dog_fights(STORM_HAMMER, is_explosion);
+    let kill = events.iter().position(
+        |event| matches!(event, GameEvent::Kill { target, .. } if target.name == "bandit"),
+    );
+    let explosion = events.iter().position(is_explosion);
+    assert!(kill.unwrap() < explosion.unwrap());
+
+    // The sword explodes the same way, with a chance of fireballs on top
+    let kill_procs = |weapon: u16| -> Vec<EffectRes> {
+        PROPS[&weapon.to_string()]
+            .effects
+            .iter()
+            .filter(|effect| {
+                matches!(
+                    effect,
+                    EffectRes::Proc {
+                        trigger: ProcTrigger::Kill,
+                        ..
+                    }
+                )
+            })
+            .cloned()
+            .collect()
+    };
+    assert_eq!(kill_procs(STORM_SWORD), kill_procs(STORM_HAMMER));
+}
+
+#[test]
+fn procs_parse_from_json() {
+    let effect: EffectRes = serde_json::from_str(
+        r#"{
+            "effect": "proc",
+            "trigger": "kill",
+            "always": true,
+            "action": {"type": "cast_spell", "spell_id": 48}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        effect,
+        EffectRes::Proc {
+            trigger: ProcTrigger::Kill,
+            chance: 0,
+            always: true,
+            action: ProcAction::CastSpell { spell_id: 48 }
+        }
+    );
+    let effect: EffectRes = serde_json::from_str(
+        r#"{"effect": "proc", "trigger": "hit", "chance": 15, "action": {"type": "poison"}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        effect,
+        EffectRes::Proc {
+            trigger: ProcTrigger::Hit,
+            chance: 15,
+            always: false,
+            action: ProcAction::Poison { random: false }
+        }
+    );
+}
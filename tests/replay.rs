@@ -0,0 +1,76 @@
+//! Recording a game and playing it back
+mod common;
+
+use aldonlib::{
+    game::AldonGame,
+    replay::{self, Replay},
+    thrift::save::RaceType,
+};
+use common::MockDialog;
+
+/// Records a minute of walking around at 60 fps
+fn recorded_game() -> (AldonGame, Replay) {
+    let mut game = AldonGame::with_seed(MockDialog::new(), 11);
+    game.start_recording().unwrap();
+    game.new_game(
+        "Test".to_string(),
+        RaceType::ELF,
+        600,
+        10,
+        10,
+        10,
+        10,
+        10,
+        10,
+    );
+    game.update(0.0);
+    game.load_map(2, 10.0, 10.0);
+    for frame in 1..=3600 {
+        if frame % 600 == 0 {
+            game.input_stage(8.5 + (frame / 600) as f64, 12.5);
+        }
+        game.update(frame as f64 * 1000.0 / 60.0);
+    }
+    game.save().unwrap();
+    let recorded = game.stop_recording().unwrap();
+    (game, recorded)
+}
+
+#[test]
+fn recording_plays_back() {
+    let (game, recorded) = recorded_game();
+    let bytes = recorded.to_bytes();
+    let loaded = Replay::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded.inputs.len(), recorded.inputs.len());
+
+    let played = replay::play(MockDialog::new(), &loaded).unwrap();
+    assert!(replay::check_end_state(&played, &loaded).is_ok());
+    assert_eq!(played.player_position(), game.player_position());
+}
+
+#[test]
+fn recordings_are_compact() {
+    let (_, recorded) = recorded_game();
+    let bytes = recorded.to_bytes();
+    // Thousands of updates, most of the replay is the end state
+    assert!(recorded.inputs.len() > 3600);
+    assert!(
+        bytes.len() < recorded.end_state.len() + 4 * 1024,
+        "{} bytes",
+        bytes.len()
+    );
+}
+
+#[test]
+fn changed_games_fail_playback() {
+    let (_, mut recorded) = recorded_game();
+    recorded.seed += 1;
+    assert!(replay::play(MockDialog::new(), &recorded).is_err());
+}
+
+#[test]
+fn damaged_recordings_are_rejected() {
+    let (_, recorded) = recorded_game();
+    let bytes = recorded.to_bytes();
+    assert!(Replay::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+}
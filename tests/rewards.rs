@@ -0,0 +1,119 @@
+//! Experience, gold and loot for kills, from the creatures in props.json
+mod common;
+
+use aldonlib::{
+    body::Body,
+    data::{LootRes, PropTypeRes, RewardRes, PROPS},
+    events::GameEvent,
+    save_json,
+    thrift::save::RaceType,
+};
+use common::MockDialog;
+use std::rc::Rc;
+
+const DOG: u16 = 61;
+const STORM_SWORD: u16 = 390;
+const BANDIT: u16 = 114;
+const PEASANT: u16 = 83;
+const GOBLIN_SHAMAN: u16 = 319;
+const MINOR_HEAL: u16 = 19;
+
+#[test]
+fn rewards_scale_with_level() {
+    let bandit = PROPS[&BANDIT.to_string()].reward();
+    assert!(bandit.observed);
+    assert_eq!(bandit.exp(1), 32);
+    assert_eq!(bandit.exp(10), 320);
+    assert_eq!(bandit.gold(10), (1, 160));
+    assert_eq!(
+        bandit.loot,
+        [LootRes {
+            prop_id: MINOR_HEAL,
+            chance: 15
+        }]
+    );
+
+    // Notes on what the original game gave are kept with the reward
+    let shaman = PROPS[&GOBLIN_SHAMAN.to_string()].reward();
+    assert_eq!(shaman.note.as_deref(), Some("lvl 1 gp: 63"));
+
+    // Creatures without a reward still give a little
+    let peasant = PROPS[&PEASANT.to_string()].reward();
+    assert_eq!(peasant.exp(3), 6);
+    assert_eq!(peasant.gold(3), (1, 3));
+}
+
+#[test]
+fn gold_range_from_json() {
+    let reward: RewardRes = serde_json::from_str(r#"{"exp": 30, "gold": [5, 10]}"#).unwrap();
+    assert_eq!(reward.gold(2), (10, 20));
+    assert!(!reward.observed);
+    assert!(reward.loot.is_empty());
+}
+
+#[test]
+fn built_in_rewards_are_valid() {
+    for (prop_id, prop) in PROPS.iter() {
+        let (PropTypeRes::Creature {
+            reward: Some(reward),
+            ..
+        }
+        | PropTypeRes::User {
+            reward: Some(reward),
+            ..
+        }) = &prop.kind
+        else {
+            continue;
+        };
+        assert!(reward.exp > 0, "{prop_id}");
+        let (gold_min, gold_max) = reward.gold(1);
+        assert!(0 < gold_min && gold_min <= gold_max, "{prop_id}");
+        for loot in &reward.loot {
+            assert!(PROPS.contains_key(&loot.prop_id.to_string()), "{prop_id}");
+            assert!((1..=100).contains(&loot.chance), "{prop_id}");
+        }
+    }
+}
+
+#[test]
+fn kills_drop_loot() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    let player = game.player().unwrap();
+    player.give_gold(100);
+    assert!(common::buy(&mut game, &dialog, DOG));
+    game.load_map(19, 6.0, 5.0);
+
+    // A dog that can take on the bandits
+    let dog = player.pet().unwrap();
+    let sword = Rc::new(Body::new("sword".to_string(), None, STORM_SWORD, 0.0, 0.0));
+    dog.force_equip(game.now(), sword);
+    dog.set_level(20);
+
+    let mut kills = 0;
+    let mut now = 0.0;
+    while now < 1200000.0 {
+        now += 50.0;
+        game.update(now);
+        player.set_health_no_max(10000);
+        dog.set_health_no_max(10000);
+        for event in game.drain_events() {
+            if let GameEvent::Kill { target, exp, .. } = event {
+                assert_eq!(target.prop_id, BANDIT);
+                // Split with the dog
+                assert_eq!(exp, 32 * 10 / 2);
+                kills += 1;
+            }
+        }
+    }
+
+    let save = save_json::to_json(&game.save().unwrap()).unwrap();
+    let dropped = save["save"]["stage"]["bodies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|body| body["prop_id"] == MINOR_HEAL)
+        .count();
+    assert!(kills > 10);
+    assert!(0 < dropped && dropped < kills);
+}
@@ -0,0 +1,97 @@
+//! Moving saves between devices as text codes
+mod common;
+
+use aldonlib::{game::AldonGame, save_code, thrift::save::RaceType};
+use base64::{engine::general_purpose, Engine as _};
+use common::MockDialog;
+
+fn exported_game() -> (AldonGame, String) {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::HUMAN);
+    game.player().unwrap().give_gold(321);
+    assert!(common::buy(&mut game, &dialog, 61)); // dog
+    game.update(1000.0);
+    let code = game.export_save().unwrap();
+    (game, code)
+}
+
+fn import(code: &str) -> Result<AldonGame, String> {
+    let mut game = AldonGame::with_seed(MockDialog::new(), 2);
+    game.load_save(code.as_bytes().to_vec())
+        .map_err(|err| err.to_string())?;
+    Ok(game)
+}
+
+#[test]
+fn export_and_import() {
+    let (game, code) = exported_game();
+    assert!(code.starts_with("aldn1:"));
+    // Safe to put in a url or a file name
+    assert!(code["aldn1:".len()..]
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+    let loaded = import(&code).unwrap();
+    assert!(loaded.loaded());
+    assert_eq!(loaded.map_id(), game.map_id());
+    assert_eq!(loaded.player_position(), game.player_position());
+    let player = loaded.player().unwrap();
+    assert_eq!(player.name, game.player().unwrap().name);
+    assert_eq!(player.gold.get(), game.player().unwrap().gold.get());
+    assert!(player.pet().is_some());
+
+    // Pasting tends to pick up whitespace
+    assert!(import(&format!("\n  {}\n", code)).is_ok());
+}
+
+#[test]
+fn codes_are_compressed() {
+    let (game, code) = exported_game();
+    let save = game.save().unwrap();
+    let plain = general_purpose::URL_SAFE_NO_PAD.encode(&save);
+    println!(
+        "save code: {} characters, plain base64: {}",
+        code.len(),
+        plain.len()
+    );
+    assert!(code.len() < plain.len() / 2);
+}
+
+#[test]
+fn chunks_in_any_order() {
+    let (game, code) = exported_game();
+    let chunks = save_code::chunks(&code, 300);
+    assert!(chunks.len() > 1);
+    assert!(chunks.iter().all(|chunk| chunk.len() <= 300));
+    assert!(chunks[0].starts_with(&format!("aldn1:1/{}:", chunks.len())));
+
+    let mut shuffled = chunks.clone();
+    shuffled.reverse();
+    let loaded = import(&shuffled.join("\n")).unwrap();
+    assert_eq!(loaded.player_position(), game.player_position());
+
+    let err = import(&chunks[1..].join(" ")).err().unwrap();
+    assert!(err.contains("missing chunk 1"), "{}", err);
+}
+
+#[test]
+fn damaged_codes_are_rejected() {
+    let (_, code) = exported_game();
+
+    let err = import(&code[..code.len() - 20]).err().unwrap();
+    assert!(err.contains("save code"), "{}", err);
+
+    // A mistyped character
+    let mut chars: Vec<char> = code.chars().collect();
+    let middle = chars.len() / 2;
+    chars[middle] = if chars[middle] == 'A' { 'B' } else { 'A' };
+    let mistyped: String = chars.into_iter().collect();
+    assert!(import(&mistyped).is_err());
+
+    let err = import("aldn1:not*base64").err().unwrap();
+    assert!(err.contains("characters"), "{}", err);
+
+    // Valid deflate of something that isn't a save
+    let err = save_code::decode(&save_code::encode(b"hello")).unwrap_err();
+    assert!(err.to_string().contains("doesn't hold a save"), "{}", err);
+}
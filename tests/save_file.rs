@@ -0,0 +1,141 @@
+//! Catching damaged saves and recovering what's left of them
+mod common;
+
+use aldonlib::{game::AldonGame, save_file, thrift::save, thrift::save::RaceType};
+use common::MockDialog;
+use thrift::{
+    protocol::{TCompactInputProtocol, TCompactOutputProtocol, TOutputProtocol, TSerializable},
+    transport::{TBufferChannel, TBufferedReadTransport, TBufferedWriteTransport},
+};
+
+/// A saved game with a pet and some gold
+fn saved_game() -> Vec<u8> {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::DWARF);
+    game.player().unwrap().give_gold(500);
+    assert!(common::buy(&mut game, &dialog, 61)); // dog
+    game.update(1000.0);
+    game.save().unwrap()
+}
+
+fn load(bytes: Vec<u8>) -> Result<AldonGame, String> {
+    let mut game = AldonGame::with_seed(MockDialog::new(), 2);
+    game.load_save(bytes).map_err(|err| err.to_string())?;
+    Ok(game)
+}
+
+fn read_save(bytes: &[u8]) -> save::AldonGame {
+    let payload = save_file::unwrap(bytes).unwrap();
+    let mut channel = TBufferChannel::with_capacity(payload.len(), 0);
+    channel.set_readable_bytes(payload);
+    let mut protocol = TCompactInputProtocol::new(TBufferedReadTransport::new(channel));
+    save::AldonGame::read_from_in_protocol(&mut protocol).unwrap()
+}
+
+fn write_save(game: &save::AldonGame) -> Vec<u8> {
+    let mut channel = TBufferChannel::with_capacity(0, 100000);
+    let mut protocol = TCompactOutputProtocol::new(TBufferedWriteTransport::new(&mut channel));
+    game.write_to_out_protocol(&mut protocol).unwrap();
+    protocol.flush().unwrap();
+    save_file::wrap(&channel.write_bytes())
+}
+
+/// How many bytes of the thrift the stage takes, counted by writing a save with only a stage
+fn stage_len(bytes: &[u8]) -> usize {
+    let stage_only = save::AldonGame {
+        stage: read_save(bytes).stage,
+        ..Default::default()
+    };
+    let mut channel = TBufferChannel::with_capacity(0, bytes.len());
+    let mut protocol = TCompactOutputProtocol::new(TBufferedWriteTransport::new(&mut channel));
+    stage_only.write_to_out_protocol(&mut protocol).unwrap();
+    protocol.flush().unwrap();
+    // Less the byte ending the AldonGame
+    channel.write_bytes().len() - 1
+}
+
+#[test]
+fn saves_are_wrapped() {
+    let bytes = saved_game();
+    assert!(save_file::is_wrapped(&bytes));
+    assert!(save_file::unwrap(&bytes).is_ok());
+    assert!(load(bytes).is_ok());
+}
+
+#[test]
+fn damage_is_detected() {
+    let bytes = saved_game();
+
+    let truncated = bytes[..bytes.len() - 10].to_vec();
+    let err = load(truncated).err().unwrap();
+    assert!(err.contains("truncated"), "{}", err);
+
+    let mut flipped = bytes.clone();
+    let middle = flipped.len() / 2;
+    flipped[middle] ^= 0x10;
+    let err = load(flipped).err().unwrap();
+    assert!(err.contains("checksum"), "{}", err);
+
+    let mut extended = bytes.clone();
+    extended.push(0);
+    assert!(load(extended).is_err());
+}
+
+#[test]
+fn recover_player_from_damaged_save() {
+    let bytes = saved_game();
+    let original = load(bytes.clone()).unwrap();
+
+    // Cut off a few bytes into the cast, losing everything after the stage
+    let cut = 13 + stage_len(&bytes) + 5;
+    let damaged = bytes[..cut].to_vec();
+    assert!(load(damaged.clone()).is_err());
+
+    let mut game = AldonGame::with_seed(MockDialog::new(), 2);
+    let lost = game.recover_save(damaged).unwrap();
+    assert_eq!(lost, ["cast", "fog", "buttons"]);
+    assert!(game.loaded());
+    assert_eq!(game.map_id(), original.map_id());
+    assert_eq!(game.player_position(), original.player_position());
+
+    let player = game.player().unwrap();
+    let original_player = original.player().unwrap();
+    assert_eq!(player.name, original_player.name);
+    assert_eq!(player.gold.get(), original_player.gold.get());
+    assert_eq!(
+        player.pet().unwrap().name,
+        original_player.pet().unwrap().name
+    );
+
+    // The recovered game plays on and saves
+    game.update(1050.0);
+    assert!(load(game.save().unwrap()).is_ok());
+}
+
+#[test]
+fn recovering_undamaged_save_loses_nothing() {
+    let mut game = AldonGame::with_seed(MockDialog::new(), 2);
+    assert!(game.recover_save(saved_game()).unwrap().is_empty());
+}
+
+#[test]
+fn player_is_needed_to_recover() {
+    let bytes = saved_game();
+    let mut game = AldonGame::with_seed(MockDialog::new(), 2);
+    assert!(game.recover_save(bytes[..40].to_vec()).is_err());
+    assert!(!game.loaded());
+}
+
+#[test]
+fn recovering_without_player_inventory_fails() {
+    let mut damaged = read_save(&saved_game());
+    let stage = damaged.stage.as_mut().unwrap();
+    stage.inventory_by_id.as_mut().unwrap().remove(&0);
+    let bytes = write_save(&damaged);
+
+    let mut game = AldonGame::with_seed(MockDialog::new(), 2);
+    let err = game.recover_save(bytes.clone()).unwrap_err();
+    assert_eq!(err.to_string(), "player has no inventory");
+    assert!(!game.loaded());
+    assert_eq!(load(bytes).err().unwrap(), "stage: player has no inventory");
+}
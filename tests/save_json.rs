@@ -0,0 +1,98 @@
+//! Dumping saves to JSON and packing edited JSON back into saves
+mod common;
+
+use aldonlib::{game::AldonGame, save_file, save_json, thrift::save::RaceType};
+use common::MockDialog;
+use serde_json::json;
+
+const FIXTURES: [&[u8]; 4] = [
+    include_bytes!("fixtures/save_v0.sav"),
+    include_bytes!("fixtures/save_v0_world.sav"),
+    include_bytes!("fixtures/save_v1.sav"),
+    include_bytes!("fixtures/save_v2.sav"),
+];
+
+#[test]
+fn round_trip_is_exact() {
+    for bytes in FIXTURES {
+        let value = save_json::to_json(bytes).unwrap();
+        // Packed saves are always in the current envelope
+        let packed = save_json::from_json(&value).unwrap();
+        assert_eq!(
+            save_file::unwrap(&packed).unwrap(),
+            save_file::unwrap(bytes).unwrap()
+        );
+        // The summary is optional
+        assert_eq!(save_json::from_json(&value["save"]).unwrap(), packed);
+    }
+}
+
+#[test]
+fn summary() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::DWARF);
+    game.player().unwrap().give_gold(500);
+    assert!(common::buy(&mut game, &dialog, 61)); // dog
+    game.update(1000.0);
+
+    let value = save_json::to_json(&game.save().unwrap()).unwrap();
+    let summary = &value["summary"];
+    assert_eq!(summary["world"], "aldon");
+    assert_eq!(summary["player"]["race"], "Dwarf");
+    assert_eq!(
+        summary["player"]["name"],
+        value["save"]["stage"]["player"]["name"]
+    );
+    assert!(summary["inventory"]["player"].is_array());
+    assert!(summary["inventory"]["pet"].is_array());
+    let narrator = &summary["actors"]["1"];
+    assert!(narrator.is_i64() || narrator == "dead", "{}", narrator);
+    let explored = summary["explored"][summary["map_id"].to_string()].as_str();
+    assert!(explored.unwrap().ends_with('%'));
+}
+
+#[test]
+fn edits_are_loaded() {
+    let mut value = save_json::to_json(FIXTURES[2]).unwrap();
+    value["save"]["stage"]["player"]["gold"] = json!(1234);
+    value["save"]["stage"]["player"]["klass"] = json!("Thief");
+
+    let mut game = AldonGame::with_seed(MockDialog::new(), 1);
+    game.load_save(save_json::from_json(&value).unwrap())
+        .unwrap();
+    let player = game.player().unwrap();
+    assert_eq!(player.gold.get(), 1234);
+    assert_eq!(player.class.get(), aldonlib::thrift::save::ClassType::THIEF);
+}
+
+#[test]
+fn bad_edits_are_rejected() {
+    let value = save_json::to_json(FIXTURES[2]).unwrap();
+    let cases = [
+        ("/stage/player/gld", json!(1), "Body has no field 'gld'"),
+        (
+            "/stage/player/gold",
+            json!("lots"),
+            "save.stage.player.gold: expected an i32",
+        ),
+        (
+            "/stage/player/klass",
+            json!("Wizard"),
+            "expected a ClassType",
+        ),
+        // Encodes fine but wouldn't load
+        (
+            "/stage/inventory_by_id",
+            json!({}),
+            "stage: player has no inventory",
+        ),
+        ("/stage/map_id", json!(9999), "stage: map_id not in world"),
+    ];
+    for (pointer, edit, message) in cases {
+        let mut value = value.clone();
+        let (parent, field) = pointer.rsplit_once('/').unwrap();
+        value["save"].pointer_mut(parent).unwrap()[field] = edit;
+        let err = save_json::from_json(&value).unwrap_err().to_string();
+        assert!(err.contains(message), "{}", err);
+    }
+}
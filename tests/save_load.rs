@@ -0,0 +1,61 @@
+//! Saving a game and loading it back
+mod common;
+
+use aldonlib::{
+    game::AldonGame,
+    thrift::save::{ClassType, RaceType},
+};
+use common::MockDialog;
+
+#[test]
+fn save_load_round_trip() {
+    let dialog = MockDialog::new();
+    let mut game = common::new_game(dialog.clone(), RaceType::DWARF);
+    let player = game.player().unwrap();
+    player.give_gold(500);
+    player.give_exp(1234);
+    assert!(common::buy(&mut game, &dialog, 14)); // Fighters' Guild
+    assert!(common::buy(&mut game, &dialog, 61)); // dog
+    player.set_health(3);
+    let now = 1000.0;
+    game.update(now);
+    let saved = game.player().unwrap();
+    let bytes = game.save().unwrap();
+
+    let dialog = MockDialog::new();
+    let mut loaded = AldonGame::with_seed(dialog.clone(), 2);
+    // Timers in the save are relative to when the game is loaded
+    loaded.update(now);
+    loaded.load_save(bytes.clone()).unwrap();
+    assert!(loaded.loaded());
+    assert_eq!(loaded.map_id(), game.map_id());
+    assert_eq!(loaded.player_position(), game.player_position());
+    assert_eq!(loaded.seed(), game.seed());
+
+    let player = loaded.player().unwrap();
+    assert_eq!(player.name, saved.name);
+    assert_eq!(player.race.get(), Some(RaceType::DWARF));
+    assert_eq!(player.class.get(), ClassType::FIGHTER);
+    assert_eq!(player.level(), saved.level());
+    assert_eq!(player.exp(), saved.exp());
+    assert_eq!(player.gold.get(), saved.gold.get());
+    assert_eq!(player.get_health(), saved.get_health());
+    assert_eq!(player.pet().unwrap().name, saved.pet().unwrap().name);
+
+    // The loaded game keeps going and can be saved again
+    loaded.update(now + 50.0);
+    assert!(loaded.save().is_ok());
+}
+
+#[test]
+fn cant_save_before_loading() {
+    let game = AldonGame::with_seed(MockDialog::new(), 1);
+    assert!(game.save().is_err());
+}
+
+#[test]
+fn load_garbage() {
+    let mut game = AldonGame::with_seed(MockDialog::new(), 1);
+    assert!(game.load_save(vec![1, 2, 3]).is_err());
+    assert!(!game.loaded());
+}
@@ -0,0 +1,163 @@
+//! Loading saves written by older versions of the game
+mod common;
+
+use aldonlib::{
+    data::World,
+    game::AldonGame,
+    migrate::{self, SAVE_VERSION},
+    save_file,
+    thrift::save,
+};
+use common::MockDialog;
+use std::collections::BTreeMap;
+use thrift::{
+    protocol::{TCompactInputProtocol, TSerializable},
+    transport::{TBufferChannel, TBufferedReadTransport},
+};
+
+/// Before versioning or picking worlds
+const V0: &[u8] = include_bytes!("fixtures/save_v0.sav");
+/// Before versioning, from the Father's Day world
+const V0_WORLD: &[u8] = include_bytes!("fixtures/save_v0_world.sav");
+/// Fog as a bool per tile
+const V1: &[u8] = include_bytes!("fixtures/save_v1.sav");
+/// Fog was packed into bits
+const V2: &[u8] = include_bytes!("fixtures/save_v2.sav");
+
+fn read(bytes: &[u8]) -> save::AldonGame {
+    let bytes = save_file::unwrap(bytes).unwrap();
+    let mut channel = TBufferChannel::with_capacity(bytes.len(), 0);
+    channel.set_readable_bytes(bytes);
+    let mut protocol = TCompactInputProtocol::new(TBufferedReadTransport::new(channel));
+    save::AldonGame::read_from_in_protocol(&mut protocol).unwrap()
+}
+
+fn load(bytes: &[u8]) -> AldonGame {
+    let mut game = AldonGame::with_seed(MockDialog::new(), 1);
+    game.load_save(bytes.to_vec()).unwrap();
+    assert!(game.loaded());
+    game
+}
+
+#[test]
+fn fixtures_have_their_versions() {
+    assert_eq!(read(V0).version, None);
+    assert_eq!(read(V0_WORLD).version, None);
+    assert_eq!(read(V1).version, Some(1));
+    assert_eq!(read(V2).version, Some(2));
+}
+
+#[test]
+fn every_version_loads() {
+    for (bytes, world_id) in [
+        (V0, "aldon"),
+        (V0_WORLD, "fathers_day"),
+        (V1, "aldon"),
+        (V2, "aldon"),
+    ] {
+        let mut game = load(bytes);
+        assert_eq!(game.world().id, world_id);
+        assert_eq!(game.player().unwrap().name, "Fixture");
+
+        // Saving again writes the current version
+        game.update(50.0);
+        let resaved = read(&game.save().unwrap());
+        assert_eq!(resaved.version, Some(SAVE_VERSION));
+        assert_eq!(resaved.world_id.as_deref(), Some(world_id));
+    }
+}
+
+#[test]
+fn fog_is_packed() {
+    let old_fog = read(V1).fog.unwrap();
+    let mut save = read(V1);
+    migrate::migrate(&mut save).unwrap();
+    let fog = save.fog.unwrap();
+    assert_eq!(fog.fog_by_map, None);
+
+    let old_by_map = old_fog.fog_by_map.unwrap();
+    let bits_by_map = fog.fog_bits_by_map.unwrap();
+    assert_eq!(
+        old_by_map.keys().collect::<Vec<_>>(),
+        bits_by_map.keys().collect::<Vec<_>>()
+    );
+    for (map_id, fogged) in &old_by_map {
+        let bits = &bits_by_map[map_id];
+        assert_eq!(bits.len(), 72);
+        for (i, fogged) in fogged.iter().enumerate() {
+            assert_eq!(
+                bits[i / 8] & (1 << (i % 8)) != 0,
+                *fogged,
+                "map {} tile {}",
+                map_id,
+                i
+            );
+        }
+    }
+}
+
+#[test]
+fn saves_from_newer_games_are_rejected() {
+    let mut save = read(V1);
+    save.version = Some(SAVE_VERSION + 1);
+    assert!(migrate::migrate(&mut save).is_err());
+}
+
+fn elemental_save(bye_state: i32, elemental_body: bool) -> save::AldonGame {
+    let mut actor_save_by_id = BTreeMap::new();
+    actor_save_by_id.insert(171, save::Actor::new(Some(40), None, None, Some(true)));
+    actor_save_by_id.insert(173, save::Actor::new(Some(bye_state), None, None, None));
+    let mut bodies = Vec::new();
+    if elemental_body {
+        bodies.push(Box::new(save::Body {
+            actor_id: Some(171),
+            ..Default::default()
+        }));
+    }
+    save::AldonGame {
+        stage: Some(save::Stage {
+            bodies: Some(bodies),
+            ..Default::default()
+        }),
+        cast: Some(save::Cast {
+            actor_save_by_id: Some(actor_save_by_id),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn elemental(save: &save::AldonGame) -> &save::Actor {
+    &save
+        .cast
+        .as_ref()
+        .unwrap()
+        .actor_save_by_id
+        .as_ref()
+        .unwrap()[&171]
+}
+
+#[test]
+fn elemental_revived_after_bye() {
+    let mut save = elemental_save(1, false);
+    migrate::migrate(&mut save).unwrap();
+    assert_eq!(elemental(&save).state, Some(0));
+    assert_eq!(elemental(&save).dead, Some(false));
+    assert_eq!(save.world_id.as_deref(), Some(World::BUILT_IN[0]));
+
+    // Left alone if it still has a body or bye hasn't happened
+    for mut save in [elemental_save(1, true), elemental_save(0, false)] {
+        migrate::migrate(&mut save).unwrap();
+        assert_eq!(elemental(&save).state, Some(40));
+        assert_eq!(elemental(&save).dead, Some(true));
+    }
+}
+
+#[test]
+fn current_saves_are_not_migrated_again() {
+    // A version 1 save in the elemental's broken state is taken as is
+    let mut save = elemental_save(1, false);
+    save.version = Some(1);
+    migrate::migrate(&mut save).unwrap();
+    assert_eq!(elemental(&save).state, Some(40));
+}
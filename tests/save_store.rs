@@ -0,0 +1,148 @@
+//! Save slots, backups and autosaves
+mod common;
+
+use aldonlib::{
+    game::AldonGame,
+    save_store::{FileSaveStore, MemorySaveStore, SaveStore, SlotInfo, AUTOSAVE_SLOT, BACKUPS},
+    thrift::save::RaceType,
+};
+use common::MockDialog;
+use std::{env, fs, process, rc::Rc};
+
+fn game_with_store(store: Rc<dyn SaveStore>) -> AldonGame {
+    let mut game = common::new_game(MockDialog::new(), RaceType::ELF);
+    game.set_save_store(store);
+    game
+}
+
+#[test]
+fn slots_have_metadata() {
+    let store = Rc::new(MemorySaveStore::new());
+    let mut game = game_with_store(store.clone());
+    game.update(5000.0);
+    game.save_slot("before the bridge").unwrap();
+
+    let slots = game.save_slots();
+    assert_eq!(slots.len(), 1);
+    let info = &slots[0];
+    assert_eq!(info.slot, "before the bridge");
+    assert_eq!(info.player_name, "Test");
+    assert_eq!(info.class, "Journeyman");
+    assert_eq!(info.level, 1);
+    assert!(!info.map_name.is_empty());
+    assert_eq!(info.play_time, 5000.0);
+    assert!(info.timestamp > 0.0);
+
+    game.delete_slot("before the bridge");
+    assert!(game.save_slots().is_empty());
+}
+
+#[test]
+fn play_time_is_saved() {
+    let store = Rc::new(MemorySaveStore::new());
+    let mut game = game_with_store(store.clone());
+    game.update(2000.0);
+    game.save_slot("one").unwrap();
+
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    loaded.set_save_store(store);
+    loaded.update(10000.0);
+    loaded.load_slot("one").unwrap();
+    assert_eq!(loaded.play_time(), 2000.0);
+    loaded.update(10500.0);
+    assert_eq!(loaded.play_time(), 2500.0);
+}
+
+#[test]
+fn old_saves_are_rotated_out() {
+    let store = Rc::new(MemorySaveStore::new());
+    let mut game = game_with_store(store.clone());
+    for i in 1..=5 {
+        game.update(i as f64 * 100.0);
+        game.save_slot("one").unwrap();
+    }
+    assert_eq!(store.keys().len(), BACKUPS + 1);
+    let saves = store.load_slot("one");
+    let play_times: Vec<f64> = saves.iter().map(|(info, _)| info.play_time).collect();
+    assert_eq!(play_times, [500.0, 400.0, 300.0]);
+}
+
+#[test]
+fn damaged_saves_fall_back_to_backups() {
+    let store = Rc::new(MemorySaveStore::new());
+    let mut game = game_with_store(store.clone());
+    game.update(100.0);
+    game.save_slot("one").unwrap();
+
+    // A save that decodes but won't load, then a record cut off while being written
+    let info = SlotInfo {
+        slot: "one".to_string(),
+        player_name: "Broken".to_string(),
+        class: String::new(),
+        level: 1,
+        map_name: String::new(),
+        play_time: 200.0,
+        timestamp: 0.0,
+    };
+    store.save_slot(info, &[1, 2, 3]).unwrap();
+    store.write("one.2.sav", &[0, 1]).unwrap();
+    assert_eq!(store.load_slot("one").len(), 2);
+
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    loaded.set_save_store(store.clone());
+    loaded.load_slot("one").unwrap();
+    assert_eq!(loaded.play_time(), 100.0);
+
+    assert!(loaded.load_slot("two").is_err());
+}
+
+#[test]
+fn slot_names_are_checked() {
+    let game = game_with_store(Rc::new(MemorySaveStore::new()));
+    assert!(game.save_slot("../etc").is_err());
+    assert!(game.save_slot("").is_err());
+}
+
+#[test]
+fn autosave_on_map_change() {
+    let store = Rc::new(MemorySaveStore::new());
+    let mut game = game_with_store(store.clone());
+    let mut plain = common::new_game(MockDialog::new(), RaceType::ELF);
+    assert!(game.save_slots().is_empty());
+
+    for game in [&mut game, &mut plain] {
+        game.update(50.0);
+        game.load_map(2, 10.0, 10.0);
+        game.update(100.0);
+    }
+    let slots = game.save_slots();
+    assert_eq!(slots.len(), 1);
+    assert_eq!(slots[0].slot, AUTOSAVE_SLOT);
+
+    // Autosaving doesn't change the game
+    assert_eq!(game.save().unwrap(), plain.save().unwrap());
+
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    loaded.set_save_store(store);
+    loaded.load_slot(AUTOSAVE_SLOT).unwrap();
+    assert_eq!(loaded.map_id(), 2);
+}
+
+#[test]
+fn file_store() {
+    let dir = env::temp_dir().join(format!("aldon-save-store-{}", process::id()));
+    let store = Rc::new(FileSaveStore::new(&dir).unwrap());
+    let mut game = game_with_store(store.clone());
+    game.update(100.0);
+    game.save_slot("one").unwrap();
+    game.save_slot("two").unwrap();
+
+    // A fresh store over the same directory sees the saves
+    let store = Rc::new(FileSaveStore::new(&dir).unwrap());
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    loaded.set_save_store(store);
+    assert_eq!(loaded.save_slots().len(), 2);
+    loaded.load_slot("one").unwrap();
+    assert!(loaded.loaded());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,100 @@
+//! Checking world data for broken actors and references
+use aldonlib::{bytecode, data::WorldRes, data::WORLD, validate};
+
+/// A world with one map holding an actor running `program`
+fn world(program: &str) -> WorldRes {
+    let actions = bytecode::assemble(program).unwrap();
+    let json = format!(
+        r#"{{
+            "maps": {{
+                "1": {{
+                    "name": "test", "north": 2, "south": null, "east": null, "west": 1,
+                    "tiles": "",
+                    "props": [{{ "id": 9999, "x": 3, "y": 4 }}],
+                    "teleports": [{{ "id": 3, "from_x": 1.0, "from_y": 2.0, "to_x": 0.0, "to_y": 0.0 }}],
+                    "spawners": [],
+                    "actors": [
+                        {{ "id": 7, "x": 0.0, "y": 0.0, "health": 0.0, "name": "Test",
+                           "bmp_offset": 0, "is_mapped": false, "actions": "{}" }}
+                    ]
+                }}
+            }},
+            "messages": {{ "1": {{ "value": "hi", "choice_a": null, "choice_b": null, "choice_c": null }} }}
+        }}"#,
+        actions
+    );
+    serde_json::from_str(&json).unwrap()
+}
+
+fn problems(world: &WorldRes) -> Vec<String> {
+    validate::validate(world)
+        .iter()
+        .map(|problem| problem.to_string())
+        .collect()
+}
+
+#[test]
+fn map_references() {
+    let problems = problems(&world("000=TELLMESSAGE 1"));
+    assert_eq!(
+        problems,
+        [
+            "map 1: north neighbour goes to unknown map 2",
+            "map 1: teleport at (1, 2) goes to unknown map 3",
+            "map 1: prop placed at (3, 4) uses unknown prop 9999",
+        ]
+    );
+}
+
+#[test]
+fn actor_problems() {
+    let world = world(
+        "
+        000=TELLMESSAGE 2
+        000=CREATESELF 9999
+        005=WAIT 10 25 0 768 0 // response
+        010=IF 0 0 0 2560 20   // unknown condition 10
+        010=SETVAR 65280       // var 255
+        020=SETSTATE 7 15
+        030=MAPSETLOC 4 0
+        ",
+    );
+    let problems: Vec<String> = problems(&world).into_iter().skip(3).collect();
+    assert_eq!(
+        problems,
+        [
+            "map 1 actor 7 state 000: TELLMESSAGE uses unknown message 2",
+            "map 1 actor 7 state 000: CREATESELF uses unknown prop 9999",
+            "map 1 actor 7 state 005: WAIT RESPONSE choice B jumps to missing state 025",
+            "map 1 actor 7 state 010: IF with unknown condition type 10",
+            "map 1 actor 7 state 010: SETVAR uses var 255, there are only 255",
+            "map 1 actor 7 state 020: SETSTATE of actor 7 jumps to missing state 015",
+            "map 1 actor 7 state 030: MAPSETLOC goes to unknown map 4",
+        ]
+    );
+}
+
+#[test]
+fn jumping_past_the_end_stops_the_actor() {
+    let world = world("000=TELLMESSAGE 1\n010=IF 0 0 0 512 99");
+    assert_eq!(problems(&world).len(), 3); // just the map problems
+}
+
+#[test]
+fn bad_byte_code() {
+    let mut world = world("000=TELLMESSAGE 1");
+    world.maps.get_mut("1").unwrap().actors[0].actions = Some("AAD//w==".to_string());
+    let problems = problems(&world);
+    assert_eq!(
+        problems[3],
+        "map 1 actor 7: byte code doesn't decode: unknown action 0xffff"
+    );
+}
+
+#[test]
+fn shipped_world() {
+    assert_eq!(
+        problems(&WORLD),
+        ["map 117 actor 257 state 110: WAIT RESPONSE choice A jumps to missing state 120"]
+    );
+}
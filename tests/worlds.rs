@@ -0,0 +1,108 @@
+//! Playing in worlds other than the default one
+mod common;
+
+use aldonlib::{data::World, game::AldonGame, thrift::save::RaceType};
+use common::MockDialog;
+
+const CUSTOM_JSON: &[u8] = include_bytes!("../src/data/maps.json");
+
+#[test]
+fn built_in_worlds() {
+    for id in World::BUILT_IN {
+        let world = World::built_in(id).unwrap();
+        assert_eq!(world.id, id);
+        assert!(world.res.maps.contains_key("1"));
+    }
+    assert!(World::built_in("nope").is_none());
+    assert_eq!(World::default().id, "aldon");
+}
+
+#[test]
+fn save_restores_the_world() {
+    let fathers_day = World::built_in("fathers_day").unwrap();
+    let mut game = common::new_game_in(MockDialog::new(), RaceType::ELF, fathers_day);
+    assert_eq!(game.world().id, "fathers_day");
+    game.update(1000.0);
+    let bytes = game.save().unwrap();
+
+    // Loading into a game in the default world switches to the save's world
+    let mut loaded = AldonGame::with_seed(MockDialog::new(), 2);
+    loaded.update(1000.0);
+    loaded.load_save(bytes).unwrap();
+    assert_eq!(loaded.world().id, "fathers_day");
+    assert_eq!(loaded.map_id(), game.map_id());
+
+    // A new game keeps playing in the same world
+    loaded.new_game(
+        "Again".to_string(),
+        RaceType::ELF,
+        600,
+        10,
+        10,
+        10,
+        10,
+        10,
+        10,
+    );
+    assert_eq!(loaded.world().id, "fathers_day");
+}
+
+#[test]
+fn custom_world() {
+    let world = World::from_json("custom", CUSTOM_JSON).unwrap();
+    let mut game = common::new_game_in(MockDialog::new(), RaceType::HUMAN, world);
+    game.update(1000.0);
+    let bytes = game.save().unwrap();
+
+    // The custom world has to be loaded before its saves
+    let mut other = AldonGame::with_seed(MockDialog::new(), 2);
+    let err = other.load_save(bytes.clone()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "save is from world 'custom' which isn't loaded"
+    );
+    assert!(!other.loaded());
+
+    let mut same = AldonGame::with_world(MockDialog::new(), 2, world);
+    same.update(1000.0);
+    same.load_save(bytes).unwrap();
+    assert_eq!(same.world().id, "custom");
+}
+
+#[test]
+fn bad_custom_worlds() {
+    assert!(World::from_json("aldon", CUSTOM_JSON).is_err());
+    assert!(World::from_json("custom", b"not json").is_err());
+    let err = World::from_json("custom", br#"{"maps": {}, "messages": {}}"#).unwrap_err();
+    assert_eq!(err.to_string(), "world has no map 1");
+}
+
+#[test]
+fn custom_worlds_with_missing_maps() {
+    let mut json: serde_json::Value = serde_json::from_slice(CUSTOM_JSON).unwrap();
+    json["maps"]["1"]["north"] = 9999.into();
+    let json = serde_json::to_vec(&json).unwrap();
+    let err = World::from_json("custom", &json).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "bad world: map 1: north neighbour goes to unknown map 9999"
+    );
+}
+
+#[test]
+fn custom_worlds_load_once() {
+    let world = World::from_json("once", CUSTOM_JSON).unwrap();
+    let again = World::from_json("once", CUSTOM_JSON).unwrap();
+    assert!(std::ptr::eq(world.id, again.id));
+    assert!(std::ptr::eq(world.res, again.res));
+
+    let mut json: serde_json::Value = serde_json::from_slice(CUSTOM_JSON).unwrap();
+    json["maps"]["1"]["name"] = "CHANGED".into();
+    let json = serde_json::to_vec(&json).unwrap();
+    let err = World::from_json("once", &json).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "a different world 'once' is already loaded"
+    );
+    assert!(World::from_json("twice", &json).is_ok());
+}
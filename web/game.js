@@ -1,5 +1,8 @@
 // Setup for the game
-import init, { AldonHtmlCanvasGame, aldon_debug_logs } from "./pkg/aldonlib.js";
+import init, {
+  AldonHtmlCanvasGame,
+  recent_debug_logs,
+} from "./pkg/aldonlib.js";
 import "./menu.js";
 import { Dialog } from "./dialog.js";
 
@@ -224,7 +227,17 @@ class Game {
   }
 
   getLogs() {
-    return aldon_debug_logs();
+    let logs = "";
+    try {
+      logs = this.game.debug_logs();
+    } catch (e) {
+      // The game can't be called into after a panic, its logs are mirrored outside of it
+      logs = recent_debug_logs();
+    }
+    if (this.panicMessage) {
+      logs += "\n" + this.panicMessage;
+    }
+    return logs;
   }
 
   getScale() {
@@ -432,7 +445,10 @@ class AldonGame extends HTMLElement {
   }
 
   async connectedCallback() {
-    window.js_panic = () => this.showUnrecoverableError();
+    window.js_panic = (msg) => {
+      this.game.panicMessage = msg;
+      this.showUnrecoverableError();
+    };
 
     window.requestAnimationFrame((now) => this.updateLoop(now));
     const menu = this.shadowRoot.querySelector("aldon-menu");